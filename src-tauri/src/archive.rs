@@ -0,0 +1,557 @@
+//! Archives as virtual directories
+//!
+//! Files inside a zip or tar(.gz) archive are addressed with a virtual path of
+//! the form `<archive path>!/<inner path>`. Single gzipped files such as
+//! `TestA_ID_1---0.html.gz` are decompressed on the fly. Every source is
+//! opened as a stream; only an import's [`ArchiveCache`] unpacks members of
+//! compressed tar archives, to a temporary directory.
+
+use flate2::read::{DeflateDecoder, GzDecoder};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use zip::{CompressionMethod, ZipArchive};
+
+/// Separates the archive path from the member path in a virtual path
+pub const ARCHIVE_SEPARATOR: &str = "!/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Archive type implied by the file extension, if any
+    pub fn from_path(path: &str) -> Option<Self> {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if lower.ends_with(".tar") {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// A file found inside an archive
+#[derive(Debug, Clone)]
+pub struct ArchiveMember {
+    /// Virtual path, see [`virtual_path`]
+    pub path: String,
+    /// First bytes of the (decompressed) content, for format detection
+    pub head: Vec<u8>,
+}
+
+/// Build the virtual path of an archive member
+pub fn virtual_path(archive_path: &str, inner_path: &str) -> String {
+    format!("{}{}{}", archive_path, ARCHIVE_SEPARATOR, inner_path)
+}
+
+/// Split a virtual path into archive path and member path
+///
+/// # Examples
+/// ```
+/// use logterminator_lib::archive::split_virtual_path;
+/// assert_eq!(
+///     split_virtual_path("/ci/logs.tar.gz!/run/TestA_ID_1---0.html"),
+///     Some(("/ci/logs.tar.gz", "run/TestA_ID_1---0.html"))
+/// );
+/// assert_eq!(split_virtual_path("/ci/TestA_ID_1---0.html"), None);
+/// ```
+pub fn split_virtual_path(path: &str) -> Option<(&str, &str)> {
+    let (archive, inner) = path.split_once(ARCHIVE_SEPARATOR)?;
+    ArchiveKind::from_path(archive).map(|_| (archive, inner))
+}
+
+/// Virtual directory holding a source, e.g. `logs.zip!/run` for
+/// `logs.zip!/run/TestA_ID_1---0.html`; `None` for ordinary files
+pub fn source_directory(path: &str) -> Option<String> {
+    let (archive, inner) = split_virtual_path(path)?;
+    let parent = inner.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+    Some(virtual_path(archive, parent))
+}
+
+/// File name used for format detection: the last path component with a
+/// trailing `.gz` removed
+pub fn logical_file_name(path: &str) -> &str {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    strip_gz(name)
+}
+
+/// `path` without a trailing `.gz`
+pub fn strip_gz(name: &str) -> &str {
+    if name.len() > 3 && name[name.len() - 3..].eq_ignore_ascii_case(".gz") {
+        &name[..name.len() - 3]
+    } else {
+        name
+    }
+}
+
+/// Wrap `reader` in a gzip decoder when `name` ends with `.gz`
+pub fn decompress_if_gzipped<'a, R: Read + Send + 'a>(name: &str, reader: R) -> Box<dyn Read + Send + 'a> {
+    if strip_gz(name).len() != name.len() {
+        Box::new(GzDecoder::new(reader))
+    } else {
+        Box::new(reader)
+    }
+}
+
+/// Open a plain file, a gzipped file or an archive member as a byte stream
+pub fn open_source(path: &str) -> io::Result<Box<dyn Read + Send>> {
+    let raw: Box<dyn Read + Send> = match split_virtual_path(path) {
+        Some((archive, inner)) => match ArchiveKind::from_path(archive) {
+            Some(ArchiveKind::Zip) => open_zip_member(archive, inner)?,
+            Some(kind) => open_tar_member(archive, kind, inner)?,
+            None => unreachable!("split_virtual_path only accepts archive paths"),
+        },
+        None => Box::new(File::open(path)?),
+    };
+    Ok(decompress_if_gzipped(path, raw))
+}
+
+/// List the regular files inside an archive together with the start of their content
+pub fn list_members(archive_path: &str, head_size: usize) -> io::Result<Vec<ArchiveMember>> {
+    match ArchiveKind::from_path(archive_path) {
+        Some(ArchiveKind::Zip) => list_zip_members(archive_path, head_size),
+        Some(kind) => list_tar_members(archive_path, kind, head_size),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Not an archive: {}", archive_path),
+        )),
+    }
+}
+
+/// First `head_size` bytes of a member, decompressed if its name ends in `.gz`
+fn read_head(name: &str, reader: impl Read, head_size: usize) -> io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(head_size);
+    if strip_gz(name).len() != name.len() {
+        GzDecoder::new(reader).take(head_size as u64).read_to_end(&mut head)?;
+    } else {
+        reader.take(head_size as u64).read_to_end(&mut head)?;
+    }
+    Ok(head)
+}
+
+fn zip_error(e: zip::result::ZipError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn list_zip_members(archive_path: &str, head_size: usize) -> io::Result<Vec<ArchiveMember>> {
+    let mut archive = ZipArchive::new(File::open(archive_path)?).map_err(zip_error)?;
+    let mut members = Vec::new();
+
+    for index in 0..archive.len() {
+        let file = archive.by_index(index).map_err(zip_error)?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file.name().to_string();
+        members.push(ArchiveMember {
+            head: read_head(&name, file, head_size)?,
+            path: virtual_path(archive_path, &name),
+        });
+    }
+
+    Ok(members)
+}
+
+/// Stream a zip member straight from its offset in the archive, so the
+/// returned reader does not borrow the `ZipArchive`
+fn open_zip_member(archive_path: &str, inner: &str) -> io::Result<Box<dyn Read + Send>> {
+    let mut archive = ZipArchive::new(File::open(archive_path)?).map_err(zip_error)?;
+    let (data_start, compressed_size, method) = {
+        let file = archive.by_name(inner).map_err(zip_error)?;
+        if file.encrypted() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Encrypted archive member: {}", inner),
+            ));
+        }
+        (file.data_start(), file.compressed_size(), file.compression())
+    };
+
+    let mut file = File::open(archive_path)?;
+    file.seek(SeekFrom::Start(data_start))?;
+    let data = file.take(compressed_size);
+
+    match method {
+        CompressionMethod::Stored => Ok(Box::new(data)),
+        CompressionMethod::Deflated => Ok(Box::new(DeflateDecoder::new(data))),
+        other => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Unsupported compression {:?} for {}", other, inner),
+        )),
+    }
+}
+
+fn open_tar_stream(archive_path: &str, kind: ArchiveKind) -> io::Result<Box<dyn Read + Send>> {
+    let file = File::open(archive_path)?;
+    Ok(match kind {
+        ArchiveKind::TarGz => Box::new(GzDecoder::new(file)),
+        _ => Box::new(file),
+    })
+}
+
+fn tar_member_name(entry: &tar::Entry<impl Read>) -> io::Result<String> {
+    Ok(entry.path()?.to_string_lossy().replace('\\', "/"))
+}
+
+fn list_tar_members(archive_path: &str, kind: ArchiveKind, head_size: usize) -> io::Result<Vec<ArchiveMember>> {
+    let mut archive = tar::Archive::new(open_tar_stream(archive_path, kind)?);
+    let mut members = Vec::new();
+
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = tar_member_name(&entry)?;
+        members.push(ArchiveMember {
+            head: read_head(&name, entry, head_size)?,
+            path: virtual_path(archive_path, &name),
+        });
+    }
+
+    Ok(members)
+}
+
+/// Where the data of a tar member starts in the (decompressed) archive
+/// stream, and how long it is
+fn find_tar_member(
+    entries: tar::Entries<'_, impl Read>,
+    archive_path: &str,
+    inner: &str,
+) -> io::Result<(u64, u64)> {
+    for entry in entries {
+        let entry = entry?;
+        if entry.header().entry_type().is_file() && tar_member_name(&entry)? == inner {
+            return Ok((entry.raw_file_position(), entry.size()));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} not found in {}", inner, archive_path),
+    ))
+}
+
+/// Open a tar member with a single pass over the archive: plain `.tar` files
+/// are indexed by seeking over member data, while a `.tar.gz` stream is read
+/// up to the member and handed on from there.
+///
+/// A tar stream has no index, so opening every member of a `.tar.gz` this
+/// way decompresses it once per member; imports use [`ArchiveCache`].
+fn open_tar_member(archive_path: &str, kind: ArchiveKind, inner: &str) -> io::Result<Box<dyn Read + Send>> {
+    if kind == ArchiveKind::Tar {
+        let mut archive = tar::Archive::new(File::open(archive_path)?);
+        let (position, size) = find_tar_member(archive.entries_with_seek()?, archive_path, inner)?;
+        let mut file = archive.into_inner();
+        file.seek(SeekFrom::Start(position))?;
+        return Ok(Box::new(file.take(size)));
+    }
+
+    let mut archive = tar::Archive::new(CountingReader::new(open_tar_stream(archive_path, kind)?));
+    let (position, size) = find_tar_member(archive.entries()?, archive_path, inner)?;
+    // The header has been read but not the data, so the stream is at (or,
+    // should the tar reader ever read ahead, past) the member
+    let mut stream = archive.into_inner();
+    let skip = position
+        .checked_sub(stream.count)
+        .ok_or_else(|| io::Error::other(format!("Read past {} in {}", inner, archive_path)))?;
+    io::copy(&mut (&mut stream).take(skip), &mut io::sink())?;
+    Ok(Box::new(stream.take(size)))
+}
+
+/// Reader keeping count of the bytes read through it
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R> CountingReader<R> {
+    fn new(inner: R) -> Self {
+        Self { inner, count: 0 }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+/// Tar archives read once for a whole import
+///
+/// Reaching a member of a tar stream means reading every member before it,
+/// so opening each member with [`open_source`] reads a `.tar.gz` once per
+/// member. Here the first member opened from a tar archive has it indexed in
+/// one pass: plain `.tar` members are then seeked to, and the wanted members
+/// of a `.tar.gz` are unpacked to a temporary directory on the way. The
+/// directory is removed when the cache is dropped.
+pub struct ArchiveCache {
+    /// Virtual paths of the members to unpack from compressed archives
+    wanted: HashSet<String>,
+    dir: PathBuf,
+    tars: Mutex<HashMap<String, Arc<TarIndex>>>,
+}
+
+/// Members of one tar archive, by name
+type TarIndex = HashMap<String, TarMember>;
+
+enum TarMember {
+    /// Position and size of the member data in a plain tar file
+    Span(u64, u64),
+    /// Unpacked copy of a member of a compressed archive
+    Unpacked(PathBuf),
+}
+
+static NEXT_CACHE_DIR: AtomicUsize = AtomicUsize::new(0);
+
+impl ArchiveCache {
+    /// Cache for an import of `sources`; other members of compressed
+    /// archives are still opened, but not unpacked
+    pub fn new<'a>(sources: impl IntoIterator<Item = &'a str>) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "logterminator-archives-{}-{}",
+            std::process::id(),
+            NEXT_CACHE_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        Self {
+            wanted: sources
+                .into_iter()
+                .filter(|path| split_virtual_path(path).is_some())
+                .map(str::to_string)
+                .collect(),
+            dir,
+            tars: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Like [`open_source`], reading tar archives through the cache
+    pub fn open_source(&self, path: &str) -> io::Result<Box<dyn Read + Send>> {
+        let raw: Box<dyn Read + Send> = match split_virtual_path(path) {
+            Some((archive, inner)) => match ArchiveKind::from_path(archive) {
+                Some(ArchiveKind::Zip) => open_zip_member(archive, inner)?,
+                Some(kind) => self.open_tar_member(archive, kind, inner)?,
+                None => unreachable!("split_virtual_path only accepts archive paths"),
+            },
+            None => Box::new(File::open(path)?),
+        };
+        Ok(decompress_if_gzipped(path, raw))
+    }
+
+    fn open_tar_member(&self, archive_path: &str, kind: ArchiveKind, inner: &str) -> io::Result<Box<dyn Read + Send>> {
+        match self.tar_index(archive_path, kind)?.get(inner) {
+            Some(TarMember::Span(position, size)) => {
+                let mut file = File::open(archive_path)?;
+                file.seek(SeekFrom::Start(*position))?;
+                Ok(Box::new(file.take(*size)))
+            }
+            Some(TarMember::Unpacked(path)) => Ok(Box::new(File::open(path)?)),
+            None => open_tar_member(archive_path, kind, inner),
+        }
+    }
+
+    fn tar_index(&self, archive_path: &str, kind: ArchiveKind) -> io::Result<Arc<TarIndex>> {
+        // Held while indexing, so workers opening members of the same archive
+        // wait for the one pass instead of each reading it
+        let mut tars = self.tars.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(index) = tars.get(archive_path) {
+            return Ok(index.clone());
+        }
+        let index = Arc::new(match kind {
+            ArchiveKind::Tar => index_tar(archive_path)?,
+            _ => self.unpack_tar(archive_path, kind, tars.len())?,
+        });
+        tars.insert(archive_path.to_string(), index.clone());
+        Ok(index)
+    }
+
+    /// Unpack the wanted members of a compressed tar archive into files
+    /// numbered after the archive and the member, so no name from the
+    /// archive ever becomes part of a path on disk
+    fn unpack_tar(&self, archive_path: &str, kind: ArchiveKind, archive_number: usize) -> io::Result<TarIndex> {
+        let mut archive = tar::Archive::new(open_tar_stream(archive_path, kind)?);
+        let mut members = TarIndex::new();
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = tar_member_name(&entry)?;
+            if !self.wanted.contains(&virtual_path(archive_path, &name)) {
+                continue;
+            }
+            fs::create_dir_all(&self.dir)?;
+            let path = self.dir.join(format!("{}-{}", archive_number, members.len()));
+            io::copy(&mut entry, &mut File::create(&path)?)?;
+            members.insert(name, TarMember::Unpacked(path));
+        }
+
+        Ok(members)
+    }
+}
+
+impl Drop for ArchiveCache {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Position and size of every member of a plain tar file, seeking over the data
+fn index_tar(archive_path: &str) -> io::Result<TarIndex> {
+    let mut archive = tar::Archive::new(File::open(archive_path)?);
+    let mut members = TarIndex::new();
+
+    for entry in archive.entries_with_seek()? {
+        let entry = entry?;
+        if entry.header().entry_type().is_file() {
+            members.insert(
+                tar_member_name(&entry)?,
+                TarMember::Span(entry.raw_file_position(), entry.size()),
+            );
+        }
+    }
+
+    Ok(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const LOG: &[u8] = b"<table><tr><td class=\"date\">2026/01/14 07:17:37,370</td></tr></table>";
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn read_all(path: &str) -> Vec<u8> {
+        let mut content = Vec::new();
+        open_source(path).unwrap().read_to_end(&mut content).unwrap();
+        content
+    }
+
+    fn write_tar<W: Write>(writer: W, members: &[(&str, &[u8])]) -> W {
+        let mut builder = tar::Builder::new(writer);
+        for (name, data) in members {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_zip_members() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("logs.zip");
+        let archive_str = archive_path.to_str().unwrap();
+
+        let mut writer = zip::ZipWriter::new(File::create(&archive_path).unwrap());
+        let deflated = zip::write::SimpleFileOptions::default();
+        let stored = deflated.compression_method(CompressionMethod::Stored);
+        writer.add_directory("run/", deflated).unwrap();
+        writer.start_file("run/TestA_ID_1---0.html", deflated).unwrap();
+        writer.write_all(LOG).unwrap();
+        writer.start_file("run/TestA_ID_1---1.html.gz", stored).unwrap();
+        writer.write_all(&gzip(LOG)).unwrap();
+        writer.finish().unwrap();
+
+        let members = list_members(archive_str, 16).unwrap();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].path, format!("{}!/run/TestA_ID_1---0.html", archive_str));
+        assert_eq!(members[1].head, &LOG[..16]);
+
+        for member in &members {
+            assert_eq!(read_all(&member.path), LOG);
+        }
+        assert_eq!(source_directory(&members[0].path), Some(format!("{}!/run", archive_str)));
+        assert!(open_source(&virtual_path(archive_str, "missing.html")).is_err());
+    }
+
+    #[test]
+    fn test_tar_gz_members() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive_path = dir.path().join("logs.tar.gz");
+        let archive_str = archive_path.to_str().unwrap();
+
+        let encoder = GzEncoder::new(File::create(&archive_path).unwrap(), Compression::default());
+        write_tar(encoder, &[("a/TestA_ID_1---0.html", LOG), ("a/notes.txt", b"hello")])
+            .finish()
+            .unwrap();
+
+        let members = list_members(archive_str, 4096).unwrap();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[1].head, b"hello");
+        assert_eq!(read_all(&members[0].path), LOG);
+        assert_eq!(read_all(&members[1].path), b"hello");
+    }
+
+    #[test]
+    fn test_archive_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let tar_path = dir.path().join("logs.tar");
+        let tar_gz_path = dir.path().join("logs.tgz");
+        let members: [(&str, &[u8]); 3] = [("a/one.html", LOG), ("a/two.txt", b"two"), ("a/three.txt", b"three")];
+        write_tar(File::create(&tar_path).unwrap(), &members);
+        let encoder = GzEncoder::new(File::create(&tar_gz_path).unwrap(), Compression::default());
+        write_tar(encoder, &members).finish().unwrap();
+
+        let wanted: Vec<String> = [&tar_path, &tar_gz_path]
+            .iter()
+            .flat_map(|path| {
+                let path = path.to_str().unwrap();
+                [virtual_path(path, "a/one.html"), virtual_path(path, "a/three.txt")]
+            })
+            .collect();
+        let cache = ArchiveCache::new(wanted.iter().map(String::as_str));
+
+        for archive in [&tar_path, &tar_gz_path] {
+            let archive = archive.to_str().unwrap();
+            for (name, data) in members {
+                let mut content = Vec::new();
+                let path = virtual_path(archive, name);
+                cache.open_source(&path).unwrap().read_to_end(&mut content).unwrap();
+                assert_eq!(content, data, "{}", path);
+                assert_eq!(read_all(&path), data, "{}", path);
+            }
+            assert!(cache.open_source(&virtual_path(archive, "a/missing.txt")).is_err());
+        }
+
+        // Only the wanted members of the compressed archive were unpacked
+        assert_eq!(fs::read_dir(&cache.dir).unwrap().count(), 2);
+        let cache_dir = cache.dir.clone();
+        drop(cache);
+        assert!(!cache_dir.exists());
+    }
+
+    #[test]
+    fn test_gzipped_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("TestA_ID_1---0.html.gz");
+        std::fs::write(&path, gzip(LOG)).unwrap();
+
+        let path = path.to_str().unwrap();
+        assert_eq!(logical_file_name(path), "TestA_ID_1---0.html");
+        assert_eq!(read_all(path), LOG);
+        assert_eq!(source_directory(path), None);
+    }
+}
@@ -95,8 +95,8 @@ pub fn create_auto_bookmark(log_entry_id: i64, title: String) -> Bookmark {
 
 /// Finds failure anchor markers in log entries.
 ///
-/// Scans all log entries for messages ending with `[FAIL]` marker,
-/// which indicates a failure anchor was detected during HTML parsing.
+/// Scans all log entries for the `is_failure` flag, which is set when a
+/// failure anchor was detected during HTML parsing.
 ///
 /// # Arguments
 /// * `entries` - Slice of log entries to scan for failure markers
@@ -107,12 +107,12 @@ pub fn create_auto_bookmark(log_entry_id: i64, title: String) -> Bookmark {
 /// # Examples
 /// ```
 /// use logterminator_lib::bookmark_utils::find_failure_anchor_markers;
-/// // Message: "Test failed assertion [FAIL]"
+/// // Entry with is_failure: true
 /// // Returns: vec![entry_id]
 /// ```
 pub fn find_failure_anchor_markers(entries: &[LogEntry]) -> Vec<i64> {
     entries.iter()
-        .filter(|entry| entry.message.ends_with("[FAIL]"))
+        .filter(|entry| entry.is_failure)
         .filter_map(|entry| entry.id)
         .collect()
 }
@@ -146,14 +146,27 @@ mod tests {
             file_path: "/test/path".to_string(),
             file_index: 0,
             timestamp: "2024-01-01 12:00:00".to_string(),
+            timestamp_ms: None,
             level: "INFO".to_string(),
             stack: "".to_string(),
             message: message.to_string(),
             line_number: 1,
+            thread: None,
+            module: None,
+            source_line: None,
+            is_failure: false,
+            json_payload: None,
             created_at: None,
         }
     }
 
+    fn create_failure_entry(id: i64, message: &str) -> LogEntry {
+        LogEntry {
+            is_failure: true,
+            ..create_test_entry(id, message)
+        }
+    }
+
     #[test]
     fn test_find_auto_bookmark_markers_basic() {
         let entries = vec![
@@ -250,9 +263,9 @@ mod tests {
     #[test]
     fn test_find_failure_anchor_markers_basic() {
         let entries = vec![
-            create_test_entry(1, "Test failed assertion [FAIL]"),
+            create_failure_entry(1, "Test failed assertion"),
             create_test_entry(2, "Normal log message"),
-            create_test_entry(3, "Another failure [FAIL]"),
+            create_failure_entry(3, "Another failure"),
         ];
 
         let failure_ids = find_failure_anchor_markers(&entries);
@@ -264,7 +277,7 @@ mod tests {
 
     #[test]
     fn test_find_failure_anchor_markers_no_id() {
-        let mut entry = create_test_entry(1, "Test failed [FAIL]");
+        let mut entry = create_failure_entry(1, "Test failed");
         entry.id = None;
 
         let entries = vec![entry];
@@ -276,9 +289,10 @@ mod tests {
 
     #[test]
     fn test_find_failure_anchor_markers_none() {
+        // A message that merely ends in [FAIL] is not a failure anchor
         let entries = vec![
             create_test_entry(1, "Test failed"),
-            create_test_entry(2, "Normal log message"),
+            create_test_entry(2, "Expected output: [FAIL]"),
         ];
 
         let failure_ids = find_failure_anchor_markers(&entries);
@@ -1,241 +1,770 @@
+use crate::bookmark_utils::{create_auto_bookmark, find_auto_bookmark_markers};
+use crate::fingerprint::FileFingerprint;
+use crate::import_report::{FileParseStatus, FileReport, ImportReport};
+use crate::json_payload::JsonPredicate;
+use crate::level::{normalize_level, severity};
 use crate::log_parser::{Bookmark, LogEntry, TestSession};
-use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult};
+use crate::migrations::{migrate, MigrationError};
+use crate::session_attributes::SessionAttributes;
+use crate::storage::{MaintenanceProgress, MaintenanceReport, RetentionCandidate, SessionUsage, StorageUsage};
+use crate::traceback::{entry_frames, StackFrame};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Result as SqlResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 // Re-export SearchResult from the parent module
 pub use crate::SearchResult;
 
+/// Optional filters applied on top of the session when listing entries
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EntryFilter {
+    /// Levels to include; an empty list matches nothing
+    pub levels: Option<Vec<String>>,
+    /// Canonical level whose severity entries must reach, e.g. `WARNING`
+    pub min_level: Option<String>,
+    /// Substring matched against timestamp and message
+    pub search_term: Option<String>,
+    /// Exact thread name from the hierarchy column
+    pub thread: Option<String>,
+    /// Module path; also matches sub-modules (`pkg` matches `pkg.mod`)
+    pub module: Option<String>,
+    /// Predicates on the entry's JSON payload, all of which must hold
+    #[serde(skip)]
+    pub json: Vec<JsonPredicate>,
+}
+
+impl EntryFilter {
+    /// Append SQL conditions and their bound parameters for every filter that is set
+    pub fn push_conditions(
+        &self,
+        where_conditions: &mut Vec<String>,
+        params: &mut Vec<Box<dyn rusqlite::ToSql>>,
+    ) {
+        // Handle level filter - support multiple levels
+        if let Some(levels) = &self.levels {
+            // Empty array means no levels selected - return no results
+            if levels.is_empty() {
+                // Add a condition that never matches
+                where_conditions.push("1 = 0".to_string());
+            } else {
+                // Filter out empty strings and "ALL" (legacy support)
+                let filtered_levels: Vec<&String> = levels.iter()
+                    .filter(|level| !level.is_empty() && *level != "ALL")
+                    .collect();
+
+                if !filtered_levels.is_empty() {
+                    // Levels are stored normalized, so one IN clause covers every spelling
+                    let placeholders = vec!["?"; filtered_levels.len()].join(", ");
+                    where_conditions.push(format!("level IN ({})", placeholders));
+                    for level in filtered_levels {
+                        params.push(Box::new(normalize_level(level)));
+                    }
+                }
+            }
+        }
+
+        // Levels without a severity, such as MARKER, are kept at any minimum
+        if let Some(min_severity) = self.min_level.as_deref().and_then(|level| severity(&normalize_level(level))) {
+            where_conditions.push("(severity >= ? OR severity IS NULL)".to_string());
+            params.push(Box::new(min_severity));
+        }
+
+        if let Some(search) = self.search_term.as_ref().filter(|s| !s.is_empty()) {
+            where_conditions.push("(timestamp LIKE ? OR message LIKE ?)".to_string());
+            let search_pattern = format!("%{}%", search);
+            params.push(Box::new(search_pattern.clone()));
+            params.push(Box::new(search_pattern));
+        }
+
+        if let Some(thread) = self.thread.as_ref().filter(|t| !t.is_empty()) {
+            where_conditions.push("thread = ?".to_string());
+            params.push(Box::new(thread.clone()));
+        }
+
+        if let Some(module) = self.module.as_ref().filter(|m| !m.is_empty()) {
+            where_conditions.push("(module = ? OR module LIKE ?)".to_string());
+            params.push(Box::new(module.clone()));
+            params.push(Box::new(format!("{}.%", module)));
+        }
+
+        for predicate in &self.json {
+            predicate.push_condition(where_conditions, params);
+        }
+    }
+
+    /// Whether no filter restricts the entries of the session
+    pub fn is_empty(&self) -> bool {
+        let mut where_conditions = Vec::new();
+        self.push_conditions(&mut where_conditions, &mut Vec::new());
+        where_conditions.is_empty()
+    }
+}
+
+/// Sort key of an entry, `(timestamp_ms, id)`, marking the edge of a page
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EntryCursor {
+    pub timestamp_ms: Option<i64>,
+    pub id: i64,
+}
+
+/// Where a requested page of entries lies
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PageAnchor {
+    /// The entries following the cursor
+    After { cursor: EntryCursor },
+    /// The entries preceding the cursor
+    Before { cursor: EntryCursor },
+    /// The entries from a 0-based position among the matching entries
+    Position { position: usize },
+    /// The page holding this entry, with pages starting at multiples of the limit
+    Entry { entry_id: i64 },
+}
+
+/// A page of entries in display order
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryPage {
+    pub entries: Vec<LogEntry>,
+    /// Entries of the session matching the filter
+    pub total: usize,
+    /// Position of the first entry among the matching entries. Only known
+    /// for pages next to a cursor when no filter is set.
+    pub position: Option<usize>,
+}
+
+/// Entries of a session whose timestamps did not match any configured format
+#[derive(Debug, Clone, Serialize)]
+pub struct UnparsedTimestamps {
+    pub count: usize,
+    pub samples: Vec<String>,
+}
+
+/// A bookmark taken off an entry that is about to be re-imported
+struct MovedBookmark {
+    file_path: String,
+    line_number: usize,
+    message: String,
+    title: Option<String>,
+    notes: Option<String>,
+    color: Option<String>,
+    created_at: Option<String>,
+}
+
 pub struct DatabaseManager {
     conn: Connection,
 }
 
 impl DatabaseManager {
-    pub fn new(db_path: &str) -> SqlResult<Self> {
-        let conn = Connection::open(db_path)?;
-        Self::init_tables(&conn)?;
+    /// Rows ordered before a given (timestamp_ms, id) under `ORDER BY timestamp_ms ASC, id ASC`.
+    ///
+    /// Binds the entry's timestamp_ms three times followed by its id. Entries
+    /// without a parsed timestamp sort first, as SQLite orders NULL lowest.
+    const BEFORE_ENTRY_CONDITION: &'static str =
+        "((? IS NOT NULL AND timestamp_ms IS NULL) OR timestamp_ms < ? OR (timestamp_ms IS ? AND id < ?))";
+
+    /// Columns read into a [`LogEntry`] by [`entry_from_row`](Self::entry_from_row)
+    const ENTRY_COLUMNS: &'static str =
+        "id, file_path, file_index, timestamp, level, stack, message, line_number, thread, module, source_line, timestamp_ms, is_failure, json_payload";
+
+    /// Entries inserted between checks for a cancelled import
+    const CANCEL_CHECK_INTERVAL: usize = 1000;
+
+    /// How long a connection waits for a lock held by another one
+    const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Open the database for writing, switching it to WAL mode and upgrading
+    /// its schema to the current version first
+    pub fn new(db_path: &str) -> Result<Self, MigrationError> {
+        let mut conn = Connection::open(db_path)?;
+        conn.busy_timeout(Self::BUSY_TIMEOUT)?;
+        // WAL lets readers run while an import writes; the mode is stored in the file
+        let _mode: String = conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        migrate(&mut conn, db_path)?;
         Ok(Self { conn })
     }
 
-    fn init_tables(conn: &Connection) -> SqlResult<()> {
-        // Create test sessions table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS test_sessions (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                directory_path TEXT NOT NULL,
-                file_count INTEGER DEFAULT 0,
-                total_entries INTEGER DEFAULT 0,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                last_parsed_at DATETIME
-            )",
-            [],
+    /// Open a read-only connection to a database already set up by [`DatabaseManager::new`]
+    pub fn open_reader(db_path: &str) -> SqlResult<Self> {
+        let conn = Connection::open_with_flags(
+            db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
         )?;
+        conn.busy_timeout(Self::BUSY_TIMEOUT)?;
+        Ok(Self { conn })
+    }
 
-        // Create log entries table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS log_entries (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                test_session_id TEXT NOT NULL,
-                file_path TEXT NOT NULL,
-                file_index INTEGER NOT NULL,
-                timestamp TEXT NOT NULL,
-                level TEXT NOT NULL,
-                stack TEXT NOT NULL,
-                message TEXT NOT NULL,
-                line_number INTEGER NOT NULL,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (test_session_id) REFERENCES test_sessions(id)
-            )",
-            [],
-        )?;
+    #[cfg(test)]
+    pub fn create_test_session(&self, session: &TestSession) -> SqlResult<String> {
+        Self::insert_session_row(&self.conn, session)?;
+        Ok(session.id.clone())
+    }
 
-        // Create bookmarks table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS bookmarks (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                log_entry_id INTEGER NOT NULL,
-                title TEXT,
-                notes TEXT,
-                color TEXT DEFAULT 'yellow',
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (log_entry_id) REFERENCES log_entries(id) ON DELETE CASCADE
-            )",
-            [],
+    #[cfg(test)]
+    pub fn insert_entries(&mut self, entries: &[LogEntry]) -> SqlResult<Vec<i64>> {
+        let tx = self.conn.transaction()?;
+        let inserted_ids = Self::insert_entry_rows(&tx, entries, &|| false)?.unwrap_or_default();
+        let mut session_ids: Vec<&str> = entries.iter().map(|entry| entry.test_session_id.as_str()).collect();
+        session_ids.sort_unstable();
+        session_ids.dedup();
+        for session_id in session_ids {
+            Self::number_entries(&tx, session_id, false)?;
+        }
+        tx.commit()?;
+        Ok(inserted_ids)
+    }
+
+    /// Start writing `session` in one transaction, with its entries added in
+    /// batches as they are parsed.
+    ///
+    /// With `reload` the stored session is brought up to date: files
+    /// passed to [`SessionImport::drop_file`] lose their entries, the entries
+    /// of every other file and their bookmarks are left untouched, and
+    /// stored attributes `session` does not have are kept. Otherwise any
+    /// session with the same name and directory is replaced by `session`.
+    pub fn begin_session_import(&mut self, session: &TestSession, reload: bool) -> SqlResult<SessionImport<'_>> {
+        let tx = self.conn.transaction()?;
+        if !reload {
+            if let Some(existing_id) = Self::session_id_by_name(&tx, &session.name, &session.directory_path)? {
+                log::info!("[DB] Replacing existing session: {}", existing_id);
+                Self::delete_session_rows(&tx, &existing_id)?;
+            }
+            Self::insert_session_row(&tx, session)?;
+        }
+        Ok(SessionImport {
+            tx,
+            session_id: session.id.clone(),
+            moved_bookmarks: HashMap::new(),
+            renumber: false,
+            auto_bookmarks: 0,
+        })
+    }
+
+    /// Set `seq`, the position in display order, on the entries of a session
+    /// that have none and on every entry sorting after them, on `conn`,
+    /// which must be inside a transaction. `from_start` renumbers the whole
+    /// session, as needed once entries were deleted. The session's entries
+    /// version is bumped whenever positions change, which invalidates
+    /// cached counts.
+    fn number_entries(conn: &Connection, session_id: &str, from_start: bool) -> SqlResult<()> {
+        let (start, from_entry) = if from_start {
+            (0, None)
+        } else {
+            let first_unnumbered: Option<(Option<i64>, i64)> = conn
+                .query_row(
+                    "SELECT timestamp_ms, id FROM log_entries WHERE test_session_id = ? AND seq IS NULL
+                     ORDER BY timestamp_ms, id LIMIT 1",
+                    [session_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            let Some((timestamp_ms, id)) = first_unnumbered else {
+                return Ok(());
+            };
+
+            // Entries before the first new one keep their positions
+            let start: Option<i64> = conn
+                .query_row(
+                    &format!(
+                        "SELECT seq + 1 FROM log_entries WHERE test_session_id = ? AND seq IS NOT NULL AND {}
+                         ORDER BY seq DESC LIMIT 1",
+                        Self::BEFORE_ENTRY_CONDITION
+                    ),
+                    params![session_id, timestamp_ms, timestamp_ms, timestamp_ms, id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            (start.unwrap_or(0), Some((timestamp_ms, id)))
+        };
+
+        let mut query = "UPDATE log_entries SET seq = numbered.seq
+             FROM (SELECT id, ? + ROW_NUMBER() OVER (ORDER BY timestamp_ms, id) - 1 AS seq
+                   FROM log_entries WHERE test_session_id = ?"
+            .to_string();
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(start), Box::new(session_id.to_string())];
+        if let Some((timestamp_ms, id)) = from_entry {
+            query.push_str(&format!(" AND NOT IFNULL({}, 0)", Self::BEFORE_ENTRY_CONDITION));
+            params.push(Box::new(timestamp_ms));
+            params.push(Box::new(timestamp_ms));
+            params.push(Box::new(timestamp_ms));
+            params.push(Box::new(id));
+        }
+        query.push_str(") AS numbered WHERE log_entries.id = numbered.id AND log_entries.seq IS NOT numbered.seq");
+
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        let changed = conn.execute(&query, &param_refs[..])?;
+
+        if changed > 0 || from_start {
+            conn.execute(
+                "UPDATE test_sessions SET entries_version = entries_version + 1 WHERE id = ?",
+                [session_id],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Highest stored line number of each file of a session
+    pub fn get_file_line_marks(&self, session_id: &str) -> SqlResult<HashMap<String, usize>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT file_path, MAX(line_number) FROM log_entries WHERE test_session_id = ? GROUP BY file_path"
         )?;
+        let marks = stmt.query_map([session_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        marks.collect()
+    }
 
-        // Create indexes for performance
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_entries_session ON log_entries(test_session_id)",
-            [],
+    /// ID of the session imported under `name` from `directory_path`, if any
+    pub fn find_session_id(&self, name: &str, directory_path: &str) -> SqlResult<Option<String>> {
+        Self::session_id_by_name(&self.conn, name, directory_path)
+    }
+
+    fn session_id_by_name(conn: &Connection, name: &str, directory_path: &str) -> SqlResult<Option<String>> {
+        conn.query_row(
+            "SELECT id FROM test_sessions WHERE name = ? AND directory_path = ?",
+            params![name, directory_path],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+    }
+
+    fn file_bookmarks(conn: &Connection, session_id: &str, file_path: &str) -> SqlResult<Vec<MovedBookmark>> {
+        let mut stmt = conn.prepare(
+            "SELECT e.file_path, e.line_number, e.message, b.title, b.notes, b.color, b.created_at
+             FROM bookmarks b
+             JOIN log_entries e ON b.log_entry_id = e.id
+             WHERE e.test_session_id = ? AND e.file_path = ?"
         )?;
+        let bookmarks = stmt.query_map(params![session_id, file_path], |row| {
+            Ok(MovedBookmark {
+                file_path: row.get(0)?,
+                line_number: row.get(1)?,
+                message: row.get(2)?,
+                title: row.get(3)?,
+                notes: row.get(4)?,
+                color: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?;
+        bookmarks.collect()
+    }
+
+    fn delete_file_rows(conn: &Connection, session_id: &str, file_path: &str) -> SqlResult<()> {
+        const FILE_ENTRIES: &str = "SELECT id FROM log_entries WHERE test_session_id = ?1 AND file_path = ?2";
         conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_entries_timestamp ON log_entries(timestamp)",
-            [],
+            &format!("DELETE FROM bookmarks WHERE log_entry_id IN ({})", FILE_ENTRIES),
+            params![session_id, file_path],
         )?;
         conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_entries_level ON log_entries(level)",
-            [],
+            &format!("DELETE FROM stack_frames WHERE log_entry_id IN ({})", FILE_ENTRIES),
+            params![session_id, file_path],
         )?;
         conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_bookmarks_entry ON bookmarks(log_entry_id)",
-            [],
+            "DELETE FROM log_entries WHERE test_session_id = ? AND file_path = ?",
+            params![session_id, file_path],
         )?;
-
-        // Add source_type column if not exists (for existing databases)
-        conn.execute(
-            "ALTER TABLE test_sessions ADD COLUMN source_type TEXT DEFAULT 'local'",
-            [],
-        ).ok();
-
         Ok(())
     }
 
-    pub fn create_test_session(&self, session: &TestSession) -> SqlResult<String> {
-        self.conn.execute(
-            "INSERT INTO test_sessions (id, name, directory_path, file_count, total_entries, source_type, last_parsed_at)
-             VALUES (?, ?, ?, ?, ?, ?, datetime('now'))",
+    fn insert_session_row(conn: &Connection, session: &TestSession) -> SqlResult<()> {
+        conn.execute(
+            "INSERT INTO test_sessions (id, name, directory_path, file_count, total_entries, source_type, incomplete, last_parsed_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, datetime('now'))",
             params![
                 &session.id,
                 &session.name,
                 &session.directory_path,
                 session.file_count,
                 session.total_entries,
-                session.source_type.as_deref().unwrap_or("local")
+                session.source_type.as_deref().unwrap_or("local"),
+                session.incomplete
             ],
         )?;
-        Ok(session.id.clone())
+        Self::insert_session_attributes(conn, &session.id, &session.attributes)
     }
 
-    pub fn insert_entries(&mut self, entries: &[LogEntry]) -> SqlResult<Vec<i64>> {
-        let tx = self.conn.transaction()?;
-
-        let mut inserted_ids = Vec::new();
+    /// Store `attributes` of a session, overwriting existing values of the same keys
+    fn insert_session_attributes(conn: &Connection, session_id: &str, attributes: &SessionAttributes) -> SqlResult<()> {
+        let mut stmt = conn.prepare(
+            "INSERT OR REPLACE INTO session_attributes (test_session_id, key, value) VALUES (?, ?, ?)"
+        )?;
+        for (key, value) in attributes {
+            stmt.execute(params![session_id, key, value])?;
+        }
+        Ok(())
+    }
 
-        {
-            let mut stmt = tx.prepare(
-                "INSERT INTO log_entries
-                 (test_session_id, file_path, file_index, timestamp, level, stack, message, line_number)
-                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
-            )?;
+    /// Insert `entries` on `conn`, which must be inside a transaction.
+    /// Returns `None` as soon as `is_cancelled` reports true.
+    fn insert_entry_rows(
+        conn: &Connection,
+        entries: &[LogEntry],
+        is_cancelled: &dyn Fn() -> bool,
+    ) -> SqlResult<Option<Vec<i64>>> {
+        let mut inserted_ids = Vec::with_capacity(entries.len());
+        let mut stmt = conn.prepare(
+            "INSERT INTO log_entries
+             (test_session_id, file_path, file_index, timestamp, level, severity, stack, message, line_number,
+              thread, module, source_line, timestamp_ms, is_failure, json_payload)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )?;
 
-            for entry in entries {
-                stmt.execute(params![
-                    &entry.test_session_id,
-                    &entry.file_path,
-                    &entry.file_index,
-                    &entry.timestamp,
-                    &entry.level,
-                    &entry.stack,
-                    &entry.message,
-                    &entry.line_number
-                ])?;
-
-                // Get the last inserted row ID from the transaction
-                inserted_ids.push(tx.last_insert_rowid());
+        for (i, entry) in entries.iter().enumerate() {
+            if i % Self::CANCEL_CHECK_INTERVAL == 0 && is_cancelled() {
+                return Ok(None);
             }
+
+            stmt.execute(params![
+                &entry.test_session_id,
+                &entry.file_path,
+                &entry.file_index,
+                &entry.timestamp,
+                &entry.level,
+                severity(&entry.level),
+                &entry.stack,
+                &entry.message,
+                &entry.line_number,
+                &entry.thread,
+                &entry.module,
+                &entry.source_line,
+                &entry.timestamp_ms,
+                &entry.is_failure,
+                &entry.json_payload
+            ])?;
+
+            // Get the last inserted row ID from the transaction
+            let entry_id = conn.last_insert_rowid();
+            inserted_ids.push(entry_id);
+
+            Self::insert_stack_frames(conn, entry_id, &entry_frames(&entry.stack, &entry.message))?;
         }
 
-        tx.commit()?;
-        Ok(inserted_ids)
+        Ok(Some(inserted_ids))
+    }
+
+    fn insert_stack_frames(conn: &Connection, log_entry_id: i64, frames: &[StackFrame]) -> SqlResult<()> {
+        if frames.is_empty() {
+            return Ok(());
+        }
+
+        let mut stmt = conn.prepare_cached(
+            "INSERT INTO stack_frames
+             (log_entry_id, traceback_index, frame_index, file, line, function, code, exception_type, exception_message)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )?;
+        for frame in frames {
+            stmt.execute(params![
+                log_entry_id,
+                frame.traceback_index,
+                frame.frame_index,
+                &frame.file,
+                frame.line,
+                &frame.function,
+                &frame.code,
+                &frame.exception_type,
+                &frame.exception_message
+            ])?;
+        }
+        Ok(())
+    }
+
+    fn insert_import_files(conn: &Connection, session_id: &str, files: &[FileReport]) -> SqlResult<()> {
+        let mut stmt = conn.prepare(
+            "INSERT INTO import_files
+             (test_session_id, file_path, sequence_index, status, bytes, rows, skipped_rows, error,
+              encoding, file_size, mtime_ms, sha256)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )?;
+        for file in files {
+            let fingerprint = file.fingerprint.as_ref();
+            stmt.execute(params![
+                session_id,
+                &file.file_path,
+                file.sequence_index,
+                file.status.as_str(),
+                file.bytes,
+                file.rows,
+                file.skipped_rows,
+                &file.error,
+                &file.encoding,
+                fingerprint.map(|f| f.size),
+                fingerprint.map(|f| f.mtime_ms),
+                fingerprint.map(|f| &f.sha256)
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// Import report of a session, with files in the order they were imported
+    pub fn get_import_report(&self, session_id: &str) -> SqlResult<ImportReport> {
+        let mut stmt = self.conn.prepare(
+            "SELECT file_path, sequence_index, status, bytes, rows, skipped_rows, error,
+                    encoding, file_size, mtime_ms, sha256
+             FROM import_files WHERE test_session_id = ? ORDER BY id ASC"
+        )?;
+
+        let file_iter = stmt.query_map([session_id], |row| {
+            Ok(FileReport {
+                file_path: row.get(0)?,
+                sequence_index: row.get(1)?,
+                status: FileParseStatus::from_db(&row.get::<_, String>(2)?),
+                bytes: row.get(3)?,
+                rows: row.get(4)?,
+                skipped_rows: row.get(5)?,
+                error: row.get(6)?,
+                encoding: row.get(7)?,
+                fingerprint: match (row.get(8)?, row.get(9)?, row.get(10)?) {
+                    (Some(size), Some(mtime_ms), Some(sha256)) => Some(FileFingerprint { size, mtime_ms, sha256 }),
+                    _ => None,
+                },
+            })
+        })?;
+        let files = file_iter.collect::<SqlResult<Vec<_>>>()?;
+
+        Ok(ImportReport::new(session_id, files))
+    }
+
+    /// Traceback frames parsed from an entry, in traceback then frame order
+    pub fn get_stack_frames(&self, log_entry_id: i64) -> SqlResult<Vec<StackFrame>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT traceback_index, frame_index, file, line, function, code, exception_type, exception_message
+             FROM stack_frames WHERE log_entry_id = ?
+             ORDER BY traceback_index ASC, frame_index ASC"
+        )?;
+
+        let frame_iter = stmt.query_map([log_entry_id], |row| {
+            Ok(StackFrame {
+                traceback_index: row.get(0)?,
+                frame_index: row.get(1)?,
+                file: row.get(2)?,
+                line: row.get(3)?,
+                function: row.get(4)?,
+                code: row.get(5)?,
+                exception_type: row.get(6)?,
+                exception_message: row.get(7)?,
+            })
+        })?;
+        frame_iter.collect()
+    }
+
+    /// Distinct exception types raised in a session's tracebacks
+    pub fn get_exception_types(&self, session_id: &str) -> SqlResult<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT f.exception_type FROM stack_frames f
+             JOIN log_entries e ON e.id = f.log_entry_id
+             WHERE e.test_session_id = ? AND f.exception_type IS NOT NULL
+             ORDER BY f.exception_type"
+        )?;
+
+        let type_iter = stmt.query_map([session_id], |row| row.get(0))?;
+        type_iter.collect()
+    }
+
+    fn entry_from_row(row: &rusqlite::Row, session_id: &str) -> SqlResult<LogEntry> {
+        Ok(LogEntry {
+            id: Some(row.get(0)?),
+            test_session_id: session_id.to_string(),
+            file_path: row.get(1)?,
+            file_index: row.get(2)?,
+            timestamp: row.get(3)?,
+            timestamp_ms: row.get(11)?,
+            level: row.get(4)?,
+            stack: row.get(5)?,
+            message: row.get(6)?,
+            line_number: row.get(7)?,
+            thread: row.get(8)?,
+            module: row.get(9)?,
+            source_line: row.get(10)?,
+            is_failure: row.get(12)?,
+            json_payload: row.get(13)?,
+            created_at: None,
+        })
+    }
+
+    /// Counter bumped whenever the positions of a session's entries change
+    pub fn entries_version(&self, session_id: &str) -> SqlResult<i64> {
+        self.conn
+            .query_row("SELECT entries_version FROM test_sessions WHERE id = ?", [session_id], |row| row.get(0))
+            .optional()
+            .map(|version| version.unwrap_or(0))
+    }
+
+    /// Number of entries of a session matching `filter`
+    pub fn count_entries(&self, session_id: &str, filter: &EntryFilter) -> SqlResult<usize> {
+        if filter.is_empty() {
+            // Positions have no gaps, so the last one gives the count
+            return self.conn.query_row(
+                "SELECT IFNULL(MAX(seq) + 1, 0) FROM log_entries WHERE test_session_id = ?",
+                [session_id],
+                |row| row.get(0),
+            );
+        }
+
+        let mut where_conditions: Vec<String> = vec!["test_session_id = ?".to_string()];
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(session_id.to_string())];
+        filter.push_conditions(&mut where_conditions, &mut params);
+
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM log_entries WHERE {}", where_conditions.join(" AND ")),
+            &param_refs[..],
+            |row| row.get(0),
+        )
     }
 
-    pub fn get_entries_paginated(
+    /// Get up to `limit` entries of a session matching `filter` at `anchor`.
+    ///
+    /// Pages next to a cursor and, without a filter, pages at a position
+    /// seek on the entry positions, so they cost the same anywhere in the
+    /// session. With a filter, a position still has to skip the matching
+    /// entries before it, from whichever end of the `total` matching
+    /// entries is nearer. Returns `None` if the anchor entry is not in the session.
+    pub fn get_entries_page(
         &self,
         session_id: &str,
-        offset: usize,
+        anchor: PageAnchor,
         limit: usize,
-        level_filter: Option<&[String]>, // Changed to &[String] for multi-select
-        search_term: Option<&str>,
-    ) -> SqlResult<(Vec<LogEntry>, usize)> {
-        // Build query dynamically
-        let mut base_query =
-            "SELECT id, file_path, file_index, timestamp, level, stack, message, line_number
-                             FROM log_entries WHERE test_session_id = ?"
-                .to_string();
-
-        let mut where_conditions: Vec<String> = Vec::new();
-        let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(session_id)];
-
-        // Handle level filter - support multiple levels
-        if let Some(levels) = level_filter {
-            // Empty array means no levels selected - return no results
-            if levels.is_empty() {
-                // Add a condition that never matches
-                where_conditions.push("1 = 0".to_string());
-            } else {
-                // Filter out empty strings and "ALL" (legacy support)
-                let filtered_levels: Vec<&String> = levels.iter()
-                    .filter(|level| !level.is_empty() && *level != "ALL")
-                    .collect();
+        filter: &EntryFilter,
+        total: usize,
+    ) -> SqlResult<Option<EntryPage>> {
+        let unfiltered = filter.is_empty();
+        let mut where_conditions: Vec<String> = vec!["test_session_id = ?".to_string()];
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(session_id.to_string())];
+        filter.push_conditions(&mut where_conditions, &mut params);
+
+        let position = match anchor {
+            PageAnchor::Position { position } => position,
+            PageAnchor::Entry { entry_id } => {
+                let seq: Option<usize> = self
+                    .conn
+                    .query_row(
+                        "SELECT seq FROM log_entries WHERE id = ? AND test_session_id = ?",
+                        params![entry_id, session_id],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                let Some(seq) = seq else {
+                    return Ok(None);
+                };
 
-                if !filtered_levels.is_empty() {
-                    // Build OR conditions for multiple levels using IN clause
-                    let level_placeholders: Vec<String> = (0..filtered_levels.len() * 2)
-                        .map(|_| "?".to_string())
-                        .collect();
-
-                    where_conditions.push(format!("(level IN ({}) OR level IN ({}))",
-                        level_placeholders[..filtered_levels.len()].join(", "),
-                        level_placeholders[filtered_levels.len()..].join(", ")));
-
-                    // Add parameters for each level (with and without brackets)
-                    for level in &filtered_levels {
-                        params.push(Box::new(level.to_string()));
-                    }
-                    for level in &filtered_levels {
-                        params.push(Box::new(format!("[{}]", level)));
-                    }
-                }
+                let index = if unfiltered {
+                    seq
+                } else {
+                    let mut conditions = where_conditions.clone();
+                    conditions.push("seq < ?".to_string());
+                    let mut param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+                    param_refs.push(&seq);
+                    self.conn.query_row(
+                        &format!("SELECT COUNT(*) FROM log_entries WHERE {}", conditions.join(" AND ")),
+                        &param_refs[..],
+                        |row| row.get(0),
+                    )?
+                };
+                index - index % limit.max(1)
             }
-        }
+            PageAnchor::After { cursor } => {
+                let (seq, _) = self.cursor_seq(session_id, cursor)?;
+                where_conditions.push("seq > ?".to_string());
+                params.push(Box::new(seq));
+                let rows = self.select_entries(session_id, &where_conditions, &params, false, limit, 0)?;
+                return Ok(Some(Self::cursor_page(rows, total, unfiltered)));
+            }
+            PageAnchor::Before { cursor } => {
+                let (seq, found) = self.cursor_seq(session_id, cursor)?;
+                where_conditions.push(if found { "seq < ?" } else { "seq <= ?" }.to_string());
+                params.push(Box::new(seq));
+                let rows = self.select_entries(session_id, &where_conditions, &params, true, limit, 0)?;
+                return Ok(Some(Self::cursor_page(rows, total, unfiltered)));
+            }
+        };
 
-        if let Some(search) = search_term {
-            where_conditions.push("(timestamp LIKE ? OR message LIKE ?)".to_string());
-            let search_pattern = format!("%{}%", search);
-            params.push(Box::new(search_pattern.clone()));
-            params.push(Box::new(search_pattern));
-        }
+        let rows = if unfiltered {
+            where_conditions.push("seq >= ?".to_string());
+            params.push(Box::new(position));
+            self.select_entries(session_id, &where_conditions, &params, false, limit, 0)?
+        } else if position >= total / 2 {
+            // Deep pages skip fewer entries counting back from the end
+            let count = limit.min(total.saturating_sub(position));
+            let from_end = total.saturating_sub(position + count);
+            self.select_entries(session_id, &where_conditions, &params, true, count, from_end)?
+        } else {
+            self.select_entries(session_id, &where_conditions, &params, false, limit, position)?
+        };
 
-        if !where_conditions.is_empty() {
-            base_query.push_str(" AND ");
-            base_query.push_str(&where_conditions.join(" AND "));
+        Ok(Some(EntryPage {
+            entries: rows.into_iter().map(|(_, entry)| entry).collect(),
+            total,
+            position: Some(position),
+        }))
+    }
+
+    fn cursor_page(rows: Vec<(usize, LogEntry)>, total: usize, unfiltered: bool) -> EntryPage {
+        let position = rows.first().filter(|_| unfiltered).map(|(seq, _)| *seq);
+        EntryPage {
+            entries: rows.into_iter().map(|(_, entry)| entry).collect(),
+            total,
+            position,
         }
+    }
 
-        // Get total count
-        let count_query = base_query.replace(
-            "SELECT id, file_path, file_index, timestamp, level, stack, message, line_number",
-            "SELECT COUNT(*)",
-        );
+    /// Position of the entry at `cursor`, and whether it still exists. For
+    /// an entry that is gone, this is the position of the last entry sorting
+    /// before it, or -1 if there is none.
+    fn cursor_seq(&self, session_id: &str, cursor: EntryCursor) -> SqlResult<(i64, bool)> {
+        let seq: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT seq FROM log_entries WHERE id = ? AND test_session_id = ? AND timestamp_ms IS ?",
+                params![cursor.id, session_id, cursor.timestamp_ms],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(seq) = seq {
+            return Ok((seq, true));
+        }
 
-        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
-        let total: usize = self
+        let before: Option<i64> = self
             .conn
-            .query_row(&count_query, &param_refs[..], |row| row.get(0))?;
+            .query_row(
+                &format!(
+                    "SELECT seq FROM log_entries WHERE test_session_id = ? AND {} ORDER BY seq DESC LIMIT 1",
+                    Self::BEFORE_ENTRY_CONDITION
+                ),
+                params![session_id, cursor.timestamp_ms, cursor.timestamp_ms, cursor.timestamp_ms, cursor.id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok((before.unwrap_or(-1), false))
+    }
 
-        // Add pagination with secondary sort by id for consistent ordering
-        let mut query = base_query;
-        query.push_str(" ORDER BY timestamp ASC, id ASC LIMIT ? OFFSET ?");
-        params.push(Box::new(limit));
-        params.push(Box::new(offset));
+    /// Entries matching `where_conditions` with their positions, in display
+    /// order. `descending` takes them from the end of the session instead.
+    fn select_entries(
+        &self,
+        session_id: &str,
+        where_conditions: &[String],
+        params: &[Box<dyn rusqlite::ToSql>],
+        descending: bool,
+        limit: usize,
+        offset: usize,
+    ) -> SqlResult<Vec<(usize, LogEntry)>> {
+        let query = format!(
+            "SELECT {}, seq FROM log_entries WHERE {} ORDER BY seq {} LIMIT ? OFFSET ?",
+            Self::ENTRY_COLUMNS,
+            where_conditions.join(" AND "),
+            if descending { "DESC" } else { "ASC" }
+        );
+        let mut param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        param_refs.push(&limit);
+        param_refs.push(&offset);
 
-        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
         let mut stmt = self.conn.prepare(&query)?;
-        let entry_iter = stmt.query_map(&param_refs[..], |row| {
-            Ok(LogEntry {
-                id: Some(row.get(0)?),
-                test_session_id: session_id.to_string(),
-                file_path: row.get(1)?,
-                file_index: row.get(2)?,
-                timestamp: row.get(3)?,
-                level: row.get(4)?,
-                stack: row.get(5)?,
-                message: row.get(6)?,
-                line_number: row.get(7)?,
-                created_at: None,
-            })
-        })?;
-
-        let entries: Result<Vec<_>, _> = entry_iter.collect();
-        Ok((entries?, total))
+        let mut rows = stmt
+            .query_map(&param_refs[..], |row| Ok((row.get(14)?, Self::entry_from_row(row, session_id)?)))?
+            .collect::<SqlResult<Vec<_>>>()?;
+        if descending {
+            rows.reverse();
+        }
+        Ok(rows)
     }
 
     pub fn add_bookmark(&self, bookmark: &Bookmark) -> SqlResult<i64> {
@@ -256,11 +785,12 @@ impl DatabaseManager {
         let query = "
             SELECT b.id, b.log_entry_id, b.title, b.notes, b.color, b.created_at,
                    e.id, e.test_session_id, e.file_path, e.file_index,
-                   e.timestamp, e.level, e.stack, e.message, e.line_number
+                   e.timestamp, e.level, e.stack, e.message, e.line_number,
+                   e.thread, e.module, e.source_line, e.timestamp_ms, e.is_failure, e.json_payload
             FROM bookmarks b
             JOIN log_entries e ON b.log_entry_id = e.id
             WHERE e.test_session_id = ?
-            ORDER BY e.timestamp ASC
+            ORDER BY e.timestamp_ms ASC, e.id ASC
         ";
 
         let mut stmt = self.conn.prepare(query)?;
@@ -280,10 +810,16 @@ impl DatabaseManager {
                 file_path: row.get(8)?,           // e.file_path
                 file_index: row.get(9)?,          // e.file_index
                 timestamp: row.get(10)?,          // e.timestamp (CORRECTED!)
+                timestamp_ms: row.get(18)?,       // e.timestamp_ms
                 level: row.get(11)?,              // e.level (CORRECTED!)
                 stack: row.get(12)?,              // e.stack
                 message: row.get(13)?,            // e.message
                 line_number: row.get(14)?,        // e.line_number
+                thread: row.get(15)?,             // e.thread
+                module: row.get(16)?,             // e.module
+                source_line: row.get(17)?,        // e.source_line
+                is_failure: row.get(19)?,         // e.is_failure
+                json_payload: row.get(20)?,       // e.json_payload
                 created_at: None,
             };
 
@@ -294,12 +830,28 @@ impl DatabaseManager {
     }
 
     pub fn get_sessions(&self) -> SqlResult<Vec<TestSession>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, name, directory_path, file_count, total_entries, created_at, last_parsed_at, source_type
-             FROM test_sessions ORDER BY last_parsed_at DESC",
-        )?;
+        self.get_sessions_with_attributes(&HashMap::new())
+    }
 
-        let session_iter = stmt.query_map([], |row| {
+    /// Sessions having every attribute in `required` with exactly that value,
+    /// most recently parsed first
+    pub fn get_sessions_with_attributes(&self, required: &HashMap<String, String>) -> SqlResult<Vec<TestSession>> {
+        let mut sql = String::from(
+            "SELECT id, name, directory_path, file_count, total_entries, created_at, last_parsed_at, source_type, incomplete
+             FROM test_sessions",
+        );
+        let mut params: Vec<&dyn rusqlite::ToSql> = Vec::new();
+        for (i, (key, value)) in required.iter().enumerate() {
+            sql.push_str(if i == 0 { " WHERE " } else { " AND " });
+            sql.push_str("id IN (SELECT test_session_id FROM session_attributes WHERE key = ? AND value = ?)");
+            params.push(key);
+            params.push(value);
+        }
+        sql.push_str(" ORDER BY last_parsed_at DESC");
+
+        let mut attributes = self.get_all_session_attributes()?;
+        let mut stmt = self.conn.prepare(&sql)?;
+        let session_iter = stmt.query_map(params.as_slice(), |row| {
             Ok(TestSession {
                 id: row.get(0)?,
                 name: row.get(1)?,
@@ -309,10 +861,30 @@ impl DatabaseManager {
                 created_at: None,
                 last_parsed_at: None,
                 source_type: row.get(7)?,
+                incomplete: row.get(8)?,
+                attributes: SessionAttributes::new(),
             })
         })?;
 
-        session_iter.collect()
+        session_iter
+            .map(|session| {
+                let mut session = session?;
+                session.attributes = attributes.remove(&session.id).unwrap_or_default();
+                Ok(session)
+            })
+            .collect()
+    }
+
+    fn get_all_session_attributes(&self) -> SqlResult<HashMap<String, SessionAttributes>> {
+        let mut stmt = self.conn.prepare("SELECT test_session_id, key, value FROM session_attributes")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?)))?;
+
+        let mut attributes: HashMap<String, SessionAttributes> = HashMap::new();
+        for row in rows {
+            let (session_id, key, value) = row?;
+            attributes.entry(session_id).or_default().insert(key, value);
+        }
+        Ok(attributes)
     }
 
     pub fn delete_session(&self, session_id: &str) -> SqlResult<()> {
@@ -320,65 +892,202 @@ impl DatabaseManager {
 
         // Use transaction for atomicity
         let tx = self.conn.unchecked_transaction()?;
+        Self::delete_session_rows(&tx, session_id)?;
+        tx.commit()?;
+        log::info!("[DB] Transaction committed");
 
-        {
-            // First, get all log_entry_ids for this session
-            let mut stmt = tx.prepare("SELECT id FROM log_entries WHERE test_session_id = ?")?;
-            let entry_ids: Vec<i64> = stmt.query_map([session_id], |row| row.get(0))?
-                .collect::<Result<Vec<_>, _>>()?;
+        Ok(())
+    }
 
-            log::info!("[DB] Found {} log entries to delete", entry_ids.len());
+    /// Delete a session with its entries and their bookmarks on `conn`,
+    /// which must be inside a transaction
+    fn delete_session_rows(conn: &Connection, session_id: &str) -> SqlResult<()> {
+        // First, get all log_entry_ids for this session
+        let mut stmt = conn.prepare("SELECT id FROM log_entries WHERE test_session_id = ?")?;
+        let entry_ids: Vec<i64> = stmt.query_map([session_id], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
 
-            // Delete bookmarks for each entry
-            if !entry_ids.is_empty() {
-                for entry_id in &entry_ids {
-                    tx.execute("DELETE FROM bookmarks WHERE log_entry_id = ?", [entry_id])?;
-                }
-                log::info!("[DB] Deleted bookmarks for {} entries", entry_ids.len());
+        log::info!("[DB] Found {} log entries to delete", entry_ids.len());
+
+        // Delete bookmarks for each entry
+        if !entry_ids.is_empty() {
+            for entry_id in &entry_ids {
+                conn.execute("DELETE FROM bookmarks WHERE log_entry_id = ?", [entry_id])?;
             }
+            log::info!("[DB] Deleted bookmarks for {} entries", entry_ids.len());
+        }
 
-            // Delete log entries
-            tx.execute(
-                "DELETE FROM log_entries WHERE test_session_id = ?",
-                [session_id],
-            )?;
-            log::info!("[DB] Deleted log entries for session");
+        conn.execute(
+            "DELETE FROM stack_frames WHERE log_entry_id IN (SELECT id FROM log_entries WHERE test_session_id = ?)",
+            [session_id],
+        )?;
 
-            // Delete the session
-            tx.execute("DELETE FROM test_sessions WHERE id = ?", [session_id])?;
-            log::info!("[DB] Deleted session record");
-        } // stmt is dropped here
+        conn.execute("DELETE FROM import_files WHERE test_session_id = ?", [session_id])?;
+        conn.execute("DELETE FROM session_attributes WHERE test_session_id = ?", [session_id])?;
 
-        tx.commit()?;
-        log::info!("[DB] Transaction committed");
+        // Delete log entries
+        conn.execute(
+            "DELETE FROM log_entries WHERE test_session_id = ?",
+            [session_id],
+        )?;
+        log::info!("[DB] Deleted log entries for session");
 
+        // Delete the session
+        conn.execute("DELETE FROM test_sessions WHERE id = ?", [session_id])?;
+        log::info!("[DB] Deleted session record");
+        Ok(())
+    }
+
+    /// Delete several sessions in one transaction
+    pub fn delete_sessions(&mut self, session_ids: &[String]) -> SqlResult<()> {
+        let tx = self.conn.transaction()?;
+        for session_id in session_ids {
+            Self::delete_session_rows(&tx, session_id)?;
+        }
+        tx.commit()?;
+        log::info!("[DB] Deleted {} sessions", session_ids.len());
         Ok(())
     }
 
-    /// Find and delete a session with the same name and directory path
-    /// Returns the session_id of the deleted session, if any
-    pub fn delete_session_by_name_and_path(&self, name: &str, directory_path: &str) -> SqlResult<Option<String>> {
-        log::info!("[DB] delete_session_by_name_and_path: name={}, dir={}", name, directory_path);
+    /// Every session with its name, age in days and whether it has a bookmark
+    pub fn retention_candidates(&self) -> SqlResult<Vec<RetentionCandidate>> {
+        // Bookmarks on failures and markers are the automatic ones, unless
+        // the user has written a note on them
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, julianday('now') - julianday(COALESCE(last_parsed_at, created_at)),
+                    id IN (SELECT e.test_session_id FROM bookmarks b JOIN log_entries e ON e.id = b.log_entry_id
+                           WHERE IFNULL(b.notes, '') <> ''
+                              OR NOT (e.is_failure OR e.level = 'MARKER' OR e.message LIKE '%###%'))
+             FROM test_sessions",
+        )?;
+        let candidates = stmt.query_map([], |row| {
+            Ok(RetentionCandidate {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                age_days: row.get(2)?,
+                bookmarked: row.get(3)?,
+            })
+        })?;
+        candidates.collect()
+    }
+
+    /// Size of the database file and an estimate of each session's share of it
+    pub fn get_storage_usage(&self) -> SqlResult<StorageUsage> {
+        let page_size: u64 = self.conn.pragma_query_value(None, "page_size", |row| row.get(0))?;
+        let free_pages: u64 = self.conn.pragma_query_value(None, "freelist_count", |row| row.get(0))?;
+        let file_bytes = self.file_bytes()?;
+        let free_bytes = free_pages * page_size;
+        let wal_bytes = self
+            .conn
+            .path()
+            .and_then(|path| std::fs::metadata(format!("{}-wal", path)).ok())
+            .map_or(0, |metadata| metadata.len());
 
+        // Byte lengths of the stored text, the bulk of a session's rows and indexes
         let mut stmt = self.conn.prepare(
-            "SELECT id FROM test_sessions WHERE name = ? AND directory_path = ?"
+            "SELECT s.id, s.name, s.directory_path, IFNULL(d.entries, 0), IFNULL(d.data_bytes, 0)
+             FROM test_sessions s
+             LEFT JOIN (
+                 SELECT test_session_id, COUNT(*) AS entries,
+                        SUM(LENGTH(CAST(message AS BLOB)) + LENGTH(CAST(stack AS BLOB))
+                            + LENGTH(CAST(timestamp AS BLOB)) + LENGTH(CAST(file_path AS BLOB))
+                            + IFNULL(LENGTH(CAST(thread AS BLOB)), 0) + IFNULL(LENGTH(CAST(module AS BLOB)), 0)
+                            + IFNULL(LENGTH(CAST(json_payload AS BLOB)), 0)) AS data_bytes
+                 FROM log_entries GROUP BY test_session_id
+             ) d ON d.test_session_id = s.id",
         )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    SessionUsage {
+                        session_id: row.get(0)?,
+                        name: row.get(1)?,
+                        directory_path: row.get(2)?,
+                        entries: row.get(3)?,
+                        bytes: 0,
+                    },
+                    row.get::<_, u64>(4)?,
+                ))
+            })?
+            .collect::<SqlResult<Vec<_>>>()?;
+
+        let data_bytes: u64 = rows.iter().map(|(_, bytes)| bytes).sum();
+        let used_bytes = file_bytes.saturating_sub(free_bytes);
+        let mut sessions: Vec<SessionUsage> = rows
+            .into_iter()
+            .map(|(mut usage, bytes)| {
+                if data_bytes > 0 {
+                    usage.bytes = (used_bytes as f64 * bytes as f64 / data_bytes as f64) as u64;
+                }
+                usage
+            })
+            .collect();
+        sessions.sort_by_key(|usage| std::cmp::Reverse(usage.bytes));
+
+        Ok(StorageUsage { file_bytes, free_bytes, wal_bytes, sessions })
+    }
 
-        let session_id_opt = stmt.query_row(params![name, directory_path], |row| {
-            row.get::<_, String>(0)
-        }).optional()?;
+    /// Refresh the query planner statistics, merge the full-text index and
+    /// rewrite the file without free pages, then shrink the write-ahead log.
+    /// `progress` is called before each step.
+    pub fn run_maintenance(&self, progress: &dyn Fn(MaintenanceProgress)) -> SqlResult<MaintenanceReport> {
+        let started = Instant::now();
+        let bytes_before = self.file_bytes()?;
 
-        match &session_id_opt {
-            Some(id) => println!("[DB] Found existing session: {}", id),
-            None => log::info!("[DB] No existing session found"),
+        let mut stmt = self.conn.prepare(
+            "SELECT name FROM pragma_table_list
+             WHERE schema = 'main' AND type = 'table' AND name NOT LIKE 'sqlite_%'
+             ORDER BY name",
+        )?;
+        let tables = stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<SqlResult<Vec<_>>>()?;
+        drop(stmt);
+
+        // Each step is a label for the progress and the SQL it runs
+        let mut steps: Vec<(String, String)> = tables
+            .iter()
+            .map(|table| (format!("analyze {}", table), format!("ANALYZE \"{}\"", table.replace('"', "\"\""))))
+            .collect();
+        steps.push((
+            "optimize full-text index".to_string(),
+            "INSERT INTO log_entries_fts (log_entries_fts) VALUES ('optimize')".to_string(),
+        ));
+        steps.push(("vacuum".to_string(), "VACUUM".to_string()));
+        let total_steps = steps.len() + 1;
+
+        for (completed_steps, (step, sql)) in steps.into_iter().enumerate() {
+            progress(MaintenanceProgress { step, completed_steps, total_steps });
+            self.conn.execute_batch(&sql)?;
         }
 
-        if let Some(session_id) = session_id_opt {
-            self.delete_session(&session_id)?;
-            Ok(Some(session_id))
-        } else {
-            Ok(None)
+        progress(MaintenanceProgress {
+            step: "checkpoint".to_string(),
+            completed_steps: total_steps - 1,
+            total_steps,
+        });
+        // A reader still on an older snapshot keeps part of the log; the next checkpoint gets it
+        let busy: i64 = self.conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))?;
+        if busy != 0 {
+            log::info!("[DB] Write-ahead log still in use by a reader, not truncated");
         }
+
+        let report = MaintenanceReport {
+            bytes_before,
+            bytes_after: self.file_bytes()?,
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+        log::info!(
+            "[DB] Maintenance done in {} ms: {} -> {} bytes",
+            report.duration_ms,
+            report.bytes_before,
+            report.bytes_after
+        );
+        Ok(report)
+    }
+
+    fn file_bytes(&self) -> SqlResult<u64> {
+        let page_size: u64 = self.conn.pragma_query_value(None, "page_size", |row| row.get(0))?;
+        let page_count: u64 = self.conn.pragma_query_value(None, "page_count", |row| row.get(0))?;
+        Ok(page_size * page_count)
     }
 
     pub fn delete_bookmark(&self, bookmark_id: i64) -> SqlResult<()> {
@@ -395,140 +1104,75 @@ impl DatabaseManager {
         Ok(())
     }
 
-    pub fn get_entry_page(
-        &self,
-        entry_id: i64,
-        items_per_page: usize,
-        level_filter: Option<&[String]>, // Changed to &[String] for multi-select
-        search_term: Option<&str>,
-    ) -> SqlResult<Option<usize>> {
-        // First get the session_id, timestamp, and id for this entry
-        let entry_info: Option<(String, String, i64)> = self
-            .conn
-            .query_row(
-                "SELECT test_session_id, timestamp, id FROM log_entries WHERE id = ?",
-                [entry_id],
-                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
-            )
-            .optional()?;
-
-        let (session_id, entry_timestamp, entry_id_value) = match entry_info {
-            Some(info) => info,
-            None => return Ok(None), // Entry not found
-        };
-
-        // Build the same WHERE conditions as get_entries_paginated
-        let mut where_conditions: Vec<String> = vec!["test_session_id = ?".to_string()];
-        let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(session_id)];
-
-        // Handle level filter - support multiple levels
-        if let Some(levels) = level_filter {
-            // Empty array means no levels selected - return no results
-            if levels.is_empty() {
-                // Add a condition that never matches
-                where_conditions.push("1 = 0".to_string());
-            } else {
-                // Filter out empty strings and "ALL" (legacy support)
-                let filtered_levels: Vec<&String> = levels.iter()
-                    .filter(|level| !level.is_empty() && *level != "ALL")
-                    .collect();
-
-                if !filtered_levels.is_empty() {
-                    // Build OR conditions for multiple levels using IN clause
-                    let level_placeholders: Vec<String> = (0..filtered_levels.len() * 2)
-                        .map(|_| "?".to_string())
-                        .collect();
-
-                    where_conditions.push(format!("(level IN ({}) OR level IN ({}))",
-                        level_placeholders[..filtered_levels.len()].join(", "),
-                        level_placeholders[filtered_levels.len()..].join(", ")));
-
-                    // Add parameters for each level (with and without brackets)
-                    for level in &filtered_levels {
-                        params.push(Box::new(level.to_string()));
-                    }
-                    for level in &filtered_levels {
-                        params.push(Box::new(format!("[{}]", level)));
-                    }
-                }
-            }
-        }
-
-        if let Some(search) = search_term {
-            where_conditions.push("(timestamp LIKE ? OR message LIKE ?)".to_string());
-            let search_pattern = format!("%{}%", search);
-            params.push(Box::new(search_pattern.clone()));
-            params.push(Box::new(search_pattern));
-        }
+    pub fn get_session_log_levels(&self, session_id: &str) -> SqlResult<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT level FROM log_entries WHERE test_session_id = ? ORDER BY level"
+        )?;
 
-        // Count entries that come before this entry (same ordering: timestamp ASC, id ASC)
-        // This matches entries with (timestamp < entry_timestamp) OR (timestamp = entry_timestamp AND id < entry_id)
-        where_conditions.push("((timestamp < ?) OR (timestamp = ? AND id < ?))".to_string());
-        params.push(Box::new(entry_timestamp.clone()));
-        params.push(Box::new(entry_timestamp));
-        params.push(Box::new(entry_id_value));
+        let level_iter = stmt.query_map([session_id], |row| row.get(0))?;
+        level_iter.collect()
+    }
 
-        let count_query = format!(
-            "SELECT COUNT(*) FROM log_entries WHERE {}",
-            where_conditions.join(" AND ")
-        );
+    /// Report entries whose timestamp text could not be parsed.
+    ///
+    /// Returns the number of affected entries and up to `sample_limit` distinct
+    /// raw timestamp strings so the user can add a matching format.
+    pub fn get_unparsed_timestamps(&self, session_id: &str, sample_limit: usize) -> SqlResult<UnparsedTimestamps> {
+        let count: usize = self.conn.query_row(
+            "SELECT COUNT(*) FROM log_entries WHERE test_session_id = ? AND timestamp_ms IS NULL",
+            [session_id],
+            |row| row.get(0),
+        )?;
 
-        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
-        let count_before: usize = self
-            .conn
-            .query_row(&count_query, &param_refs[..], |row| row.get(0))?;
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT timestamp FROM log_entries
+             WHERE test_session_id = ? AND timestamp_ms IS NULL LIMIT ?"
+        )?;
+        let samples = stmt
+            .query_map(params![session_id, sample_limit], |row| row.get(0))?
+            .collect::<SqlResult<Vec<String>>>()?;
 
-        // Calculate page number (1-indexed)
-        let page = (count_before / items_per_page) + 1;
-        Ok(Some(page))
+        Ok(UnparsedTimestamps { count, samples })
     }
 
-    pub fn get_session_log_levels(&self, session_id: &str) -> SqlResult<Vec<String>> {
+    /// Distinct thread names recorded for a session, for the thread filter
+    pub fn get_session_threads(&self, session_id: &str) -> SqlResult<Vec<String>> {
         let mut stmt = self.conn.prepare(
-            "SELECT DISTINCT level FROM log_entries WHERE test_session_id = ? ORDER BY level"
+            "SELECT DISTINCT thread FROM log_entries
+             WHERE test_session_id = ? AND thread IS NOT NULL ORDER BY thread"
         )?;
 
-        let level_iter = stmt.query_map([session_id], |row| row.get(0))?;
-        level_iter.collect()
+        let thread_iter = stmt.query_map([session_id], |row| row.get(0))?;
+        thread_iter.collect()
     }
 
     /// Get the page number for a specific log entry without filters (for search result jumping).
     ///
-    /// Counts entries that come before the target entry in the same session,
-    /// ordered by timestamp ASC, id ASC.
+    /// The entry's position in the session gives the page directly.
     pub fn find_entry_page_simple(
         &self,
         session_id: &str,
         entry_id: i64,
         items_per_page: usize,
     ) -> SqlResult<usize> {
-        // First get the timestamp for the target entry
-        let entry_timestamp: String = self.conn.query_row(
-            "SELECT timestamp FROM log_entries WHERE test_session_id = ? AND id = ?",
-            [session_id, &entry_id.to_string()],
+        let seq: usize = self.conn.query_row(
+            "SELECT seq FROM log_entries WHERE test_session_id = ? AND id = ?",
+            params![session_id, entry_id],
             |row| row.get(0),
         )?;
 
-        // Count entries before this one (same ordering as get_log_entries: timestamp ASC, id ASC)
-        let count: i64 = self.conn.query_row(
-            "SELECT COUNT(*) FROM log_entries
-             WHERE test_session_id = ? AND ((timestamp < ?) OR (timestamp = ? AND id < ?))",
-            [session_id, &entry_timestamp, &entry_timestamp, &entry_id.to_string()],
-            |row| row.get(0),
-        )?;
-
-        let page = (count as usize) / items_per_page + 1;
+        let page = seq / items_per_page + 1;
         Ok(page)
     }
 
     /// Query entries with failure anchor markers for a session.
     ///
-    /// Returns entry IDs where message ends with [FAIL] marker.
+    /// Returns IDs of entries flagged `is_failure`.
     fn query_failure_entries(&self, session_id: &str) -> SqlResult<Vec<i64>> {
         let mut stmt = self.conn.prepare(
             "SELECT id FROM log_entries
-             WHERE test_session_id = ? AND message LIKE '%[FAIL]'
-             ORDER BY timestamp ASC"
+             WHERE test_session_id = ? AND is_failure = 1
+             ORDER BY timestamp_ms ASC, id ASC"
         )?;
 
         let entry_iter = stmt.query_map([session_id], |row| {
@@ -545,7 +1189,8 @@ impl DatabaseManager {
         let query = "
             SELECT b.id, b.log_entry_id, b.title, b.notes, b.color,
                    e.id, e.test_session_id, e.file_path, e.file_index,
-                   e.timestamp, e.level, e.stack, e.message, e.line_number
+                   e.timestamp, e.level, e.stack, e.message, e.line_number,
+                   e.thread, e.module, e.source_line, e.timestamp_ms, e.is_failure, e.json_payload
             FROM bookmarks b
             JOIN log_entries e ON b.log_entry_id = e.id
             WHERE b.log_entry_id = ?
@@ -569,10 +1214,16 @@ impl DatabaseManager {
                 file_path: row.get(7)?,
                 file_index: row.get(8)?,
                 timestamp: row.get(9)?,
+                timestamp_ms: row.get(17)?,
                 level: row.get(10)?,
                 stack: row.get(11)?,
                 message: row.get(12)?,
                 line_number: row.get(13)?,
+                thread: row.get(14)?,
+                module: row.get(15)?,
+                source_line: row.get(16)?,
+                is_failure: row.get(18)?,
+                json_payload: row.get(19)?,
                 created_at: None,
             };
 
@@ -601,7 +1252,7 @@ impl DatabaseManager {
     /// This is called when switching to a session to ensure bookmarks are created.
     ///
     /// Priority order:
-    /// 1. Failure anchors ([FAIL] marker) - Red (#F56C6C), "Failure" title
+    /// 1. Failure anchors (`is_failure` flag) - Red (#F56C6C), "Failure" title
     /// 2. STEP entries (MARKER level with [STEP in message) - Dark Turquoise (#00CED1)
     /// 3. ###TEXT### pattern - Transparent background
     /// 4. MARKER level (other) - Transparent background
@@ -653,8 +1304,8 @@ impl DatabaseManager {
             WHERE e.test_session_id = ?
               AND e.level = 'MARKER'
               AND e.message LIKE '%[STEP%'
-              AND e.message NOT LIKE '%[FAIL]'
-            ORDER BY e.timestamp ASC
+              AND e.is_failure = 0
+            ORDER BY e.timestamp_ms ASC, e.id ASC
         ";
 
         {
@@ -711,9 +1362,9 @@ impl DatabaseManager {
             FROM log_entries e
             WHERE e.test_session_id = ?
               AND (e.message LIKE '%###%' OR e.level = 'MARKER')
-              AND e.message NOT LIKE '%[FAIL]'
+              AND e.is_failure = 0
               AND e.message NOT LIKE '%[STEP%'
-            ORDER BY e.timestamp ASC
+            ORDER BY e.timestamp_ms ASC, e.id ASC
         ";
 
         let mut stmt = self.conn.prepare(query)?;
@@ -793,3 +1444,105 @@ impl DatabaseManager {
         Ok(results)
     }
 }
+
+/// A session being written by [`DatabaseManager::begin_session_import`].
+///
+/// Nothing is visible to readers until [`finish`](Self::finish) commits;
+/// dropping the import instead rolls it back and leaves the database as it was.
+pub struct SessionImport<'a> {
+    tx: rusqlite::Transaction<'a>,
+    session_id: String,
+    /// Bookmarks of dropped files by file and line, waiting for the new entry there
+    moved_bookmarks: HashMap<(String, usize), Vec<MovedBookmark>>,
+    /// Entries were dropped, so positions are reassigned from the start
+    renumber: bool,
+    auto_bookmarks: usize,
+}
+
+impl SessionImport<'_> {
+    /// Drop the stored entries of a changed or removed file. Their bookmarks
+    /// move to the entries inserted afterwards at the same line if the
+    /// message there is the same.
+    pub fn drop_file(&mut self, file_path: &str) -> SqlResult<()> {
+        for moved in DatabaseManager::file_bookmarks(&self.tx, &self.session_id, file_path)? {
+            self.moved_bookmarks
+                .entry((moved.file_path.clone(), moved.line_number))
+                .or_default()
+                .push(moved);
+        }
+        DatabaseManager::delete_file_rows(&self.tx, &self.session_id, file_path)?;
+        self.renumber = true;
+        Ok(())
+    }
+
+    /// Insert a batch of entries of the session, returning their IDs in order
+    pub fn insert_entries(&mut self, entries: &[LogEntry]) -> SqlResult<Vec<i64>> {
+        let inserted_ids = DatabaseManager::insert_entry_rows(&self.tx, entries, &|| false)?.unwrap_or_default();
+        if !self.moved_bookmarks.is_empty() {
+            for (entry, entry_id) in entries.iter().zip(&inserted_ids) {
+                let key = (entry.file_path.clone(), entry.line_number);
+                let Some(moved) = self.moved_bookmarks.remove(&key) else {
+                    continue;
+                };
+                for bookmark in moved.iter().filter(|bookmark| bookmark.message == entry.message) {
+                    self.tx.execute(
+                        "INSERT INTO bookmarks (log_entry_id, title, notes, color, created_at) VALUES (?, ?, ?, ?, ?)",
+                        params![entry_id, &bookmark.title, &bookmark.notes, &bookmark.color, &bookmark.created_at],
+                    )?;
+                }
+            }
+        }
+        Ok(inserted_ids)
+    }
+
+    /// Insert a batch of newly parsed entries and bookmark their
+    /// `###MARKER###` entries, returning the entries with their IDs set.
+    /// Entries that already have a bookmark, such as one moved by a reload,
+    /// get no automatic one.
+    pub fn insert_parsed(&mut self, mut entries: Vec<LogEntry>) -> SqlResult<Vec<LogEntry>> {
+        let inserted_ids = self.insert_entries(&entries)?;
+        for (entry, entry_id) in entries.iter_mut().zip(inserted_ids) {
+            entry.id = Some(entry_id);
+        }
+
+        for (entry_id, title) in find_auto_bookmark_markers(&entries) {
+            let bookmarked: bool = self.tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM bookmarks WHERE log_entry_id = ?)",
+                [entry_id],
+                |row| row.get(0),
+            )?;
+            if bookmarked {
+                continue;
+            }
+            let bookmark = create_auto_bookmark(entry_id, title);
+            self.tx.execute(
+                "INSERT INTO bookmarks (log_entry_id, title, notes, color) VALUES (?, ?, ?, ?)",
+                params![bookmark.log_entry_id, &bookmark.title, &bookmark.notes, &bookmark.color],
+            )?;
+            self.auto_bookmarks += 1;
+        }
+        Ok(entries)
+    }
+
+    /// Automatic bookmarks added by [`insert_parsed`](Self::insert_parsed) so far
+    pub fn auto_bookmarks(&self) -> usize {
+        self.auto_bookmarks
+    }
+
+    /// Number the new entries, store the file list, attributes and counts of
+    /// `session` and commit
+    pub fn finish(self, session: &TestSession, files: &[FileReport]) -> SqlResult<()> {
+        let tx = self.tx;
+        DatabaseManager::number_entries(&tx, &self.session_id, self.renumber)?;
+        tx.execute("DELETE FROM import_files WHERE test_session_id = ?", [&self.session_id])?;
+        DatabaseManager::insert_import_files(&tx, &self.session_id, files)?;
+        DatabaseManager::insert_session_attributes(&tx, &self.session_id, &session.attributes)?;
+        tx.execute(
+            "UPDATE test_sessions
+             SET file_count = ?, total_entries = ?, incomplete = ?, last_parsed_at = datetime('now')
+             WHERE id = ?",
+            params![session.file_count, session.total_entries, session.incomplete, &self.session_id],
+        )?;
+        tx.commit()
+    }
+}
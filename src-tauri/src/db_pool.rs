@@ -0,0 +1,373 @@
+//! Shared connections to the log database
+//!
+//! The database runs in WAL mode, so readers see the last committed state
+//! while a write is in progress. One writer connection is shared behind a
+//! mutex by imports, followers and the commands that change data. A fixed
+//! set of read-only connections serves everything else. A long regex search
+//! or a large import therefore doesn't hold up pagination or the session list.
+//!
+//! The pool also remembers how many entries match each filter, so paging
+//! through a filtered session counts its entries once. A count is reused
+//! until the session's entries version changes.
+
+use crate::database::{DatabaseManager, EntryFilter, EntryPage, PageAnchor};
+use crate::migrations::MigrationError;
+use rusqlite::Result as SqlResult;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+/// Read connections opened by [`DbPool::open`]
+pub const DEFAULT_READERS: usize = 4;
+
+/// Filtered counts kept before the cache is cleared
+const MAX_CACHED_COUNTS: usize = 256;
+
+/// Session id and the filter's debug form
+type CountKey = (String, String);
+
+pub struct DbPool {
+    writer: Mutex<DatabaseManager>,
+    /// Idle read connections
+    readers: Mutex<Vec<DatabaseManager>>,
+    reader_returned: Condvar,
+    /// Filtered entry counts with the entries version they were taken at
+    counts: Mutex<HashMap<CountKey, (i64, usize)>>,
+}
+
+/// A read connection borrowed from the pool, returned to it when dropped
+pub struct PooledReader<'a> {
+    pool: &'a DbPool,
+    manager: Option<DatabaseManager>,
+}
+
+impl DbPool {
+    /// Open the writer, which sets up and migrates the database, and then
+    /// `readers` read-only connections
+    pub fn open(path: &str, readers: usize) -> Result<Self, MigrationError> {
+        let writer = DatabaseManager::new(path)?;
+        let readers = (0..readers.max(1))
+            .map(|_| DatabaseManager::open_reader(path))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            writer: Mutex::new(writer),
+            readers: Mutex::new(readers),
+            reader_returned: Condvar::new(),
+            counts: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Borrow a read connection, waiting for one to be returned if all are in use.
+    ///
+    /// Borrowing another connection while holding one can wait forever once
+    /// every connection is held that way, so commands take one at a time.
+    pub fn read(&self) -> PooledReader<'_> {
+        let mut idle = self.readers.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(manager) = idle.pop() {
+                return PooledReader { pool: self, manager: Some(manager) };
+            }
+            idle = self.reader_returned.wait(idle).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// The writer connection, held exclusively until the guard is dropped
+    pub fn write(&self) -> MutexGuard<'_, DatabaseManager> {
+        // A panic while writing rolls back its transaction, so the connection stays usable
+        self.writer.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A page of a session's entries together with the number of entries matching `filter`
+    pub fn entries_page(
+        &self,
+        session_id: &str,
+        anchor: PageAnchor,
+        limit: usize,
+        filter: &EntryFilter,
+    ) -> SqlResult<Option<EntryPage>> {
+        let reader = self.read();
+        let total = self.count_entries(&reader, session_id, filter)?;
+        reader.get_entries_page(session_id, anchor, limit, filter, total)
+    }
+
+    fn count_entries(&self, reader: &DatabaseManager, session_id: &str, filter: &EntryFilter) -> SqlResult<usize> {
+        if filter.is_empty() {
+            return reader.count_entries(session_id, filter);
+        }
+
+        // The version is read before counting, so a write landing in between
+        // files the new count under the old version and is counted again next time
+        let key = (session_id.to_string(), format!("{:?}", filter));
+        let version = reader.entries_version(session_id)?;
+        if let Some(&(cached_version, count)) = self.lock_counts().get(&key) {
+            if cached_version == version {
+                return Ok(count);
+            }
+        }
+
+        let count = reader.count_entries(session_id, filter)?;
+        let mut counts = self.lock_counts();
+        if counts.len() >= MAX_CACHED_COUNTS {
+            counts.clear();
+        }
+        counts.insert(key, (version, count));
+        Ok(count)
+    }
+
+    fn lock_counts(&self) -> MutexGuard<'_, HashMap<CountKey, (i64, usize)>> {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Deref for PooledReader<'_> {
+    type Target = DatabaseManager;
+
+    fn deref(&self) -> &DatabaseManager {
+        self.manager.as_ref().expect("reader is only taken on drop")
+    }
+}
+
+impl Drop for PooledReader<'_> {
+    fn drop(&mut self) {
+        if let Some(manager) = self.manager.take() {
+            self.pool.readers.lock().unwrap_or_else(|e| e.into_inner()).push(manager);
+            self.pool.reader_returned.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::EntryCursor;
+    use crate::log_parser::{LogEntry, TestSession};
+
+    fn session(id: &str) -> TestSession {
+        TestSession {
+            id: id.to_string(),
+            name: id.to_string(),
+            directory_path: "/logs".to_string(),
+            file_count: 0,
+            total_entries: 0,
+            created_at: None,
+            last_parsed_at: None,
+            source_type: None,
+            incomplete: false,
+            attributes: Default::default(),
+        }
+    }
+
+    fn entry(session_id: &str, timestamp_ms: Option<i64>, level: &str, message: &str) -> LogEntry {
+        LogEntry {
+            id: None,
+            test_session_id: session_id.to_string(),
+            file_path: "run.html".to_string(),
+            file_index: 0,
+            timestamp: String::new(),
+            timestamp_ms,
+            level: level.to_string(),
+            stack: String::new(),
+            message: message.to_string(),
+            line_number: 0,
+            thread: None,
+            module: None,
+            source_line: None,
+            is_failure: false,
+            json_payload: None,
+            created_at: None,
+        }
+    }
+
+    fn messages(page: &EntryPage) -> Vec<&str> {
+        page.entries.iter().map(|entry| entry.message.as_str()).collect()
+    }
+
+    fn cursor(entry: &LogEntry) -> EntryCursor {
+        EntryCursor { timestamp_ms: entry.timestamp_ms, id: entry.id.unwrap() }
+    }
+
+    /// A session whose entries `m0`..`m9` are inserted out of display order
+    fn paged_pool(dir: &tempfile::TempDir) -> DbPool {
+        let path = dir.path().join("pool.db");
+        let pool = DbPool::open(path.to_str().unwrap(), 1).unwrap();
+        let mut writer = pool.write();
+        writer.create_test_session(&session("s")).unwrap();
+        let entries: Vec<LogEntry> = [5, 2, 9, 0, 7, 3, 8, 1, 6, 4]
+            .into_iter()
+            .map(|i| {
+                // m0 has no parsed timestamp and sorts first
+                let timestamp_ms = (i > 0).then_some(1000 + i);
+                let level = if i % 2 == 0 { "ERROR" } else { "INFO" };
+                entry("s", timestamp_ms, level, &format!("m{}", i))
+            })
+            .collect();
+        writer.insert_entries(&entries).unwrap();
+        drop(writer);
+        pool
+    }
+
+    #[test]
+    fn test_pages_by_position_cursor_and_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = paged_pool(&dir);
+        let all = EntryFilter::default();
+
+        let page = pool.entries_page("s", PageAnchor::Position { position: 3 }, 3, &all).unwrap().unwrap();
+        assert_eq!(messages(&page), vec!["m3", "m4", "m5"]);
+        assert_eq!((page.total, page.position), (10, Some(3)));
+
+        let after = PageAnchor::After { cursor: cursor(page.entries.last().unwrap()) };
+        let next = pool.entries_page("s", after, 3, &all).unwrap().unwrap();
+        assert_eq!(messages(&next), vec!["m6", "m7", "m8"]);
+        assert_eq!(next.position, Some(6));
+
+        let before = PageAnchor::Before { cursor: cursor(&page.entries[0]) };
+        let previous = pool.entries_page("s", before, 3, &all).unwrap().unwrap();
+        assert_eq!(messages(&previous), vec!["m0", "m1", "m2"]);
+
+        let m7 = next.entries[1].id.unwrap();
+        let holding = pool.entries_page("s", PageAnchor::Entry { entry_id: m7 }, 4, &all).unwrap().unwrap();
+        assert_eq!(messages(&holding), vec!["m4", "m5", "m6", "m7"]);
+        assert_eq!(holding.position, Some(4));
+        assert!(pool.entries_page("s", PageAnchor::Entry { entry_id: -1 }, 4, &all).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cursor_survives_deleted_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = paged_pool(&dir);
+        let all = EntryFilter::default();
+        let page = pool.entries_page("s", PageAnchor::Position { position: 4 }, 1, &all).unwrap().unwrap();
+        let m4 = cursor(&page.entries[0]);
+
+        // Reloading the file drops every entry and inserts them again
+        let mut reloaded = session("s");
+        reloaded.total_entries = 9;
+        let entries: Vec<LogEntry> = (0..10)
+            .filter(|&i| i != 4)
+            .map(|i| entry("s", (i > 0).then_some(1000 + i), "INFO", &format!("m{}", i)))
+            .collect();
+        let mut db = pool.write();
+        let mut import = db.begin_session_import(&reloaded, true).unwrap();
+        import.drop_file("run.html").unwrap();
+        import.insert_entries(&entries).unwrap();
+        import.finish(&reloaded, &[]).unwrap();
+        drop(db);
+
+        let next = pool.entries_page("s", PageAnchor::After { cursor: m4 }, 2, &all).unwrap().unwrap();
+        assert_eq!(messages(&next), vec!["m5", "m6"]);
+        assert_eq!((next.total, next.position), (9, Some(4)));
+        let previous = pool.entries_page("s", PageAnchor::Before { cursor: m4 }, 2, &all).unwrap().unwrap();
+        assert_eq!(messages(&previous), vec!["m2", "m3"]);
+    }
+
+    #[test]
+    fn test_filtered_pages_and_cached_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = paged_pool(&dir);
+        let errors = EntryFilter { levels: Some(vec!["ERROR".to_string()]), ..Default::default() };
+
+        let first = pool.entries_page("s", PageAnchor::Position { position: 0 }, 2, &errors).unwrap().unwrap();
+        assert_eq!(messages(&first), vec!["m0", "m2"]);
+        assert_eq!(first.total, 5);
+        // The last page is read back from the end
+        let last = pool.entries_page("s", PageAnchor::Position { position: 4 }, 2, &errors).unwrap().unwrap();
+        assert_eq!(messages(&last), vec!["m8"]);
+
+        let after = PageAnchor::After { cursor: cursor(&first.entries[1]) };
+        let next = pool.entries_page("s", after, 2, &errors).unwrap().unwrap();
+        assert_eq!(messages(&next), vec!["m4", "m6"]);
+        assert_eq!(next.position, None);
+
+        let m6 = next.entries[1].id.unwrap();
+        let holding = pool.entries_page("s", PageAnchor::Entry { entry_id: m6 }, 2, &errors).unwrap().unwrap();
+        assert_eq!(holding.position, Some(2));
+        assert_eq!(pool.counts.lock().unwrap().len(), 1);
+
+        // New entries bump the session's entries version, so the count is taken again
+        pool.write().insert_entries(&[entry("s", Some(1100), "ERROR", "m10")]).unwrap();
+        let first = pool.entries_page("s", PageAnchor::Position { position: 0 }, 2, &errors).unwrap().unwrap();
+        assert_eq!(first.total, 6);
+    }
+
+    #[test]
+    fn test_session_import_is_written_on_finish() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pool.db");
+        let pool = DbPool::open(path.to_str().unwrap(), 1).unwrap();
+        let batch = |from: i64| -> Vec<LogEntry> {
+            (from..from + 3).map(|i| entry("s", Some(1000 + i), "INFO", &format!("m{}", i))).collect()
+        };
+
+        // Dropped before finishing: nothing is stored
+        let mut writer = pool.write();
+        let mut import = writer.begin_session_import(&session("s"), false).unwrap();
+        import.insert_parsed(batch(0)).unwrap();
+        drop(import);
+        drop(writer);
+        assert!(pool.read().get_sessions().unwrap().is_empty());
+
+        let mut writer = pool.write();
+        let mut import = writer.begin_session_import(&session("s"), false).unwrap();
+        let inserted = import.insert_parsed(batch(3)).unwrap();
+        assert!(inserted.iter().all(|entry| entry.id.is_some()));
+        import.insert_parsed(batch(0)).unwrap();
+        // Readers see the session only once it is committed
+        assert!(pool.read().get_sessions().unwrap().is_empty());
+        let mut finished = session("s");
+        finished.total_entries = 6;
+        import.finish(&finished, &[]).unwrap();
+        drop(writer);
+
+        let page = pool.entries_page("s", PageAnchor::Position { position: 0 }, 10, &EntryFilter::default()).unwrap().unwrap();
+        assert_eq!(messages(&page), vec!["m0", "m1", "m2", "m3", "m4", "m5"]);
+        assert_eq!(pool.read().get_sessions().unwrap()[0].total_entries, 6);
+    }
+
+    #[test]
+    fn test_readers_run_while_writer_is_held() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pool.db");
+        let pool = DbPool::open(path.to_str().unwrap(), 2).unwrap();
+
+        pool.write().create_test_session(&session("a")).unwrap();
+
+        let writer = pool.write();
+        writer.create_test_session(&session("b")).unwrap();
+        // Both readers are usable while the writer is taken
+        let first = pool.read();
+        let second = pool.read();
+        assert_eq!(first.get_sessions().unwrap().len(), 2);
+        assert_eq!(second.get_sessions().unwrap().len(), 2);
+        drop(writer);
+
+        drop(first);
+        drop(second);
+        assert_eq!(pool.readers.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_readers_cannot_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pool.db");
+        let pool = DbPool::open(path.to_str().unwrap(), 1).unwrap();
+
+        assert!(pool.read().create_test_session(&session("a")).is_err());
+    }
+
+    #[test]
+    fn test_read_waits_for_a_returned_reader() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pool.db");
+        let pool = DbPool::open(path.to_str().unwrap(), 1).unwrap();
+
+        let held = pool.read();
+        std::thread::scope(|scope| {
+            let waiting = scope.spawn(|| pool.read().get_sessions().unwrap().len());
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            drop(held);
+            assert_eq!(waiting.join().unwrap(), 0);
+        });
+    }
+}
@@ -0,0 +1,207 @@
+//! Character encoding of log sources
+//!
+//! Sources are transcoded to UTF-8 before any format parses them. The
+//! encoding comes from a byte order mark, then from an HTML `<meta charset>`,
+//! and otherwise is UTF-8 if the start of the source is valid UTF-8 or a
+//! statistical guess if it is not. Bytes that are invalid in the chosen
+//! encoding become U+FFFD instead of failing the file.
+
+use chardetng::EncodingDetector;
+use encoding_rs::{CoderResult, Decoder, Encoding, UTF_8};
+use regex::bytes::Regex;
+use std::borrow::Cow;
+use std::io::{self, Read};
+use std::sync::OnceLock;
+
+/// Number of leading bytes examined to choose an encoding
+pub const ENCODING_SNIFF_SIZE: usize = 64 * 1024;
+
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+fn meta_charset_regex() -> &'static Regex {
+    static META: OnceLock<Regex> = OnceLock::new();
+    // Covers both <meta charset="x"> and <meta http-equiv=... content="text/html; charset=x">
+    META.get_or_init(|| Regex::new(r#"(?i)<meta\b[^>]*?\bcharset\s*=\s*["']?([A-Za-z0-9_.:-]+)"#).unwrap())
+}
+
+fn is_html_name(file_name: &str) -> bool {
+    let lower = file_name.to_ascii_lowercase();
+    lower.ends_with(".html") || lower.ends_with(".htm")
+}
+
+/// Whether `bytes` is valid UTF-8, allowing a character cut off at the end
+fn is_utf8_prefix(bytes: &[u8]) -> bool {
+    match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+/// Encoding of a source named `file_name` whose first bytes are `prefix`.
+///
+/// `at_eof` tells whether `prefix` is the whole source, which lets the
+/// detector judge a trailing multi-byte sequence.
+pub fn detect_encoding(file_name: &str, prefix: &[u8], at_eof: bool) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(prefix) {
+        return encoding;
+    }
+
+    if is_html_name(file_name) {
+        let declared = meta_charset_regex()
+            .captures(prefix)
+            .and_then(|caps| Encoding::for_label(&caps[1]));
+        if let Some(encoding) = declared {
+            // A page cannot really be UTF-16 if its <meta> could be read as ASCII
+            return encoding.output_encoding();
+        }
+    }
+
+    if is_utf8_prefix(prefix) {
+        return UTF_8;
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(prefix, at_eof);
+    detector.guess(None, true)
+}
+
+/// `head` decoded to UTF-8 with the encoding it appears to use, for format detection
+pub fn decode_head<'a>(file_name: &str, head: &'a [u8]) -> Cow<'a, [u8]> {
+    let encoding = detect_encoding(file_name, head, false);
+    if encoding == UTF_8 && Encoding::for_bom(head).is_none() && std::str::from_utf8(head).is_ok() {
+        return Cow::Borrowed(head);
+    }
+    let (text, _, _) = encoding.decode(head);
+    Cow::Owned(text.into_owned().into_bytes())
+}
+
+/// Reader yielding the UTF-8 transcoding of `inner`; a byte order mark is
+/// removed and malformed input is replaced with U+FFFD
+pub struct DecodingReader<R: Read> {
+    inner: R,
+    decoder: Decoder,
+    input: Vec<u8>,
+    input_start: usize,
+    input_end: usize,
+    output: Vec<u8>,
+    output_start: usize,
+    output_end: usize,
+    input_done: bool,
+    finished: bool,
+}
+
+impl<R: Read> DecodingReader<R> {
+    pub fn new(inner: R, encoding: &'static Encoding) -> Self {
+        Self {
+            inner,
+            decoder: encoding.new_decoder_with_bom_removal(),
+            input: vec![0; READ_CHUNK_SIZE],
+            input_start: 0,
+            input_end: 0,
+            output: Vec::new(),
+            output_start: 0,
+            output_end: 0,
+            input_done: false,
+            finished: false,
+        }
+    }
+
+    /// Decode the next chunk of input into `output`
+    fn fill(&mut self) -> io::Result<()> {
+        if self.input_start == self.input_end && !self.input_done {
+            let n = loop {
+                match self.inner.read(&mut self.input) {
+                    Ok(n) => break n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            };
+            self.input_start = 0;
+            self.input_end = n;
+            self.input_done = n == 0;
+        }
+
+        let pending = &self.input[self.input_start..self.input_end];
+        let capacity = self
+            .decoder
+            .max_utf8_buffer_length(pending.len())
+            .unwrap_or(READ_CHUNK_SIZE * 3)
+            .max(4);
+        self.output.resize(capacity, 0);
+
+        let (result, read, written, _) = self.decoder.decode_to_utf8(pending, &mut self.output, self.input_done);
+        self.input_start += read;
+        self.output_start = 0;
+        self.output_end = written;
+        if self.input_done && result == CoderResult::InputEmpty {
+            self.finished = true;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.output_start == self.output_end {
+            if self.finished {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+
+        let n = buf.len().min(self.output_end - self.output_start);
+        buf[..n].copy_from_slice(&self.output[self.output_start..self.output_start + n]);
+        self.output_start += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(encoding: &'static Encoding, bytes: &[u8]) -> String {
+        let mut text = String::new();
+        DecodingReader::new(bytes, encoding).read_to_string(&mut text).unwrap();
+        text
+    }
+
+    #[test]
+    fn test_detect_encoding() {
+        assert_eq!(detect_encoding("a.log", b"\xEF\xBB\xBFplain", false), UTF_8);
+        assert_eq!(detect_encoding("a.log", b"\xFF\xFEa\x00", false).name(), "UTF-16LE");
+        assert_eq!(
+            detect_encoding("a.html", br#"<html><head><meta charset="gbk"></head>"#, false).name(),
+            "GBK"
+        );
+        assert_eq!(
+            detect_encoding(
+                "a.htm",
+                br#"<META http-equiv="Content-Type" content="text/html; charset=ISO-8859-1">"#,
+                false
+            )
+            .name(),
+            "windows-1252"
+        );
+        // Only HTML files are checked for <meta>
+        assert_eq!(detect_encoding("a.log", br#"<meta charset="gbk">"#, false), UTF_8);
+        // A character cut off at the end of the sniffed bytes is still UTF-8
+        assert_eq!(detect_encoding("a.log", "温度".as_bytes().split_at(4).0, false), UTF_8);
+
+        let (gbk, _, _) = encoding_rs::GBK.encode("设备温度过高，正在重启控制器。设备温度过高，正在重启控制器。");
+        assert_eq!(detect_encoding("a.log", &gbk, true).name(), "GBK");
+    }
+
+    #[test]
+    fn test_decoding_reader() {
+        let (latin1, _, _) = encoding_rs::WINDOWS_1252.encode("Gerät gestartet: 25 °C");
+        assert_eq!(decode_all(encoding_rs::WINDOWS_1252, &latin1), "Gerät gestartet: 25 °C");
+
+        assert_eq!(decode_all(UTF_8, b"\xEF\xBB\xBFok \xFF done"), "ok \u{FFFD} done");
+
+        // Longer than one read chunk, with characters straddling the boundaries
+        let long = "é中".repeat(READ_CHUNK_SIZE);
+        let (gbk, _, _) = encoding_rs::GBK.encode(&long);
+        assert_eq!(decode_all(encoding_rs::GBK, &gbk), long);
+    }
+}
@@ -0,0 +1,101 @@
+//! Size, modification time and content hash of imported files
+//!
+//! Every session remembers the fingerprint of each file it was built from, so
+//! reloading a directory only re-parses files that were added or changed.
+//! Size and mtime are checked first; the content is hashed only when they
+//! differ from the stored values.
+
+use crate::archive;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read};
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileFingerprint {
+    /// Size of the file on disk; for archive members, of the archive
+    pub size: u64,
+    /// Modification time in epoch milliseconds; for archive members, of the archive
+    pub mtime_ms: i64,
+    /// Hex SHA-256 of the (decompressed) content
+    pub sha256: String,
+}
+
+impl FileFingerprint {
+    /// Fingerprint `file_path`, reusing the hash of `previous` when size and
+    /// mtime still match it
+    pub fn take(file_path: &str, previous: Option<&FileFingerprint>) -> io::Result<Self> {
+        Self::take_with(file_path, previous, || archive::open_source(file_path))
+    }
+
+    /// Like [`take`](Self::take), reading the content from `open` if it has
+    /// to be hashed
+    pub fn take_with<R: Read>(
+        file_path: &str,
+        previous: Option<&FileFingerprint>,
+        open: impl FnOnce() -> io::Result<R>,
+    ) -> io::Result<Self> {
+        let on_disk = archive::split_virtual_path(file_path).map_or(file_path, |(archive, _)| archive);
+        let metadata = fs::metadata(on_disk)?;
+        let size = metadata.len();
+        let mtime_ms = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+
+        if let Some(previous) = previous.filter(|p| p.size == size && p.mtime_ms == mtime_ms) {
+            return Ok(previous.clone());
+        }
+
+        Ok(Self {
+            size,
+            mtime_ms,
+            sha256: content_hash(open()?)?,
+        })
+    }
+
+    /// Whether both fingerprints were taken from the same content
+    pub fn same_content(&self, other: &FileFingerprint) -> bool {
+        self.sha256 == other.sha256
+    }
+}
+
+fn content_hash(mut reader: impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_reuses_and_detects_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("T_ID_1---0.html");
+        let path_str = path.to_str().unwrap();
+        fs::write(&path, "first").unwrap();
+
+        let first = FileFingerprint::take(path_str, None).unwrap();
+        assert_eq!(first.size, 5);
+        assert_eq!(first.sha256.len(), 64);
+
+        // Unchanged size and mtime: the stored hash is trusted without reading
+        let stale = FileFingerprint { sha256: "stored".to_string(), ..first.clone() };
+        assert_eq!(FileFingerprint::take(path_str, Some(&stale)).unwrap().sha256, "stored");
+
+        fs::write(&path, "second!").unwrap();
+        let second = FileFingerprint::take(path_str, Some(&first)).unwrap();
+        assert_eq!(second.size, 7);
+        assert!(!second.same_content(&first));
+    }
+}
@@ -0,0 +1,270 @@
+//! Following a local directory while a test is still writing its logs
+//!
+//! A [`SessionFollower`] keeps a loaded session in step with its directory.
+//! Filesystem notifications trigger a sync, which parses the files of the
+//! session that changed and any new `---N` parts as still being written (see
+//! [`ParseContext::growing`](crate::log_formats::ParseContext::growing)) and
+//! stores the entries past those already in the database. The directory is
+//! only scanned again when files were created, removed or renamed. A row whose markup
+//! is not complete yet, such as the last row of a table still missing its
+//! `</table>`, is picked up by a later sync.
+
+use crate::archive;
+use crate::db_pool::DbPool;
+use crate::fingerprint::FileFingerprint;
+use crate::import_report::{is_incomplete, FileReport};
+use crate::log_formats::FormatRegistry;
+use crate::log_parser::{LogEntry, TestSession};
+use crate::session_attributes::{AttributeExtractors, ATTRIBUTE_ENTRY_LIMIT};
+use crate::settings;
+use crate::timestamp::TimestampParser;
+use notify::event::ModifyKind;
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Quiet time after a filesystem event before syncing, so a burst of writes
+/// is parsed once
+const SETTLE_DELAY: Duration = Duration::from_millis(300);
+
+/// How often the watch loop checks whether it should stop
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Entries added to a followed session by one sync
+#[derive(Debug, Clone, Serialize)]
+pub struct FollowUpdate {
+    pub session_id: String,
+    pub file_count: usize,
+    pub total_entries: usize,
+    /// New entries with their database IDs, in file then row order
+    pub entries: Vec<LogEntry>,
+}
+
+pub struct SessionFollower {
+    db: Arc<DbPool>,
+    session: TestSession,
+    formats: FormatRegistry,
+    timestamps: TimestampParser,
+    extractors: AttributeExtractors,
+    /// Files of the session as of the last directory scan
+    group: Vec<String>,
+    /// Files were added or removed since that scan
+    rescan: bool,
+    /// Report of each file as of the last sync, with its fingerprint
+    files: HashMap<String, FileReport>,
+    /// Highest stored line number of each file
+    line_marks: HashMap<String, usize>,
+    /// Files parsed as still growing, which the final sync parses once more
+    /// for the rows held back
+    growing_files: HashSet<String>,
+}
+
+impl SessionFollower {
+    /// Prepare to follow a session imported from a local directory
+    pub fn new(db: Arc<DbPool>, session_id: &str) -> Result<Self, String> {
+        let db_manager = db.read();
+        let session = db_manager
+            .get_sessions()
+            .map_err(|e| format!("Failed to get sessions: {}", e))?
+            .into_iter()
+            .find(|s| s.id == session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+
+        if session.source_type.as_deref() == Some("http") {
+            return Err("Only sessions loaded from a local directory can be followed".to_string());
+        }
+        if archive::split_virtual_path(&session.directory_path).is_some() {
+            return Err("Sessions loaded from an archive cannot be followed".to_string());
+        }
+
+        let files = db_manager
+            .get_import_report(session_id)
+            .map_err(|e| format!("Failed to get import report: {}", e))?
+            .files
+            .into_iter()
+            .map(|file| (file.file_path.clone(), file))
+            .collect();
+        let line_marks = db_manager
+            .get_file_line_marks(session_id)
+            .map_err(|e| format!("Failed to read stored entries: {}", e))?;
+        drop(db_manager);
+
+        let app_settings = settings::load_settings();
+        Ok(Self {
+            db,
+            session,
+            formats: app_settings.format_registry(),
+            timestamps: app_settings.timestamp_parser(),
+            extractors: app_settings.attribute_extractors(),
+            group: Vec::new(),
+            rescan: true,
+            files,
+            line_marks,
+            growing_files: HashSet::new(),
+        })
+    }
+
+    /// Parse what changed since the last sync and store the new entries.
+    ///
+    /// With `growing` set, trailing rows that may be incomplete are held
+    /// back; without it, files that were parsed as growing are parsed once
+    /// more in full. Returns `None` when nothing changed.
+    pub fn sync(&mut self, growing: bool) -> Result<Option<FollowUpdate>, String> {
+        if self.rescan {
+            let mut groups = self
+                .formats
+                .scan_directory(&self.session.directory_path)
+                .map_err(|e| format!("Failed to scan directory: {}", e))?
+                .groups;
+            self.group = groups.remove(&self.session.name).unwrap_or_default();
+            self.rescan = false;
+        }
+        let group = self.group.clone();
+
+        let mut new_entries = Vec::new();
+        let mut changed = false;
+
+        for (index, file_path) in group.iter().enumerate() {
+            let previous = self.files.get(file_path).and_then(|f| f.fingerprint.as_ref());
+            let Ok(fingerprint) = FileFingerprint::take(file_path, previous) else {
+                // Removed or unreadable for the moment; try again next time
+                continue;
+            };
+            let unchanged = previous.is_some_and(|p| p.same_content(&fingerprint));
+            if unchanged && (growing || !self.growing_files.contains(file_path)) {
+                continue;
+            }
+
+            // Rows already stored are passed over as they stream by
+            let mark = self.line_marks.get(file_path).copied();
+            let mut added = Vec::new();
+            let mut header_entries = Vec::new();
+            let streamed = self.formats.stream_followed_file_with_report(
+                file_path,
+                &self.session.id,
+                index,
+                &self.timestamps,
+                growing,
+                &mut |batch| {
+                    if index == 0 {
+                        let wanted = ATTRIBUTE_ENTRY_LIMIT.saturating_sub(header_entries.len());
+                        header_entries.extend(batch.iter().take(wanted).cloned());
+                    }
+                    added.extend(batch.into_iter().filter(|entry| mark.is_none_or(|m| entry.line_number > m)));
+                    Ok(())
+                },
+            );
+            if let Some(e) = &streamed.report.error {
+                log::warn!("[FOLLOW] Failed to parse {}: {}", file_path, e);
+                continue;
+            }
+
+            if index == 0 {
+                let attributes = self.extractors.extract(
+                    &self.session.directory_path,
+                    streamed.title.as_deref(),
+                    &header_entries,
+                );
+                self.session.attributes.extend(attributes);
+            }
+
+            if let Some(last) = added.last() {
+                self.line_marks.insert(file_path.clone(), last.line_number);
+            }
+            new_entries.extend(added);
+
+            if growing {
+                self.growing_files.insert(file_path.clone());
+            } else {
+                self.growing_files.remove(file_path);
+            }
+            let mut report = streamed.report;
+            report.fingerprint = Some(fingerprint);
+            self.files.insert(file_path.clone(), report);
+            changed = true;
+        }
+
+        if !changed {
+            return Ok(None);
+        }
+
+        let reports: Vec<FileReport> = group.iter().filter_map(|path| self.files.get(path).cloned()).collect();
+        self.session.file_count = group.len();
+        self.session.total_entries += new_entries.len();
+        self.session.incomplete = is_incomplete(&reports);
+
+        // Auto-bookmarks are written in the same transaction as their entries
+        let write_error = |e: rusqlite::Error| format!("Failed to store followed entries: {}", e);
+        let mut db_manager = self.db.write();
+        let mut import = db_manager.begin_session_import(&self.session, true).map_err(write_error)?;
+        let new_entries = import.insert_parsed(new_entries).map_err(write_error)?;
+        import.finish(&self.session, &reports).map_err(write_error)?;
+
+        Ok(Some(FollowUpdate {
+            session_id: self.session.id.clone(),
+            file_count: self.session.file_count,
+            total_entries: self.session.total_entries,
+            entries: new_entries,
+        }))
+    }
+
+    /// Watch the session's directory until `stop` is set, syncing after
+    /// every burst of changes and once more, in full, before returning
+    pub fn run(mut self, stop: &AtomicBool, on_update: &dyn Fn(FollowUpdate)) -> Result<(), String> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event {
+                // Whether the set of files may have changed, not just their content
+                let rescan = match event.kind {
+                    EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_)) => true,
+                    EventKind::Modify(_) => false,
+                    _ => return,
+                };
+                let _ = tx.send(rescan);
+            }
+        })
+        .map_err(|e| format!("Failed to watch directory: {}", e))?;
+        watcher
+            .watch(Path::new(&self.session.directory_path), RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", self.session.directory_path, e))?;
+        log::info!("[FOLLOW] Following {} in {}", self.session.name, self.session.directory_path);
+
+        // Catch up with anything written since the session was imported
+        let sync_and_notify = |follower: &mut Self, growing: bool| match follower.sync(growing) {
+            Ok(Some(update)) => on_update(update),
+            Ok(None) => {}
+            Err(e) => log::warn!("[FOLLOW] Sync of {} failed: {}", follower.session.name, e),
+        };
+        sync_and_notify(&mut self, true);
+
+        while !stop.load(Ordering::Relaxed) {
+            match rx.recv_timeout(STOP_POLL_INTERVAL) {
+                Ok(rescan) => {
+                    thread::sleep(SETTLE_DELAY);
+                    self.rescan |= rescan;
+                    while let Ok(rescan) = rx.try_recv() {
+                        self.rescan |= rescan;
+                    }
+                    sync_and_notify(&mut self, true);
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        drop(watcher);
+        log::info!("[FOLLOW] Stopped following {}", self.session.name);
+        // Events after the last sync are no longer delivered
+        self.rescan = true;
+        if let Some(update) = self.sync(false)? {
+            on_update(update);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+//! User-defined rules for grouping log files into test sessions
+//!
+//! Rules are tried in order before the built-in `<Name>_ID_<n>---<k>` naming
+//! scheme. Each rule yields a test name and, optionally, the position of the
+//! file within its session.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A grouping rule as stored in the settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GroupingRule {
+    /// Regex with a `test` capture and an optional `index` capture, matched
+    /// against the file path (with `/` separators)
+    Regex { pattern: String },
+    /// Path template such as `{suite}/{test}/part_{index}.html`.
+    ///
+    /// `{test}` and `{index}` are captured, any other `{name}` matches one
+    /// path segment and `*` matches any run of characters within a segment.
+    /// The template is matched against the end of the path.
+    Template { template: String },
+}
+
+impl GroupingRule {
+    /// Compile the rule into a regex, checking it captures `test`
+    pub fn compile(&self) -> Result<Regex, String> {
+        let (source, pattern) = match self {
+            Self::Regex { pattern } => (pattern.as_str(), pattern.clone()),
+            Self::Template { template } => (template.as_str(), template_to_regex(template)?),
+        };
+
+        let regex = Regex::new(&pattern)
+            .map_err(|e| format!("Invalid grouping rule '{}': {}", source, e))?;
+        if !regex.capture_names().flatten().any(|name| name == "test") {
+            return Err(format!("Grouping rule '{}' must capture 'test'", source));
+        }
+        Ok(regex)
+    }
+}
+
+fn template_to_regex(template: &str) -> Result<String, String> {
+    let mut pattern = String::from("(?:^|/)");
+    let mut rest = template;
+
+    while let Some(pos) = rest.find(['{', '*']) {
+        pattern.push_str(&regex::escape(&rest[..pos]));
+        if rest[pos..].starts_with('*') {
+            pattern.push_str("[^/]*");
+            rest = &rest[pos + 1..];
+            continue;
+        }
+
+        let end = rest[pos..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in template '{}'", template))?;
+        match &rest[pos + 1..pos + end] {
+            "test" => pattern.push_str("(?P<test>[^/]+?)"),
+            "index" => pattern.push_str(r"(?P<index>\d+)"),
+            _ => pattern.push_str("[^/]+?"),
+        }
+        rest = &rest[pos + end + 1..];
+    }
+
+    pattern.push_str(&regex::escape(rest));
+    pattern.push('$');
+    Ok(pattern)
+}
+
+/// Test session and position a file was assigned to
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileGroup {
+    pub test: String,
+    pub index: Option<usize>,
+}
+
+/// Compiled grouping rules
+#[derive(Default)]
+pub struct SessionGrouper {
+    rules: Vec<Regex>,
+}
+
+impl SessionGrouper {
+    /// Compile `rules`, skipping invalid ones with a warning; settings are
+    /// validated on save, so this only happens for hand-edited files
+    pub fn new(rules: &[GroupingRule]) -> Self {
+        let rules = rules
+            .iter()
+            .filter_map(|rule| match rule.compile() {
+                Ok(regex) => Some(regex),
+                Err(e) => {
+                    log::warn!("Skipping grouping rule: {}", e);
+                    None
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Group for the first rule matching `path`
+    ///
+    /// # Examples
+    /// ```
+    /// use logterminator_lib::grouping::{GroupingRule, SessionGrouper};
+    /// let grouper = SessionGrouper::new(&[GroupingRule::Template {
+    ///     template: "{suite}/{test}/part_{index}.html".to_string(),
+    /// }]);
+    /// let group = grouper.match_path("/ci/smoke/TestLogin/part_2.html").unwrap();
+    /// assert_eq!(group.test, "TestLogin");
+    /// assert_eq!(group.index, Some(2));
+    /// ```
+    pub fn match_path(&self, path: &str) -> Option<FileGroup> {
+        let path = path.replace('\\', "/");
+        self.rules.iter().find_map(|regex| {
+            let caps = regex.captures(&path)?;
+            let test = caps.name("test")?.as_str();
+            if test.is_empty() {
+                return None;
+            }
+            Some(FileGroup {
+                test: test.to_string(),
+                index: caps.name("index").and_then(|m| m.as_str().parse().ok()),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regex_rule() {
+        let grouper = SessionGrouper::new(&[GroupingRule::Regex {
+            pattern: r"(?P<test>[^/]+)-run(?P<index>\d+)\.html$".to_string(),
+        }]);
+
+        let group = grouper.match_path("logs/TestBoot-run3.html").unwrap();
+        assert_eq!(group, FileGroup { test: "TestBoot".to_string(), index: Some(3) });
+        assert!(grouper.match_path("logs/TestBoot.html").is_none());
+    }
+
+    #[test]
+    fn test_template_rule() {
+        let grouper = SessionGrouper::new(&[GroupingRule::Template {
+            template: "{test}-run{index}.*".to_string(),
+        }]);
+
+        let group = grouper.match_path("C:\\logs\\TestBoot-run3.html").unwrap();
+        assert_eq!(group.test, "TestBoot");
+        assert_eq!(group.index, Some(3));
+
+        // Literal characters in the template are not regex syntax
+        let grouper = SessionGrouper::new(&[GroupingRule::Template {
+            template: "{test}(1).log".to_string(),
+        }]);
+        assert!(grouper.match_path("a/TestX(1).log").is_some());
+        assert!(grouper.match_path("a/TestX1.log").is_none());
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        let no_test = GroupingRule::Regex { pattern: r"(?P<index>\d+)".to_string() };
+        assert!(no_test.compile().is_err());
+
+        let unclosed = GroupingRule::Template { template: "{test/part.html".to_string() };
+        assert!(unclosed.compile().is_err());
+
+        let grouper = SessionGrouper::new(&[no_test]);
+        assert!(grouper.is_empty());
+    }
+}
@@ -0,0 +1,211 @@
+//! Declarative column mappings for HTML log tables
+//!
+//! A profile says which cell of a `<tr>` holds each entry field, either by CSS
+//! class (`td.date`) or by the text of the column's `<th>`. The profile for a
+//! file is picked from its header row, so pytest and log4j tables can be mixed
+//! in one directory.
+
+use crate::html_stream::{HtmlCell, HtmlRow};
+use serde::{Deserialize, Serialize};
+
+/// How to find a cell within a row
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "by", content = "value", rename_all = "snake_case")]
+pub enum CellSelector {
+    /// First `<td>` carrying this class
+    Class(String),
+    /// First `<td>` carrying this class and the `hidden` attribute, like
+    /// the collapsed stack traces of pytest logs
+    HiddenClass(String),
+    /// `<td>` in the column whose `<th>` text matches (case-insensitive)
+    Header(String),
+}
+
+impl CellSelector {
+    pub fn class(name: &str) -> Self {
+        Self::Class(name.to_string())
+    }
+
+    pub fn hidden_class(name: &str) -> Self {
+        Self::HiddenClass(name.to_string())
+    }
+
+    pub fn header(text: &str) -> Self {
+        Self::Header(text.to_string())
+    }
+
+    /// Locate the cell in `row`; `columns` are the texts of the last header row
+    pub fn find<'r>(&self, row: &'r HtmlRow, columns: &[String]) -> Option<&'r HtmlCell> {
+        match self {
+            Self::Class(class) => row.data_cell_with_class(class),
+            Self::HiddenClass(class) => row
+                .cells
+                .iter()
+                .find(|cell| !cell.is_header && cell.hidden && cell.has_class(class)),
+            Self::Header(text) => {
+                let index = columns.iter().position(|c| c.eq_ignore_ascii_case(text))?;
+                row.cells.get(index).filter(|cell| !cell.is_header)
+            }
+        }
+    }
+}
+
+/// Mapping from table cells to `LogEntry` fields
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HtmlTableProfile {
+    pub name: String,
+    /// Header texts that must all be present for the profile to be chosen
+    pub detect_headers: Vec<String>,
+    pub timestamp: CellSelector,
+    pub level: Option<CellSelector>,
+    pub message: CellSelector,
+    pub stack: Option<CellSelector>,
+    /// Combined `Thread: <name>` / `module -- line` cell, see `HierarchyInfo`
+    #[serde(default)]
+    pub hierarchy: Option<CellSelector>,
+    #[serde(default)]
+    pub thread: Option<CellSelector>,
+    #[serde(default)]
+    pub module: Option<CellSelector>,
+    /// Cell with a `File.java:42` style location, used for the source line
+    #[serde(default)]
+    pub location: Option<CellSelector>,
+    /// Rows with a single cell continue the previous entry's stack trace
+    #[serde(default)]
+    pub continuation_rows: bool,
+}
+
+impl HtmlTableProfile {
+    /// Tables written by the pytest HTML log handler
+    pub fn pytest() -> Self {
+        Self {
+            name: "pytest".to_string(),
+            detect_headers: vec!["Timestamp".to_string(), "Level".to_string(), "Message".to_string()],
+            timestamp: CellSelector::class("date"),
+            level: Some(CellSelector::class("level")),
+            message: CellSelector::class("message"),
+            stack: Some(CellSelector::hidden_class("stack")),
+            hierarchy: Some(CellSelector::class("hierarchy")),
+            thread: None,
+            module: None,
+            location: None,
+            continuation_rows: false,
+        }
+    }
+
+    /// Tables written by log4j's `HTMLLayout`.
+    ///
+    /// Its Time column is usually milliseconds since the JVM started, which
+    /// cannot be turned into an absolute timestamp.
+    pub fn log4j() -> Self {
+        Self {
+            name: "log4j".to_string(),
+            detect_headers: vec![
+                "Time".to_string(),
+                "Thread".to_string(),
+                "Level".to_string(),
+                "Message".to_string(),
+            ],
+            timestamp: CellSelector::header("Time"),
+            level: Some(CellSelector::header("Level")),
+            message: CellSelector::header("Message"),
+            stack: None,
+            hierarchy: None,
+            thread: Some(CellSelector::header("Thread")),
+            module: Some(CellSelector::header("Category")),
+            location: Some(CellSelector::header("File:Line")),
+            continuation_rows: true,
+        }
+    }
+
+    /// Profiles every HTML import knows about, in detection order
+    pub fn builtin() -> Vec<Self> {
+        vec![Self::pytest(), Self::log4j()]
+    }
+
+    /// Whether a header row with these column texts belongs to this profile
+    pub fn matches_headers(&self, columns: &[String]) -> bool {
+        !self.detect_headers.is_empty()
+            && self
+                .detect_headers
+                .iter()
+                .all(|h| columns.iter().any(|c| c.eq_ignore_ascii_case(h)))
+    }
+
+    /// Reject profiles that could never produce an entry
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("HTML profile name cannot be empty".to_string());
+        }
+        let selectors = [Some(&self.timestamp), Some(&self.message), self.level.as_ref()];
+        for selector in selectors.into_iter().flatten() {
+            let (CellSelector::Class(value) | CellSelector::HiddenClass(value) | CellSelector::Header(value)) =
+                selector;
+            if value.trim().is_empty() {
+                return Err(format!("HTML profile '{}' has an empty cell selector", self.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_header_detection() {
+        let log4j = columns(&["Time", "Thread", "Level", "Category", "Message"]);
+        let pytest = columns(&["Timestamp", "Level", "Hierarchy", "Message"]);
+
+        assert!(HtmlTableProfile::log4j().matches_headers(&log4j));
+        assert!(!HtmlTableProfile::log4j().matches_headers(&pytest));
+        assert!(HtmlTableProfile::pytest().matches_headers(&pytest));
+        assert!(!HtmlTableProfile::pytest().matches_headers(&log4j));
+    }
+
+    #[test]
+    fn test_header_selector() {
+        let row = HtmlRow {
+            cells: vec![
+                HtmlCell { text: "12".to_string(), ..Default::default() },
+                HtmlCell { text: "main".to_string(), ..Default::default() },
+            ],
+            classes: Vec::new(),
+        };
+        let cols = columns(&["Time", "Thread"]);
+
+        assert_eq!(CellSelector::header("thread").find(&row, &cols).map(|c| c.text.as_str()), Some("main"));
+        assert!(CellSelector::header("Level").find(&row, &cols).is_none());
+        assert!(CellSelector::class("date").find(&row, &cols).is_none());
+    }
+
+    #[test]
+    fn test_hidden_class_selector() {
+        let cell = |text: &str, hidden: bool| HtmlCell {
+            text: text.to_string(),
+            classes: vec!["stack".to_string()],
+            hidden,
+            ..Default::default()
+        };
+        let row = HtmlRow {
+            cells: vec![cell("shown", false), cell("Traceback", true)],
+            classes: Vec::new(),
+        };
+
+        let stack = HtmlTableProfile::pytest().stack.unwrap();
+        assert_eq!(stack.find(&row, &[]).map(|c| c.text.as_str()), Some("Traceback"));
+        let visible_only = HtmlRow { cells: vec![cell("shown", false)], classes: Vec::new() };
+        assert!(stack.find(&visible_only, &[]).is_none());
+    }
+
+    #[test]
+    fn test_selector_serialization() {
+        let json = serde_json::to_string(&CellSelector::header("Time")).unwrap();
+        assert_eq!(json, r#"{"by":"header","value":"Time"}"#);
+    }
+}
@@ -0,0 +1,318 @@
+//! Streaming HTML table reader
+//!
+//! Feeds bytes from any `Read` source into the html5ever tokenizer and yields
+//! `<tr>` rows as soon as they are closed, so a log file never has to be held
+//! in memory as a full DOM.
+
+use html5ever::tendril::StrTendril;
+use html5ever::tokenizer::states::RawKind;
+use html5ever::tokenizer::{
+    BufferQueue, Tag, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts,
+};
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+/// Size of each read from the underlying source
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A single `<td>` or `<th>` cell of a table row
+#[derive(Debug, Clone, Default)]
+pub struct HtmlCell {
+    /// True for `<th>` cells
+    pub is_header: bool,
+    /// Whitespace-separated values of the `class` attribute
+    pub classes: Vec<String>,
+    /// Whether the cell carries the `hidden` attribute
+    pub hidden: bool,
+    /// Concatenated text of all descendant text nodes (untrimmed)
+    pub text: String,
+    /// Whether any descendant element has `id="failureAnchor"`
+    pub has_failure_anchor: bool,
+    /// Classes of descendant elements, e.g. `ERROR` from `<pre class="ERROR">`
+    pub descendant_classes: Vec<String>,
+}
+
+impl HtmlCell {
+    pub fn has_class(&self, class: &str) -> bool {
+        self.classes.iter().any(|c| c == class)
+    }
+}
+
+/// A completed `<tr>` found inside a `<table>`
+#[derive(Debug, Clone, Default)]
+pub struct HtmlRow {
+    pub cells: Vec<HtmlCell>,
+    /// Whitespace-separated values of the `<tr>`'s `class` attribute
+    pub classes: Vec<String>,
+}
+
+impl HtmlRow {
+    /// Whether the row contains at least one `<th>` cell
+    pub fn has_header_cell(&self) -> bool {
+        self.cells.iter().any(|c| c.is_header)
+    }
+
+    /// First `<td>` carrying the given class, mirroring a `td.<class>` selector
+    pub fn data_cell_with_class(&self, class: &str) -> Option<&HtmlCell> {
+        self.cells
+            .iter()
+            .find(|c| !c.is_header && c.has_class(class))
+    }
+}
+
+/// Values of a tag's `class` attribute
+fn class_list(tag: &Tag) -> Vec<String> {
+    tag.attrs
+        .iter()
+        .filter(|attr| &*attr.name.local == "class")
+        .flat_map(|attr| attr.value.split_whitespace().map(str::to_string))
+        .collect()
+}
+
+/// Token sink that tracks table/row/cell nesting and collects finished rows
+#[derive(Default)]
+struct RowCollector {
+    table_depth: usize,
+    current_row: Option<HtmlRow>,
+    current_cell: Option<HtmlCell>,
+    rows: VecDeque<HtmlRow>,
+    /// Text of the document's first `<title>`, collected while inside it
+    title: Option<String>,
+    in_title: bool,
+}
+
+impl RowCollector {
+    fn close_cell(&mut self) {
+        if let Some(cell) = self.current_cell.take() {
+            self.current_row.get_or_insert_with(HtmlRow::default).cells.push(cell);
+        }
+    }
+
+    fn close_row(&mut self) {
+        self.close_cell();
+        if let Some(row) = self.current_row.take() {
+            self.rows.push_back(row);
+        }
+    }
+
+    fn handle_tag(&mut self, tag: Tag) -> TokenSinkResult<()> {
+        let name: &str = &tag.name;
+
+        match (tag.kind, name) {
+            (TagKind::StartTag, "table") => {
+                self.table_depth += 1;
+            }
+            (TagKind::EndTag, "table") if self.table_depth > 0 => {
+                self.close_row();
+                self.table_depth -= 1;
+            }
+            (TagKind::StartTag, "tr") if self.table_depth > 0 => {
+                self.close_row();
+                self.current_row = Some(HtmlRow {
+                    classes: class_list(&tag),
+                    ..Default::default()
+                });
+            }
+            (TagKind::EndTag, "tr") if self.table_depth > 0 => {
+                self.close_row();
+            }
+            (TagKind::StartTag, "td" | "th") if self.table_depth > 0 => {
+                self.close_cell();
+                let mut cell = HtmlCell {
+                    is_header: name == "th",
+                    ..Default::default()
+                };
+                cell.classes = class_list(&tag);
+                cell.hidden = tag.attrs.iter().any(|attr| &*attr.name.local == "hidden");
+                self.current_cell = Some(cell);
+            }
+            (TagKind::EndTag, "td" | "th") => {
+                self.close_cell();
+            }
+            (TagKind::StartTag, "title") if self.title.is_none() && !tag.self_closing => {
+                self.title = Some(String::new());
+                self.in_title = true;
+            }
+            (TagKind::EndTag, "title") => {
+                self.in_title = false;
+            }
+            (TagKind::StartTag, _) => {
+                if let Some(cell) = self.current_cell.as_mut() {
+                    if tag
+                        .attrs
+                        .iter()
+                        .any(|attr| &*attr.name.local == "id" && &*attr.value == "failureAnchor")
+                    {
+                        cell.has_failure_anchor = true;
+                    }
+                    cell.descendant_classes.extend(class_list(&tag));
+                }
+            }
+            _ => {}
+        }
+
+        // Switch the tokenizer into raw text mode the same way a tree builder would,
+        // so markup-like content in scripts and styles is not tokenized as tags
+        if tag.kind == TagKind::StartTag && !tag.self_closing {
+            match name {
+                "script" => return TokenSinkResult::RawData(RawKind::ScriptData),
+                "style" | "xmp" | "iframe" | "noembed" | "noframes" => {
+                    return TokenSinkResult::RawData(RawKind::Rawtext)
+                }
+                "title" | "textarea" => return TokenSinkResult::RawData(RawKind::Rcdata),
+                _ => {}
+            }
+        }
+
+        TokenSinkResult::Continue
+    }
+}
+
+impl TokenSink for RowCollector {
+    type Handle = ();
+
+    fn process_token(&mut self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+        match token {
+            Token::TagToken(tag) => return self.handle_tag(tag),
+            Token::CharacterTokens(text) => {
+                if self.in_title {
+                    if let Some(title) = self.title.as_mut() {
+                        title.push_str(&text);
+                    }
+                } else if let Some(cell) = self.current_cell.as_mut() {
+                    cell.text.push_str(&text);
+                }
+            }
+            Token::EOFToken => {
+                self.close_row();
+            }
+            _ => {}
+        }
+        TokenSinkResult::Continue
+    }
+}
+
+/// Iterator over the table rows of an HTML document read incrementally from `R`
+///
+/// Memory use is bounded by the read chunk size plus the rows produced from a
+/// single chunk, regardless of the total document size.
+pub struct HtmlRowReader<R: Read> {
+    reader: R,
+    tokenizer: Tokenizer<RowCollector>,
+    input: BufferQueue,
+    /// Bytes read but not yet decoded (an incomplete UTF-8 sequence at a chunk boundary)
+    pending: Vec<u8>,
+    chunk: Vec<u8>,
+    bytes_read: u64,
+    finished: bool,
+    /// Drop a row that is still open at the end of the input instead of emitting it
+    hold_open_row: bool,
+}
+
+impl<R: Read> HtmlRowReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            tokenizer: Tokenizer::new(RowCollector::default(), TokenizerOpts::default()),
+            input: BufferQueue::default(),
+            pending: Vec::new(),
+            chunk: vec![0; READ_CHUNK_SIZE],
+            bytes_read: 0,
+            finished: false,
+            hold_open_row: false,
+        }
+    }
+
+    /// For documents still being written: a row not yet closed by `</tr>`,
+    /// a following `<tr>` or `</table>` when the input ends is left out, as
+    /// its remaining cells may not have been written yet. A missing
+    /// `</table>` does not affect rows that are closed.
+    pub fn hold_open_row(mut self) -> Self {
+        self.hold_open_row = true;
+        self
+    }
+
+    /// Total number of bytes consumed from the underlying reader so far
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Trimmed text of the document's `<title>`, once it has been read
+    pub fn title(&self) -> Option<String> {
+        let title = self.tokenizer.sink.title.as_deref()?.trim();
+        (!title.is_empty()).then(|| title.to_string())
+    }
+
+    /// Read the next chunk and feed whatever decodes cleanly to the tokenizer
+    fn fill(&mut self) -> io::Result<()> {
+        let n = loop {
+            match self.reader.read(&mut self.chunk) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+
+        if n == 0 {
+            self.finished = true;
+            if self.hold_open_row {
+                // A multi-byte character may be cut off along with the row
+                self.pending.clear();
+                self.tokenizer.sink.current_row = None;
+                self.tokenizer.sink.current_cell = None;
+                return Ok(());
+            }
+            if !self.pending.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                ));
+            }
+            self.tokenizer.end();
+            return Ok(());
+        }
+
+        self.bytes_read += n as u64;
+        self.pending.extend_from_slice(&self.chunk[..n]);
+
+        let valid_len = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => {
+                self.finished = true;
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                ));
+            }
+        };
+
+        if valid_len > 0 {
+            let text = std::str::from_utf8(&self.pending[..valid_len])
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            self.input.push_back(StrTendril::from_slice(text));
+            self.pending.drain(..valid_len);
+            let _ = self.tokenizer.feed(&mut self.input);
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for HtmlRowReader<R> {
+    type Item = io::Result<HtmlRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.tokenizer.sink.rows.pop_front() {
+                return Some(Ok(row));
+            }
+            if self.finished {
+                return None;
+            }
+            if let Err(e) = self.fill() {
+                return Some(Err(e));
+            }
+        }
+    }
+}
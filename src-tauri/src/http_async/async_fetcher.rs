@@ -1,10 +1,11 @@
 use reqwest::Client;
 use reqwest::Url;
+use futures::stream::StreamExt;
+use std::io::{self, Read};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use std::collections::HashMap;
-use super::types::DownloadResult;
+use tokio::sync::mpsc;
 use super::progress_tracker::SpeedCalculator;
 use crate::http_log_fetcher::HttpFetchError;
 
@@ -23,6 +24,51 @@ const MAX_CONCURRENT_CHUNKS: usize = 3;
 /// Maximum retries per chunk
 const MAX_CHUNK_RETRIES: u32 = 3;
 
+/// Pieces of a body queued between a download and its parser
+const BODY_QUEUE_LEN: usize = 8;
+
+/// Sending end of a [`body_channel`], fed by [`AsyncHttpLogFetcher::stream_file_with_retry`]
+pub type BodySender = mpsc::Sender<io::Result<Vec<u8>>>;
+
+/// A bounded channel carrying a file's body from its download to a parser
+/// on a blocking thread
+pub fn body_channel() -> (BodySender, BodyReader) {
+    let (sender, receiver) = mpsc::channel(BODY_QUEUE_LEN);
+    let reader = BodyReader {
+        receiver,
+        piece: Vec::new(),
+        position: 0,
+    };
+    (sender, reader)
+}
+
+/// Blocking [`Read`] over a body arriving through a [`body_channel`]. It
+/// ends when the sender is dropped, or with the error the download sent.
+/// Must not be read from an async task.
+pub struct BodyReader {
+    receiver: mpsc::Receiver<io::Result<Vec<u8>>>,
+    piece: Vec<u8>,
+    position: usize,
+}
+
+impl Read for BodyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.piece.len() {
+            match self.receiver.blocking_recv() {
+                Some(piece) => {
+                    self.piece = piece?;
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.piece.len() - self.position);
+        buf[..len].copy_from_slice(&self.piece[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
 /// Async HTTP log fetcher with retry and chunked download support
 #[derive(Clone)]
 pub struct AsyncHttpLogFetcher {
@@ -58,19 +104,23 @@ impl AsyncHttpLogFetcher {
         &self.base_url
     }
 
-    /// Fetch a single file with retry
-    pub async fn fetch_file_with_retry(
+    /// Download a file into `body` as it arrives, for a parser reading the
+    /// other end. Attempts that fail before any of the file was sent are
+    /// retried; once the parser has part of it, an error ends the download,
+    /// as the file cannot be restarted under it. On failure the error is also
+    /// sent to the parser. Returns the bytes sent.
+    pub async fn stream_file_with_retry(
         &self,
         url: &str,
         max_retries: u32,
+        body: &BodySender,
         bytes_downloaded: Arc<AtomicU64>,
         speed_calculator: Arc<SpeedCalculator>,
-    ) -> Result<DownloadResult, HttpFetchError> {
+    ) -> Result<u64, HttpFetchError> {
         let mut retry_count = 0;
-        let mut last_error = None;
         let mut error_history = Vec::new();
 
-        while retry_count <= max_retries {
+        let error = loop {
             if retry_count > 0 {
                 // Exponential backoff: 100ms, 200ms, 400ms...
                 let backoff = 100 * (2_u64.pow(retry_count - 1));
@@ -79,44 +129,52 @@ impl AsyncHttpLogFetcher {
                 tokio::time::sleep(Duration::from_millis(backoff)).await;
             }
 
-            match self.fetch_file_once(url, bytes_downloaded.clone(), speed_calculator.clone(), retry_count).await {
-                Ok(result) => {
+            let mut sent = 0;
+            match self.stream_file_once(url, body, &mut sent, bytes_downloaded.clone(), speed_calculator.clone(), retry_count).await {
+                Ok(()) => {
                     if retry_count > 0 {
                         log::info!("[ASYNC_DL] Success on retry {} for {}", retry_count, url);
                     }
-                    return Ok(result);
+                    return Ok(sent);
                 }
                 Err(e) => {
                     let error_detail = format!("Retry {} failed: {}", retry_count, e);
                     log::error!("[ASYNC_DL] {}", error_detail);
                     error_history.push(error_detail);
-                    last_error = Some(e);
+                    if sent > 0 || retry_count >= max_retries {
+                        break e;
+                    }
                     retry_count += 1;
                 }
             }
-        }
+        };
 
         // Build comprehensive error message with history
         let final_error = format!(
             "Failed to download {} after {} retries.\nError history:\n  {}\nLast error: {}",
             url,
-            max_retries,
+            retry_count,
             error_history.join("\n  "),
-            last_error.as_ref().map(|e| e.to_string()).unwrap_or_else(|| "Unknown".to_string())
+            error
         );
         log::error!("[ASYNC_DL] {}", final_error);
 
-        Err(last_error.unwrap())
+        let _ = body.send(Err(io::Error::other(error.to_string()))).await;
+        Err(error)
     }
 
-    /// Fetch file once (single attempt) - automatically chooses chunked or simple download
-    async fn fetch_file_once(
+    /// Single attempt at [`stream_file_with_retry`](Self::stream_file_with_retry),
+    /// choosing a chunked or simple download. `sent` counts the bytes handed
+    /// to `body`.
+    async fn stream_file_once(
         &self,
         url: &str,
+        body: &BodySender,
+        sent: &mut u64,
         bytes_downloaded: Arc<AtomicU64>,
         speed_calculator: Arc<SpeedCalculator>,
         attempt_number: u32,
-    ) -> Result<DownloadResult, HttpFetchError> {
+    ) -> Result<(), HttpFetchError> {
         log::info!("[ASYNC_DL] [Attempt {}] Starting download: {}", attempt_number, url);
 
         // First, do a HEAD request to check file size and Range support
@@ -128,7 +186,7 @@ impl AsyncHttpLogFetcher {
         if use_chunked {
             log::info!("[ASYNC_DL] [Attempt {}] Using chunked download: {} bytes ({} MB), Range support: yes",
                 attempt_number, content_length, content_length / 1024 / 1024);
-            self.fetch_file_chunked_parallel(url, content_length, bytes_downloaded, speed_calculator, attempt_number).await
+            self.stream_file_chunked(url, content_length, body, sent, bytes_downloaded, speed_calculator, attempt_number).await
         } else {
             if content_length >= CHUNKED_DOWNLOAD_THRESHOLD {
                 log::warn!("[ASYNC_DL] [Attempt {}] File is large ({} MB) but server doesn't support Range requests, using simple download",
@@ -137,7 +195,7 @@ impl AsyncHttpLogFetcher {
                 log::info!("[ASYNC_DL] [Attempt {}] Using simple download: {} bytes ({} MB)",
                     attempt_number, content_length, content_length / 1024 / 1024);
             }
-            self.fetch_file_simple(url, bytes_downloaded, speed_calculator, attempt_number).await
+            self.stream_file_simple(url, body, sent, bytes_downloaded, speed_calculator, attempt_number).await
         }
     }
 
@@ -167,168 +225,107 @@ impl AsyncHttpLogFetcher {
         Ok((content_length, supports_range))
     }
 
-    /// Fetch file using parallel chunked download with Range requests
-    async fn fetch_file_chunked_parallel(
+    /// Download a file with Range requests, up to [`MAX_CONCURRENT_CHUNKS`]
+    /// at a time, sending the chunks to `body` in order
+    #[allow(clippy::too_many_arguments)]
+    async fn stream_file_chunked(
         &self,
         url: &str,
         content_length: u64,
+        body: &BodySender,
+        sent: &mut u64,
         bytes_downloaded: Arc<AtomicU64>,
         speed_calculator: Arc<SpeedCalculator>,
         attempt_number: u32,
-    ) -> Result<DownloadResult, HttpFetchError> {
+    ) -> Result<(), HttpFetchError> {
         let start_time = std::time::Instant::now();
-        let total_chunks = (content_length + CHUNK_SIZE - 1) / CHUNK_SIZE;
+        let total_chunks = content_length.div_ceil(CHUNK_SIZE);
 
         log::info!("[ASYNC_DL] [Attempt {}] Parallel chunked download: {} bytes in {} chunks ({} MB/chunk, {} concurrent)",
             attempt_number, content_length, total_chunks, CHUNK_SIZE / 1024 / 1024, MAX_CONCURRENT_CHUNKS);
 
-        // Create a semaphore to limit concurrent downloads
-        let semaphore = Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_CHUNKS));
-        let url_str = url.to_string();
-        let client = self.client.clone();
-
-        // Track chunk results: chunk_index -> (data, retry_count)
-        let chunk_results = Arc::new(std::sync::Mutex::new(HashMap::new()));
-        let mut download_tasks = Vec::new();
-
-        // Wrap in Arc for sharing across tasks
-        let bytes_downloaded = Arc::new(bytes_downloaded.clone());
-        let speed_calculator = Arc::new(speed_calculator.clone());
-
-        // Spawn download tasks for all chunks
-        for chunk_index in 0..total_chunks {
-            let semaphore = semaphore.clone();
-            let client = client.clone();
-            let url = url_str.clone();
-            let chunk_results = chunk_results.clone();
-            let bytes_downloaded = bytes_downloaded.clone();
-            let speed_calculator = speed_calculator.clone();
-
-            let task = tokio::spawn(async move {
-                // Acquire semaphore permit
-                let _permit = semaphore.acquire().await
-                    .map_err(|e| {
-                        log::error!("[ASYNC_DL] Failed to acquire semaphore: {}", e);
-                        HttpFetchError::DownloadFailed {
-                            url: url.clone(),
-                            reason: format!("Semaphore error: {}", e),
-                        }
-                    })?;
-
+        // Chunks download in their own tasks; only the next few are started,
+        // so at most that many are held while the parser catches up
+        let mut chunks = futures::stream::iter(0..total_chunks)
+            .map(|chunk_index| {
                 let start = chunk_index * CHUNK_SIZE;
                 let end = std::cmp::min(start + CHUNK_SIZE - 1, content_length - 1);
-
-                // Download with chunk-level retry
-                let mut retry_count = 0u32;
-                let mut last_error = None;
-
-                while retry_count <= MAX_CHUNK_RETRIES {
-                    if retry_count > 0 {
-                        let backoff = 100 * (2_u64.pow(retry_count - 1));
-                        log::warn!("[ASYNC_DL] Chunk {} retry {}/{} after {}ms",
-                            chunk_index, retry_count, MAX_CHUNK_RETRIES, backoff);
-                        tokio::time::sleep(Duration::from_millis(backoff)).await;
-                    }
-
-                    match Self::fetch_chunk_single(client.clone(), url.clone(), chunk_index, start, end).await {
-                        Ok(chunk_data) => {
-                            let chunk_len = chunk_data.len() as u64;
-                            bytes_downloaded.fetch_add(chunk_len, Ordering::Relaxed);
-                            speed_calculator.add_sample(bytes_downloaded.load(Ordering::Relaxed));
-
-                            let progress = (bytes_downloaded.load(Ordering::Relaxed) * 100) / content_length;
-                            log::debug!("[ASYNC_DL] Chunk {}/{} downloaded: {} bytes ({}% complete)",
-                                chunk_index + 1, total_chunks, chunk_len, progress);
-
-                            // Store successful result
-                            {
-                                let mut results = chunk_results.lock().unwrap();
-                                results.insert(chunk_index, chunk_data);
-                            }
-                            return Ok::<(), HttpFetchError>(());
-                        }
-                        Err(e) => {
-                            log::warn!("[ASYNC_DL] Chunk {} attempt {}/{} failed: {}",
-                                chunk_index, retry_count + 1, MAX_CHUNK_RETRIES + 1, e);
-                            last_error = Some(e);
-                            retry_count += 1;
-                        }
-                    }
+                tokio::spawn(Self::fetch_chunk_with_retry(
+                    self.client.clone(),
+                    url.to_string(),
+                    chunk_index,
+                    start,
+                    end,
+                    bytes_downloaded.clone(),
+                    speed_calculator.clone(),
+                ))
+            })
+            .buffered(MAX_CONCURRENT_CHUNKS);
+
+        let mut chunk_index = 0;
+        while let Some(task) = chunks.next().await {
+            let chunk_data = task.map_err(|e| {
+                log::error!("[ASYNC_DL] Chunk {} task failed: {}", chunk_index, e);
+                HttpFetchError::DownloadFailed {
+                    url: url.to_string(),
+                    reason: format!("Chunk {} task failed: {}", chunk_index, e),
                 }
+            })??;
+            log::debug!("[ASYNC_DL] Chunk {}/{} downloaded: {} bytes ({}% complete)",
+                chunk_index + 1, total_chunks, chunk_data.len(), (*sent + chunk_data.len() as u64) * 100 / content_length);
+
+            let chunk_len = chunk_data.len() as u64;
+            if body.send(Ok(chunk_data)).await.is_err() {
+                log::debug!("[ASYNC_DL] Parser stopped reading {}", url);
+                return Ok(());
+            }
+            *sent += chunk_len;
+            chunk_index += 1;
+        }
 
-                // All retries failed
-                let error = last_error.unwrap();
-                log::error!("[ASYNC_DL] Chunk {} failed after {} retries", chunk_index, MAX_CHUNK_RETRIES);
-                Err(error)
-            });
+        let elapsed = start_time.elapsed().as_secs();
+        log::info!("[ASYNC_DL] [Attempt {}] Parallel chunked download completed in {}s: {} bytes",
+            attempt_number, elapsed, sent);
 
-            download_tasks.push(task);
-        }
+        Ok(())
+    }
 
-        // Wait for all chunks to complete
-        let mut failed_chunks = Vec::new();
-        for (chunk_index, task) in download_tasks.into_iter().enumerate() {
-            match task.await {
-                Ok(Ok(())) => {}
-                Ok(Err(_e)) => {
-                    failed_chunks.push(chunk_index);
-                }
-                Err(e) => {
-                    log::error!("[ASYNC_DL] Chunk {} task failed: {}", chunk_index, e);
-                    failed_chunks.push(chunk_index);
-                }
+    /// Fetch one chunk, retrying it up to [`MAX_CHUNK_RETRIES`] times
+    async fn fetch_chunk_with_retry(
+        client: Client,
+        url: String,
+        chunk_index: u64,
+        start: u64,
+        end: u64,
+        bytes_downloaded: Arc<AtomicU64>,
+        speed_calculator: Arc<SpeedCalculator>,
+    ) -> Result<Vec<u8>, HttpFetchError> {
+        let mut retry_count = 0u32;
+        loop {
+            if retry_count > 0 {
+                let backoff = 100 * (2_u64.pow(retry_count - 1));
+                log::warn!("[ASYNC_DL] Chunk {} retry {}/{} after {}ms",
+                    chunk_index, retry_count, MAX_CHUNK_RETRIES, backoff);
+                tokio::time::sleep(Duration::from_millis(backoff)).await;
             }
-        }
 
-        // Check if any chunks failed
-        if !failed_chunks.is_empty() {
-            return Err(HttpFetchError::DownloadFailed {
-                url: url.to_string(),
-                reason: format!("Failed to download {}/{} chunks: {:?}",
-                    failed_chunks.len(), total_chunks, failed_chunks),
-            });
-        }
-
-        // Collect all chunks in order
-        let results = chunk_results.lock().unwrap();
-        let mut chunks = Vec::with_capacity(total_chunks as usize);
-        for chunk_index in 0..total_chunks {
-            match results.get(&chunk_index) {
-                Some(chunk_data) => chunks.push(chunk_data.clone()),
-                None => {
-                    return Err(HttpFetchError::DownloadFailed {
-                        url: url.to_string(),
-                        reason: format!("Chunk {} is missing from results", chunk_index),
-                    });
+            match Self::fetch_chunk_single(client.clone(), url.clone(), chunk_index, start, end).await {
+                Ok(chunk_data) => {
+                    bytes_downloaded.fetch_add(chunk_data.len() as u64, Ordering::Relaxed);
+                    speed_calculator.add_sample(bytes_downloaded.load(Ordering::Relaxed));
+                    return Ok(chunk_data);
+                }
+                Err(e) => {
+                    log::warn!("[ASYNC_DL] Chunk {} attempt {}/{} failed: {}",
+                        chunk_index, retry_count + 1, MAX_CHUNK_RETRIES + 1, e);
+                    if retry_count >= MAX_CHUNK_RETRIES {
+                        log::error!("[ASYNC_DL] Chunk {} failed after {} retries", chunk_index, MAX_CHUNK_RETRIES);
+                        return Err(e);
+                    }
+                    retry_count += 1;
                 }
             }
         }
-        drop(results);
-
-        // Merge all chunks
-        let total_size = chunks.iter().map(|c| c.len()).sum();
-        let mut buffer = Vec::with_capacity(total_size);
-        for chunk in chunks {
-            buffer.extend_from_slice(&chunk);
-        }
-
-        // Convert to String
-        let final_content = match String::from_utf8(buffer) {
-            Ok(s) => s,
-            Err(e) => {
-                log::error!("[ASYNC_DL] [Attempt {}] UTF-8 conversion error at byte {}",
-                    attempt_number, e.utf8_error().valid_up_to());
-                let recovered = String::from_utf8_lossy(e.as_bytes()).to_string();
-                log::warn!("[ASYNC_DL] [Attempt {}] Recovered with lossy conversion", attempt_number);
-                recovered
-            }
-        };
-
-        let elapsed = start_time.elapsed().as_secs();
-        log::info!("[ASYNC_DL] [Attempt {}] Parallel chunked download completed in {}s: {} bytes, {} chars",
-            attempt_number, elapsed, bytes_downloaded.load(Ordering::Relaxed), final_content.len());
-
-        Ok(DownloadResult::new(url.to_string(), final_content, bytes_downloaded.load(Ordering::Relaxed)))
     }
 
     /// Fetch a single chunk using Range request (static method for use in spawned tasks)
@@ -364,14 +361,16 @@ impl AsyncHttpLogFetcher {
         Ok(bytes.to_vec())
     }
 
-    /// Fetch file using simple (non-chunked) download
-    async fn fetch_file_simple(
+    /// Download a file in one request, sending its body to `body` as it arrives
+    async fn stream_file_simple(
         &self,
         url: &str,
+        body: &BodySender,
+        sent: &mut u64,
         bytes_downloaded: Arc<AtomicU64>,
         speed_calculator: Arc<SpeedCalculator>,
         attempt_number: u32,
-    ) -> Result<DownloadResult, HttpFetchError> {
+    ) -> Result<(), HttpFetchError> {
         log::info!("[ASYNC_DL] [Attempt {}] Starting simple download: {}", attempt_number, url);
 
         let start_time = std::time::Instant::now();
@@ -397,10 +396,6 @@ impl AsyncHttpLogFetcher {
         log::info!("[ASYNC_DL] [Attempt {}] Content-Length: {} bytes ({} MB)",
             attempt_number, content_length, content_length / 1024 / 1024);
 
-        let mut downloaded = 0u64;
-        let mut buffer = Vec::with_capacity(content_length as usize);
-
-        use futures::stream::StreamExt;
         let mut stream = response.bytes_stream();
 
         while let Some(chunk_result) = stream.next().await {
@@ -409,47 +404,38 @@ impl AsyncHttpLogFetcher {
                 Err(e) => {
                     let elapsed = start_time.elapsed().as_secs();
                     log::error!("[ASYNC_DL] [Attempt {}] Stream error after {}s at byte {}: {}",
-                        attempt_number, elapsed, downloaded, e);
+                        attempt_number, elapsed, sent, e);
                     return Err(HttpFetchError::NetworkError(e));
                 }
             };
 
-            downloaded += chunk.len() as u64;
-            bytes_downloaded.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+            let chunk_len = chunk.len() as u64;
+            bytes_downloaded.fetch_add(chunk_len, Ordering::Relaxed);
             speed_calculator.add_sample(bytes_downloaded.load(Ordering::Relaxed));
 
-            buffer.extend_from_slice(&chunk);
+            if body.send(Ok(chunk.into())).await.is_err() {
+                log::debug!("[ASYNC_DL] Parser stopped reading {}", url);
+                return Ok(());
+            }
+            *sent += chunk_len;
 
             // Log progress every 5MB
-            if downloaded % (5 * 1024 * 1024) == 0 || downloaded == content_length {
+            if *sent % (5 * 1024 * 1024) == 0 || *sent == content_length {
                 let progress = if content_length > 0 {
-                    format!("{}%", downloaded * 100 / content_length)
+                    format!("{}%", *sent * 100 / content_length)
                 } else {
-                    format!("{} bytes", downloaded)
+                    format!("{} bytes", sent)
                 };
                 log::debug!("[ASYNC_DL] [Attempt {}] Progress: {} / {} bytes ({})",
-                    attempt_number, downloaded, content_length, progress);
+                    attempt_number, sent, content_length, progress);
             }
         }
 
-        // Convert to String
-        let final_content = match String::from_utf8(buffer) {
-            Ok(s) => s,
-            Err(e) => {
-                let elapsed = start_time.elapsed().as_secs();
-                log::error!("[ASYNC_DL] [Attempt {}] UTF-8 conversion error after {}s at byte {}",
-                    attempt_number, elapsed, e.utf8_error().valid_up_to());
-                let recovered = String::from_utf8_lossy(e.as_bytes()).to_string();
-                log::warn!("[ASYNC_DL] [Attempt {}] Recovered with lossy conversion", attempt_number);
-                recovered
-            }
-        };
-
         let elapsed = start_time.elapsed().as_secs();
-        log::info!("[ASYNC_DL] [Attempt {}] Simple download completed in {}s: {} bytes, {} chars",
-            attempt_number, elapsed, downloaded, final_content.len());
+        log::info!("[ASYNC_DL] [Attempt {}] Simple download completed in {}s: {} bytes",
+            attempt_number, elapsed, sent);
 
-        Ok(DownloadResult::new(url.to_string(), final_content, downloaded))
+        Ok(())
     }
 
     /// Parse directory listing HTML and extract all file URLs
@@ -1,30 +1,29 @@
 //! Session-level download coordination
 
-use crate::database::DatabaseManager;
+use crate::db_pool::DbPool;
 use crate::http_log_fetcher::HttpFetchError;
-use crate::log_parser::HtmlLogParser;
+use crate::session_attributes::ATTRIBUTE_ENTRY_LIMIT;
 use super::types::{ProgressStatus, FileStatus, FileDownloadStatus};
-use super::async_fetcher::AsyncHttpLogFetcher;
+use super::async_fetcher::{body_channel, AsyncHttpLogFetcher, BodyReader};
 use super::progress_tracker::SpeedCalculator;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc as std_mpsc;
 use std::collections::HashMap;
-use tokio::sync::Semaphore;
+use tokio::sync::{oneshot, Semaphore};
 use chrono::Utc;
 
 /// Download coordinator for managing concurrent sessions and files
 pub struct SessionDownloadCoordinator {
     max_sessions: usize,
-    max_files_per_session: usize,
     max_retries: u32,
 }
 
 impl SessionDownloadCoordinator {
     /// Create a new download coordinator
-    pub fn new(max_sessions: usize, max_files_per_session: usize, max_retries: u32) -> Self {
+    pub fn new(max_sessions: usize, max_retries: u32) -> Self {
         Self {
             max_sessions,
-            max_files_per_session,
             max_retries,
         }
     }
@@ -32,7 +31,7 @@ impl SessionDownloadCoordinator {
     /// Download all sessions from HTTP URL
     pub async fn download_sessions(
         &self,
-        db_path: String,
+        db: Arc<DbPool>,
         url: String,
         selected_tests: Option<Vec<String>>,
         progress_callback: Arc<dyn Fn(ProgressStatus) + Send + Sync>,
@@ -58,12 +57,11 @@ impl SessionDownloadCoordinator {
         progress_callback(ProgressStatus::Scanning { found: test_log_urls.len() });
 
         // Group by test session
+        let formats = crate::settings::load_settings().format_registry();
         let mut session_groups: HashMap<String, Vec<(String, usize)>> = HashMap::new();
         for (index, log_url) in test_log_urls.iter().enumerate() {
-            if let Some(filename) = log_url.rsplit('/').next() {
-                if let Some(test_name) = HtmlLogParser::is_test_log_file(filename) {
-                    session_groups.entry(test_name).or_default().push((log_url.clone(), index));
-                }
+            if let Some((_, test_name)) = formats.classify_url(log_url) {
+                session_groups.entry(test_name).or_default().push((log_url.clone(), index));
             }
         }
 
@@ -97,9 +95,8 @@ impl SessionDownloadCoordinator {
             let bytes = bytes_downloaded.clone();
             let speed = speed_calculator.clone();
             let url_clone = url.clone();
-            let db_path_clone = db_path.clone();
+            let db_clone = db.clone();
             let session_num = current_session.fetch_add(1, Ordering::SeqCst) + 1;
-            let max_files = self.max_files_per_session;
             let max_retries = self.max_retries;
 
             let task = tokio::spawn(async move {
@@ -110,13 +107,12 @@ impl SessionDownloadCoordinator {
                     session_name,
                     log_files,
                     url_clone,
-                    db_path_clone,
+                    db_clone,
                     progress_cb,
                     bytes,
                     speed,
                     session_num,
                     session_count,
-                    max_files,
                     max_retries,
                 ).await
             });
@@ -140,154 +136,124 @@ impl SessionDownloadCoordinator {
         Ok(session_ids)
     }
 
-    /// Download a single session with parallel file downloads
+    /// Download a single session, parsing each file as it arrives and writing
+    /// its entries in batches. Files are fetched one at a time in listing
+    /// order; the session is only stored if every file downloads, as complete
+    /// log data requires all files.
+    #[allow(clippy::too_many_arguments)]
     async fn download_single_session(
         fetcher: Arc<AsyncHttpLogFetcher>,
         session_name: String,
         log_files: Vec<(String, usize)>,
         url: String,
-        db_path: String,
+        db: Arc<DbPool>,
         progress_callback: Arc<dyn Fn(ProgressStatus) + Send + Sync>,
         bytes_downloaded: Arc<AtomicU64>,
         speed_calculator: Arc<SpeedCalculator>,
         session_num: usize,
         total_sessions: usize,
-        max_files: usize,
         max_retries: u32,
     ) -> Result<String, HttpFetchError> {
-        let file_semaphore = Arc::new(Semaphore::new(max_files));
-        let mut file_tasks = Vec::new();
-
-        // Track file status with HashMap for O(1) lookups
-        let file_status = Arc::new(std::sync::Mutex::new(HashMap::new()));
-
-        for (file_url, file_index) in log_files.iter() {
-            let semaphore = file_semaphore.clone();
-            let fetcher_clone = fetcher.clone();
-            let status = file_status.clone();
-            let bytes_clone = bytes_downloaded.clone();
-            let speed_clone = speed_calculator.clone();
-            let progress_cb_clone = progress_callback.clone();
-            let file_url = file_url.clone();
-            let log_files_len = log_files.len();
-            // Store the original file_index to preserve it through the async operation
-            let original_file_index = *file_index;
-
-            let task = tokio::spawn(async move {
-                let _permit = semaphore.acquire().await
-                    .map_err(|_| HttpFetchError::ParseError("Semaphore closed".to_string()))?;
+        // Generate session ID (needed for parsing)
+        let session_id = format!(
+            "session_{}_{}",
+            session_name.replace(|c: char| !c.is_alphanumeric() && c != '_', "_"),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        );
+        let test_session = crate::log_parser::TestSession {
+            id: session_id.clone(),
+            name: session_name.clone(),
+            directory_path: url.clone(),
+            file_count: log_files.len(),
+            total_entries: 0,
+            created_at: Some(Utc::now()),
+            last_parsed_at: Some(Utc::now()),
+            source_type: Some("http".to_string()),
+            incomplete: false,
+            attributes: Default::default(),
+        };
 
-                // Update status to downloading
-                {
-                    let mut st = status.lock().map_err(|e| HttpFetchError::ParseError(format!("Mutex error: {}", e)))?;
-                    st.insert(file_url.clone(), FileStatus {
-                        file_url: file_url.clone(),
-                        status: FileDownloadStatus::Downloading,
-                        retry_count: 0,
-                        error_message: None,
-                    });
+        // The writer takes the database before any download starts, so a
+        // session waiting for another one to be written holds no connection open
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let (files_tx, files_rx) = std_mpsc::channel();
+        let writer = tokio::task::spawn_blocking(move || Self::write_session(&db, test_session, ready_tx, files_rx));
+
+        let mut file_status: Vec<FileStatus> = log_files
+            .iter()
+            .map(|(file_url, _)| FileStatus {
+                file_url: file_url.clone(),
+                status: FileDownloadStatus::Pending,
+                retry_count: 0,
+                error_message: None,
+            })
+            .collect();
+        let mut failed_download = None;
+
+        if ready_rx.await.is_ok() {
+            for (position, (file_url, file_index)) in log_files.iter().enumerate() {
+                file_status[position].status = FileDownloadStatus::Downloading;
+
+                let (body_tx, body) = body_channel();
+                let file = SessionFile::Body { url: file_url.clone(), file_index: *file_index, body };
+                if files_tx.send(file).is_err() {
+                    // The writer failed; its error is returned below
+                    break;
                 }
 
-                match fetcher_clone.fetch_file_with_retry(
-                    &file_url,
+                let downloaded = fetcher.stream_file_with_retry(
+                    file_url,
                     max_retries,
-                    bytes_clone,
-                    speed_clone.clone(),
-                ).await {
-                    Ok(result) => {
-                        // Update status to completed
-                        {
-                            let mut st = status.lock().map_err(|e| HttpFetchError::ParseError(format!("Mutex error: {}", e)))?;
-                            if let Some(fs) = st.get_mut(&file_url) {
-                                fs.status = FileDownloadStatus::Completed;
-                            }
-                            log::info!("[DL] Completed: {}", file_url);
-                        }
-
-                        // Emit progress after each file completes
-                        let progress_vec = {
-                            let st = status.lock().map_err(|e| HttpFetchError::ParseError(format!("Mutex error: {}", e)))?;
-                            st.values().cloned().collect::<Vec<_>>()
-                        };
-                        progress_cb_clone(ProgressStatus::Downloading {
-                            total_sessions,
-                            current_session: session_num,
-                            total_files: log_files_len,
-                            completed_files: progress_vec.iter().filter(|f| matches!(f.status, FileDownloadStatus::Completed)).count(),
-                            failed_files: progress_vec.iter().filter(|f| matches!(f.status, FileDownloadStatus::Failed)).count(),
-                            speed: speed_clone.format_speed(),
-                            files: progress_vec,
-                        });
-
-                        // Return the URL, content, AND the original file_index
-                        Ok((file_url, result.content, original_file_index))
+                    &body_tx,
+                    bytes_downloaded.clone(),
+                    speed_calculator.clone(),
+                ).await;
+                drop(body_tx);
+
+                match downloaded {
+                    Ok(bytes) => {
+                        file_status[position].status = FileDownloadStatus::Completed;
+                        log::info!("[DL] Completed: {} ({} bytes, file_index={})", file_url, bytes, file_index);
                     }
                     Err(e) => {
-                        // Update status to failed
-                        let mut st = status.lock().map_err(|e| HttpFetchError::ParseError(format!("Mutex error: {}", e)))?;
-                        if let Some(fs) = st.get_mut(&file_url) {
-                            fs.status = FileDownloadStatus::Failed;
-                            fs.error_message = Some(e.to_string());
-                        }
-                        Err(e)
+                        file_status[position].status = FileDownloadStatus::Failed;
+                        file_status[position].error_message = Some(e.to_string());
+                        failed_download = Some(format!("{}: {}", file_url, e));
                     }
                 }
-            });
 
-            file_tasks.push(task);
-        }
-
-        // Wait for all files and collect results
-        log::info!("Waiting for {} file downloads to complete...", log_files.len());
-        let results = futures::future::join_all(file_tasks).await;
-        log::info!("All download tasks completed");
-
-        let total_results = results.len();
-        let mut downloaded_contents = Vec::new();
-        let mut total_downloaded_bytes = 0u64;
-        let mut failed_downloads = Vec::new();
-
-        for (index, result) in results.into_iter().enumerate() {
-            match result {
-                Ok(Ok((url, content, original_file_index))) => {
-                    log::info!("File {}/{} downloaded successfully: {} ({} chars, original_index={})",
-                        index + 1, total_results, url, content.len(), original_file_index);
-                    total_downloaded_bytes += content.len() as u64;
-
-                    // Verify content is not empty
-                    if content.is_empty() {
-                        log::error!("WARNING: Downloaded content is EMPTY for {}", url);
-                    } else {
-                        log::debug!("Content preview (first 200 chars): {}...", &content[..content.len().min(200)]);
-                    }
-
-                    // Store with the original file_index to preserve correct ordering
-                    downloaded_contents.push((url, content, original_file_index));
-                }
-                Ok(Err(e)) => {
-                    let error_msg = format!("File download {}/{} failed: {}", index + 1, total_results, e);
-                    log::error!("{}", error_msg);
-                    eprintln!("{}", error_msg);
-                    failed_downloads.push(error_msg);
-                }
-                Err(e) => {
-                    let error_msg = format!("Task {}/{} join error: {}", index + 1, total_results, e);
-                    log::error!("{}", error_msg);
-                    eprintln!("{}", error_msg);
-                    failed_downloads.push(error_msg);
+                // Emit progress after each file completes
+                progress_callback(ProgressStatus::Downloading {
+                    total_sessions,
+                    current_session: session_num,
+                    total_files: file_status.len(),
+                    completed_files: file_status.iter().filter(|f| matches!(f.status, FileDownloadStatus::Completed)).count(),
+                    failed_files: file_status.iter().filter(|f| matches!(f.status, FileDownloadStatus::Failed)).count(),
+                    speed: speed_calculator.format_speed(),
+                    files: file_status.clone(),
+                });
+
+                if failed_download.is_some() {
+                    break;
                 }
             }
+
+            if failed_download.is_none() {
+                progress_callback(ProgressStatus::Parsing { session: session_name.clone() });
+                let _ = files_tx.send(SessionFile::Complete);
+            }
         }
+        // Without `Complete` the writer rolls back, leaving any previous session in place
+        drop(files_tx);
+
+        let written = writer.await
+            .map_err(|e| HttpFetchError::ParseError(format!("Database task failed: {}", e)))?;
 
-        // CRITICAL: If any files failed to download, return error BEFORE deleting old session
-        // We require ALL files to succeed for complete log data
-        if !failed_downloads.is_empty() {
+        if let Some(failure) = failed_download {
             let error_summary = format!(
-                "Failed to download {}/{} files for session {}. Complete log data requires all files. Errors:\n  {}",
-                failed_downloads.len(),
-                total_results,
+                "Failed to download a file of session {}. Complete log data requires all files. Error:\n  {}",
                 session_name,
-                failed_downloads.join("\n  ")
+                failure
             );
             log::error!("{}", error_summary);
             eprintln!("{}", error_summary);
@@ -297,203 +263,124 @@ impl SessionDownloadCoordinator {
             });
         }
 
-        // Sort by original file_index to ensure correct processing order
-        downloaded_contents.sort_by_key(|(_, _, index)| *index);
-        log::info!("Sorted {} downloaded files by original file_index", downloaded_contents.len());
-
-        log::info!("Total downloaded: ~{} MB across {} files",
-            total_downloaded_bytes / 1024 / 1024, downloaded_contents.len());
-
-        // Update progress - do this BEFORE any async calls that might hold locks
-        {
-            let status_map = file_status.lock().map_err(|e| HttpFetchError::ParseError(format!("Mutex error: {}", e)))?;
-            let status_vec: Vec<FileStatus> = status_map.values().cloned().collect();
-            drop(status_map); // Release lock before any async operations
-
-            progress_callback(ProgressStatus::Downloading {
-                total_sessions,
-                current_session: session_num,
-                total_files: status_vec.len(),
-                completed_files: status_vec.iter().filter(|f| matches!(f.status, FileDownloadStatus::Completed)).count(),
-                failed_files: status_vec.iter().filter(|f| matches!(f.status, FileDownloadStatus::Failed)).count(),
-                speed: speed_calculator.format_speed(),
-                files: status_vec,
-            });
+        match written? {
+            Some(entry_count) => println!(
+                "Completed session {}: {} files, {} entries",
+                session_name,
+                log_files.len(),
+                entry_count
+            ),
+            None => println!("Warning: No valid log entries found for session {}", session_name),
         }
 
-        // Process and store in database
-        progress_callback(ProgressStatus::Parsing { session: session_name.clone() });
-        log::info!("[DB] Starting database operations for session {}", session_name);
-
-        // Generate session ID (needed for parsing)
-        let session_id = format!(
-            "session_{}_{}",
-            session_name.replace(|c: char| !c.is_alphanumeric() && c != '_', "_"),
-            Utc::now().timestamp_nanos_opt().unwrap_or_default()
-        );
-
-        // CRITICAL: Parse BEFORE deleting old session to avoid data loss on parse errors
-        log::info!("Starting to parse {} downloaded files for session {}", downloaded_contents.len(), session_name);
-
-        // Clone data needed for spawn_blocking
-        let downloaded_contents_for_parse = downloaded_contents.clone();
-        let session_id_for_parse = session_id.clone();
-
-        // IMPORTANT: Use spawn_blocking for CPU-intensive parsing
-        let parse_results = tokio::task::spawn_blocking(move || {
-            let downloaded_count = downloaded_contents_for_parse.len();
-            let mut entries = Vec::new();
-            let mut parse_errors = Vec::new();
+        Ok(session_id)
+    }
 
-            for (i, (file_url, html_content, file_index)) in downloaded_contents_for_parse.into_iter().enumerate() {
-                log::info!("[Parse {}/{}] Starting parse: {} ({} chars, file_index={})",
-                    i + 1, downloaded_count, file_url, html_content.len(), file_index);
+    /// Parse and store the files of `session` as [`download_single_session`](Self::download_single_session)
+    /// sends them, in one transaction that is only committed once
+    /// [`SessionFile::Complete`] arrives. `ready` is signalled once the
+    /// database is held. Returns the number of entries stored, or `None`
+    /// if the session had none.
+    fn write_session(
+        db: &DbPool,
+        mut session: crate::log_parser::TestSession,
+        ready: oneshot::Sender<()>,
+        files: std_mpsc::Receiver<SessionFile>,
+    ) -> Result<Option<usize>, HttpFetchError> {
+        let settings = crate::settings::load_settings();
+        let timestamps = settings.timestamp_parser();
+        let formats = settings.format_registry();
+        let write_error = |e: rusqlite::Error| {
+            log::error!("Failed to write session: {}", e);
+            HttpFetchError::ParseError(format!("Failed to write session: {}", e))
+        };
 
-                match HtmlLogParser::parse_html_string(&html_content, &file_url, &session_id_for_parse, file_index) {
-                    Ok(parsed_entries) => {
-                        log::info!("[Parse {}/{}] Completed: {} entries from {} (file_index={})",
-                            i + 1, downloaded_count, parsed_entries.len(), file_url, file_index);
-                        entries.extend(parsed_entries);
+        // Replaces any session with the same name and URL once committed
+        let mut db_manager = db.write();
+        let mut import = db_manager.begin_session_import(&session, false).map_err(write_error)?;
+        let _ = ready.send(());
+
+        let mut file_reports = Vec::new();
+        let mut total_entries = 0;
+        let mut header_entries = Vec::new();
+        let mut title = None;
+
+        // A file that fails to parse is recorded in its report and marks
+        // the session incomplete; the other files are still imported
+        loop {
+            let (file_url, file_index, body) = match files.recv() {
+                Ok(SessionFile::Body { url, file_index, body }) => (url, file_index, body),
+                Ok(SessionFile::Complete) => break,
+                // A download failed; dropping the import rolls the session back
+                Err(_) => return Ok(None),
+            };
+            log::info!("[Parse] Starting parse: {} (file_index={})", file_url, file_index);
+
+            let mut failed_write = None;
+            let streamed = formats.stream_reader_with_report(
+                body,
+                &file_url,
+                &session.id,
+                file_index,
+                &timestamps,
+                &mut |batch| match import.insert_parsed(batch) {
+                    Ok(batch) => {
+                        total_entries += batch.len();
+                        let wanted = ATTRIBUTE_ENTRY_LIMIT.saturating_sub(header_entries.len());
+                        header_entries.extend(batch.into_iter().take(wanted));
+                        Ok(())
                     }
                     Err(e) => {
-                        let error_msg = format!("[Parse {}/{}] FAILED for {}: {}", i + 1, downloaded_count, file_url, e);
-                        log::error!("{}", error_msg);
-                        eprintln!("{}", error_msg);
-                        parse_errors.push(error_msg);
+                        let message = e.to_string();
+                        failed_write = Some(e);
+                        Err(message.into())
                     }
-                }
-            }
-
-            // CRITICAL: If any files failed to parse, return error
-            // We require ALL files to parse successfully for complete log data
-            if !parse_errors.is_empty() {
-                return Err(format!(
-                    "Failed to parse {}/{} files. Complete log data requires all files to parse. Errors:\n  {}",
-                    parse_errors.len(),
-                    downloaded_count,
-                    parse_errors.join("\n  ")
-                ));
+                },
+            );
+            if let Some(e) = failed_write {
+                return Err(write_error(e));
             }
-
-            Ok(entries)
-        }).await
-        .map_err(|e| HttpFetchError::ParseError(format!("Parse task failed: {}", e)))?
-        .map_err(|e| HttpFetchError::ParseError(format!("Parse failed: {}", e)))?;
-
-        let all_entries = parse_results;
-        log::info!("Total entries parsed for session {}: {} entries from {} files", session_name, all_entries.len(), downloaded_contents.len());
-
-        // CRITICAL: Only delete old session AFTER parsing succeeds
-        // Delete existing session if any
-        // Create a new connection for deletion to avoid lock issues
-        match DatabaseManager::new(&db_path) {
-            Ok(delete_db_manager) => {
-                match delete_db_manager.delete_session_by_name_and_path(&session_name, &url) {
-                    Ok(Some(deleted_session_id)) => {
-                        println!("Deleted existing session: {}", deleted_session_id);
-                    }
-                    Ok(None) => {
-                        println!("No existing session to delete for {}", session_name);
-                    }
-                    Err(e) => {
-                        println!("Warning: Failed to delete existing session: {}", e);
+            match &streamed.report.error {
+                None => {
+                    log::info!("[Parse] Completed: {} entries from {} (file_index={})",
+                        streamed.report.rows, file_url, file_index);
+                    if file_index == 0 {
+                        title = streamed.title;
                     }
                 }
+                Some(e) => {
+                    log::error!("[Parse] FAILED for {}: {}", file_url, e);
+                }
             }
-            Err(e) => {
-                println!("Warning: Could not create database connection for deletion: {}", e);
-            }
+            file_reports.push(streamed.report);
         }
 
-        if all_entries.is_empty() {
-            log::error!("ERROR: No valid log entries found for session {}! Downloaded {} files but parsed 0 entries.", session_name, downloaded_contents.len());
-            println!("Warning: No valid log entries found for session {}", session_name);
-            return Ok(session_id);
+        // A session whose files all failed is still recorded so the failures show up
+        let incomplete = crate::import_report::is_incomplete(&file_reports);
+        if total_entries == 0 && !incomplete {
+            log::error!("ERROR: No valid log entries found for session {}! Downloaded {} files but parsed 0 entries.", session.name, file_reports.len());
+            return Ok(None);
         }
 
-        // Create test session
-        let entry_count = all_entries.len();
-        let test_session = crate::log_parser::TestSession {
-            id: session_id.clone(),
-            name: session_name.clone(),
-            directory_path: url.clone(),
-            file_count: log_files.len(),
-            total_entries: entry_count,
-            created_at: Some(Utc::now()),
-            last_parsed_at: Some(Utc::now()),
-            source_type: Some("http".to_string()),
-        };
-
-        // Clone test_session for spawn_blocking
-        let test_session_clone = test_session.clone();
-
-        log::info!("Creating session in database: {} with {} entries", session_id, entry_count);
-
-        // Clone data needed for logging after spawn_blocking
-        let session_name_for_log = session_name.clone();
-        let log_files_count = log_files.len();
+        session.total_entries = total_entries;
+        session.incomplete = incomplete;
+        session.attributes = settings.attribute_extractors().extract(&session.directory_path, title.as_deref(), &header_entries);
 
-        // Move all_entries into spawn_blocking
-        tokio::task::spawn_blocking(move || {
-            let mut db_manager = match DatabaseManager::new(&db_path) {
-                Ok(manager) => manager,
-                Err(e) => {
-                    log::error!("Failed to create database connection: {}", e);
-                    return;
-                }
-            };
-
-            if let Err(e) = db_manager.create_test_session(&test_session_clone) {
-                log::error!("Failed to create session: {}", e);
-                return;
-            }
-
-            log::info!("Session created, inserting {} entries...", all_entries.len());
-
-            let inserted_ids = match db_manager.insert_entries(&all_entries) {
-                Ok(ids) => {
-                    log::info!("Successfully inserted {} entries", ids.len());
-                    ids
-                }
-                Err(e) => {
-                    log::error!("Failed to insert entries: {}", e);
-                    return;
-                }
-            };
-
-            // Assign IDs to entries for auto-bookmark detection
-            // IMPORTANT: Modify entries in place to avoid cloning
-            let mut entries_with_ids = all_entries;
-            for (i, entry_id) in inserted_ids.iter().enumerate() {
-                if i < entries_with_ids.len() {
-                    entries_with_ids[i].id = Some(*entry_id);
-                }
-            }
-
-            // Find and create auto-bookmarks
-            use crate::bookmark_utils::{create_auto_bookmark, find_auto_bookmark_markers};
-            let auto_markers = find_auto_bookmark_markers(&entries_with_ids);
-            if !auto_markers.is_empty() {
-                log::info!("Found {} auto-bookmark markers, creating bookmarks...", auto_markers.len());
-                for (entry_id, title) in &auto_markers {
-                    let bookmark = create_auto_bookmark(*entry_id, title.clone());
-                    if let Err(e) = db_manager.add_bookmark(&bookmark) {
-                        log::warn!("Failed to create auto-bookmark '{}': {}", title, e);
-                    }
-                }
-            }
-
-            log::info!("Database operations completed for session {}", session_name_for_log);
-        }).await
-        .map_err(|e| HttpFetchError::ParseError(format!("Database task failed: {}", e)))?;
-
-        println!(
-            "Completed session {}: {} files, {} entries",
-            session_name,
-            log_files_count,
-            entry_count
-        );
-
-        Ok(session_id)
+        log::info!("Committing session {} with {} entries, {} auto-bookmarks", session.id, total_entries, import.auto_bookmarks());
+        import.finish(&session, &file_reports).map_err(write_error)?;
+        log::info!("Database operations completed for session {}", session.name);
+        Ok(Some(total_entries))
     }
 }
+
+/// What [`SessionDownloadCoordinator::download_single_session`] hands its writer
+enum SessionFile {
+    /// The next file, its body still arriving
+    Body {
+        url: String,
+        file_index: usize,
+        body: BodyReader,
+    },
+    /// Every file downloaded, so the session can be committed
+    Complete,
+}
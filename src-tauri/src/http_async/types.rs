@@ -15,7 +15,17 @@ pub enum ProgressStatus {
         files: Vec<FileStatus>,
     },
     Parsing { session: String },
+    /// 本地目录导入：已解析的文件数和条目数
+    ParsingFiles {
+        current_test: String,
+        total_files: usize,
+        parsed_files: usize,
+        entries: usize,
+    },
     Complete,
+    Cancelled,
+    /// 导入失败，附错误信息
+    Failed { error: String },
 }
 
 /// 文件下载状态
@@ -36,21 +46,3 @@ pub enum FileDownloadStatus {
     Failed,
     Retrying,
 }
-
-/// 下载结果
-#[derive(Debug)]
-pub struct DownloadResult {
-    pub url: String,
-    pub content: String,
-    pub bytes_downloaded: u64,
-}
-
-impl DownloadResult {
-    pub fn new(url: String, content: String, bytes_downloaded: u64) -> Self {
-        Self {
-            url,
-            content,
-            bytes_downloaded,
-        }
-    }
-}
@@ -1,3 +1,5 @@
+use crate::db_pool::DbPool;
+use crate::session_attributes::ATTRIBUTE_ENTRY_LIMIT;
 use reqwest::blocking::Client;
 use reqwest::Url;
 use scraper::{Html, Selector};
@@ -155,16 +157,29 @@ impl HttpLogFetcher {
             .map_err(|e| HttpFetchError::NetworkError(e))
     }
 
-    /// Filter URLs to only include test log files matching the pattern
+    /// Open a single log file as a byte stream so it can be parsed while downloading
+    pub fn fetch_log_stream(&self, file_url: &str) -> Result<reqwest::blocking::Response, HttpFetchError> {
+        let response = self
+            .client
+            .get(file_url)
+            .send()
+            .map_err(|e| HttpFetchError::NetworkError(e))?;
+
+        if !response.status().is_success() {
+            return Err(HttpFetchError::DownloadFailed {
+                url: file_url.to_string(),
+                reason: format!("HTTP status: {}", response.status()),
+            });
+        }
+
+        Ok(response)
+    }
+
+    /// Filter URLs to only include log files a registered format assigns to a session
     pub fn filter_test_log_files(urls: &[String]) -> Vec<String> {
+        let formats = crate::settings::load_settings().format_registry();
         urls.iter()
-            .filter(|url| {
-                if let Some(filename) = url.rsplit('/').next() {
-                    crate::log_parser::HtmlLogParser::is_test_log_file(filename).is_some()
-                } else {
-                    false
-                }
-            })
+            .filter(|url| formats.classify_url(url).is_some())
             .cloned()
             .collect()
     }
@@ -175,8 +190,6 @@ pub fn scan_http_url(
     url: String,
     existing_sessions: &[crate::log_parser::TestSession],
 ) -> Result<Vec<crate::log_parser::ScanResult>, HttpFetchError> {
-    use crate::log_parser::HtmlLogParser;
-
     println!("[scan_http_url] Starting scan for URL: {}", url);
 
     // Create fetcher
@@ -197,13 +210,12 @@ pub fn scan_http_url(
     }
 
     // Group by test session (test_name + test_id)
+    let formats = crate::settings::load_settings().format_registry();
     let mut session_groups: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
     for log_url in &test_log_urls {
-        if let Some(filename) = log_url.rsplit('/').next() {
-            if let Some(test_name) = HtmlLogParser::is_test_log_file(filename) {
-                println!("[scan_http_url] Found test: {} from file: {}", test_name, filename);
-                session_groups.entry(test_name).or_default().push(log_url.clone());
-            }
+        if let Some((format, test_name)) = formats.classify_url(log_url) {
+            println!("[scan_http_url] Found {} test: {} from {}", format.name(), test_name, log_url);
+            session_groups.entry(test_name).or_default().push(log_url.clone());
         }
     }
 
@@ -239,13 +251,11 @@ pub fn scan_http_url(
 
 /// Fetch all logs from HTTP server and return session IDs
 pub fn fetch_logs_from_http(
-    db_path: String,
+    db: &DbPool,
     url: String,
     progress_callback: impl Fn(String),
     selected_tests: Option<Vec<String>>,
 ) -> Result<Vec<String>, HttpFetchError> {
-    use crate::database::DatabaseManager;
-    use crate::log_parser::HtmlLogParser;
     use chrono::Utc;
 
     progress_callback("Connecting to server...".to_string());
@@ -270,15 +280,15 @@ pub fn fetch_logs_from_http(
     progress_callback(format!("Found {} test log file(s)", test_log_urls.len()));
 
     // Group by test session - use test_name directly as key (matches scan_http_url behavior)
-    // test_name from is_test_log_file already includes the ID (e.g., "TestSimpleIO_ID_1")
+    // The session name already includes the ID (e.g., "TestSimpleIO_ID_1")
+    let settings = crate::settings::load_settings();
+    let formats = settings.format_registry();
     let mut session_groups: std::collections::HashMap<String, Vec<(String, usize)>> = std::collections::HashMap::new();
     for (index, log_url) in test_log_urls.iter().enumerate() {
-        if let Some(filename) = log_url.rsplit('/').next() {
-            if let Some(test_name) = HtmlLogParser::is_test_log_file(filename) {
-                println!("[fetch_logs_from_http] Found test: {} from file: {}", test_name, filename);
-                // Use test_name directly as the key - don't add extra ID suffix
-                session_groups.entry(test_name.clone()).or_default().push((log_url.clone(), index));
-            }
+        if let Some((_, test_name)) = formats.classify_url(log_url) {
+            println!("[fetch_logs_from_http] Found test: {} from {}", test_name, log_url);
+            // Use test_name directly as the key - don't add extra ID suffix
+            session_groups.entry(test_name).or_default().push((log_url.clone(), index));
         }
     }
 
@@ -305,11 +315,9 @@ pub fn fetch_logs_from_http(
         return Ok(vec![]);
     }
 
-    // Initialize database
-    let mut db_manager = DatabaseManager::new(&db_path)
-        .map_err(|e| HttpFetchError::ParseError(format!("Failed to initialize database: {}", e)))?;
-
     let mut session_ids = Vec::new();
+    let timestamps = settings.timestamp_parser();
+    let extractors = settings.attribute_extractors();
 
     // Download and process each session
     let counter = Arc::new(AtomicUsize::new(0));
@@ -319,28 +327,6 @@ pub fn fetch_logs_from_http(
         let count = counter.fetch_add(1, Ordering::SeqCst) + 1;
         progress_callback(format!("Processing session ({}/{})...", count, total));
 
-        // Delete existing session with the same name and directory path if it exists
-        // Use a separate database connection to avoid lock issues
-        let db_path_for_delete = db_path.clone();
-        match crate::database::DatabaseManager::new(&db_path_for_delete) {
-            Ok(delete_db_manager) => {
-                match delete_db_manager.delete_session_by_name_and_path(&session_key, &url) {
-                    Ok(Some(deleted_session_id)) => {
-                        println!("[HTTP] Deleted existing session: {}", deleted_session_id);
-                    }
-                    Ok(None) => {
-                        println!("[HTTP] No existing session to delete for {}", session_key);
-                    }
-                    Err(e) => {
-                        println!("[HTTP] Warning: Failed to delete existing session: {}", e);
-                    }
-                }
-            }
-            Err(e) => {
-                println!("[HTTP] Warning: Could not create database connection for deletion: {}", e);
-            }
-        }
-
         // Generate session ID
         let session_id = format!(
             "session_{}_{}",
@@ -349,7 +335,31 @@ pub fn fetch_logs_from_http(
         );
         println!("[HTTP] Generated session_id: {}", session_id);
 
-        let mut all_entries = Vec::new();
+        let mut test_session = crate::log_parser::TestSession {
+            id: session_id.clone(),
+            name: session_key.clone(),
+            directory_path: url.clone(),
+            file_count: log_files.len(),
+            total_entries: 0,
+            created_at: Some(Utc::now()),
+            last_parsed_at: Some(Utc::now()),
+            source_type: Some("http".to_string()),
+            incomplete: false,
+            attributes: Default::default(),
+        };
+
+        // Entries are written as they are parsed, in one transaction that
+        // replaces any session with the same name and URL once it commits
+        let write_error = |e: rusqlite::Error| {
+            println!("[HTTP] Error writing session: {:?}", e);
+            HttpFetchError::ParseError(format!("Failed to write session: {}", e))
+        };
+        let mut db_manager = db.write();
+        let mut import = db_manager.begin_session_import(&test_session, false).map_err(write_error)?;
+
+        let mut file_reports = Vec::new();
+        let mut total_entries = 0;
+        let mut header_entries = Vec::new();
 
         // Download each log file in the session
         for (file_index, (log_url, _)) in log_files.iter().enumerate() {
@@ -358,98 +368,64 @@ pub fn fetch_logs_from_http(
             // Small delay for politeness
             std::thread::sleep(Duration::from_millis(50));
 
-            println!("[HTTP] Fetching HTML from: {}", log_url);
-            let html_content = match fetcher.fetch_log_file(log_url) {
-                Ok(content) => {
-                    println!("[HTTP] Successfully fetched {} bytes", content.len());
-                    content
-                }
+            println!("[HTTP] Streaming log from: {}", log_url);
+            let response = match fetcher.fetch_log_stream(log_url) {
+                Ok(response) => response,
                 Err(e) => {
                     println!("[HTTP] Error fetching {}: {:?}", log_url, e);
                     return Err(e);
                 }
             };
 
-            // Parse using HTML string parser
-            println!("[HTTP] Parsing HTML content...");
-            let entries = match HtmlLogParser::parse_html_string(&html_content, log_url, &session_id, file_index) {
-                Ok(entries) => {
-                    println!("[HTTP] Parsed {} entries from {}", entries.len(), log_url);
-                    entries
-                }
-                Err(e) => {
-                    println!("[HTTP] Error parsing {}: {:?}", log_url, e);
-                    return Err(HttpFetchError::ParseError(format!("Failed to parse {}: {}", log_url, e)));
-                }
-            };
-
-            all_entries.extend(entries);
+            // Parse rows as they arrive instead of buffering the whole document.
+            // A file that fails is recorded in the report and the rest still imported.
+            let mut failed_write = None;
+            let streamed = formats.stream_reader_with_report(
+                response,
+                log_url,
+                &session_id,
+                file_index,
+                &timestamps,
+                &mut |batch| match import.insert_parsed(batch) {
+                    Ok(batch) => {
+                        total_entries += batch.len();
+                        let wanted = ATTRIBUTE_ENTRY_LIMIT.saturating_sub(header_entries.len());
+                        header_entries.extend(batch.into_iter().take(wanted));
+                        Ok(())
+                    }
+                    Err(e) => {
+                        let message = e.to_string();
+                        failed_write = Some(e);
+                        Err(message.into())
+                    }
+                },
+            );
+            if let Some(e) = failed_write {
+                return Err(write_error(e));
+            }
+            match &streamed.report.error {
+                None => println!("[HTTP] Parsed {} entries from {}", streamed.report.rows, log_url),
+                Some(e) => println!("[HTTP] Error parsing {}: {}", log_url, e),
+            }
+            file_reports.push(streamed.report);
         }
 
-        println!("[HTTP] Total entries parsed: {}", all_entries.len());
+        println!("[HTTP] Total entries parsed: {}", total_entries);
 
-        if all_entries.is_empty() {
+        // A session whose files all failed is still recorded so the failures show up
+        let incomplete = crate::import_report::is_incomplete(&file_reports);
+        if total_entries == 0 && !incomplete {
             println!("Warning: No valid log entries found for session {}", session_key);
             continue;
         }
 
-        // Save the total entries count before moving all_entries
-        let total_entries = all_entries.len();
-
-        // Create test session
         println!("[HTTP] Creating test session: id={}, name={}", session_id, session_key);
-        let test_session = crate::log_parser::TestSession {
-            id: session_id.clone(),
-            name: session_key.clone(),
-            directory_path: url.clone(),
-            file_count: log_files.len(),
-            total_entries,
-            created_at: Some(Utc::now()),
-            last_parsed_at: Some(Utc::now()),
-            source_type: Some("http".to_string()),
-        };
-
-        db_manager
-            .create_test_session(&test_session)
-            .map_err(|e| {
-                println!("[HTTP] Error creating session: {:?}", e);
-                HttpFetchError::ParseError(format!("Failed to create session: {}", e))
-            })?;
-        println!("[HTTP] Session created successfully in database");
-
-        let inserted_ids = db_manager
-            .insert_entries(&all_entries)
-            .map_err(|e| {
-                println!("[HTTP] Error inserting entries: {:?}", e);
-                HttpFetchError::ParseError(format!("Failed to insert entries: {}", e))
-            })?;
-        println!("[HTTP] Inserted {} entries into database", inserted_ids.len());
-
-        // Assign IDs to entries for auto-bookmark detection
-        let mut entries_with_ids = all_entries;
-        for (i, entry_id) in inserted_ids.iter().enumerate() {
-            if i < entries_with_ids.len() {
-                entries_with_ids[i].id = Some(*entry_id);
-            }
-        }
-
-        // Find and create auto-bookmarks for ###MARKER### patterns
-        use crate::bookmark_utils::{create_auto_bookmark, find_auto_bookmark_markers};
-        let auto_markers = find_auto_bookmark_markers(&entries_with_ids);
-        if !auto_markers.is_empty() {
-            println!("Found {} auto-bookmark markers", auto_markers.len());
-            for (entry_id, title) in &auto_markers {
-                let bookmark = create_auto_bookmark(*entry_id, title.clone());
-                match db_manager.add_bookmark(&bookmark) {
-                    Ok(_) => {
-                        println!("Created auto-bookmark: '{}'", title);
-                    }
-                    Err(e) => {
-                        eprintln!("Warning: Failed to create auto-bookmark '{}': {}", title, e);
-                    }
-                }
-            }
-        }
+        test_session.total_entries = total_entries;
+        test_session.incomplete = incomplete;
+        test_session.attributes = extractors.extract(&url, None, &header_entries);
+        let auto_bookmarks = import.auto_bookmarks();
+        import.finish(&test_session, &file_reports).map_err(write_error)?;
+        println!("[HTTP] Inserted {} entries into database", total_entries);
 
         session_ids.push(session_id);
 
@@ -458,7 +434,7 @@ pub fn fetch_logs_from_http(
             session_key,
             log_files.len(),
             total_entries,
-            auto_markers.len()
+            auto_bookmarks
         );
     }
 
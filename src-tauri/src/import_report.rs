@@ -0,0 +1,213 @@
+//! What happened to each file of an import
+//!
+//! Every imported session keeps one report row per file, so files that failed
+//! to parse, skipped rows and holes in the `---N` file sequence stay visible
+//! after the import instead of only showing up in the console.
+
+use crate::archive;
+use crate::fingerprint::FileFingerprint;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+/// Outcome of parsing one file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileParseStatus {
+    /// Parsed into at least one entry
+    Parsed,
+    /// Parsed without errors but produced no entries
+    Empty,
+    /// Could not be read or parsed to the end; entries after the error are
+    /// missing from the session
+    Failed,
+}
+
+impl FileParseStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Parsed => "parsed",
+            Self::Empty => "empty",
+            Self::Failed => "failed",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "parsed" => Self::Parsed,
+            "empty" => Self::Empty,
+            _ => Self::Failed,
+        }
+    }
+}
+
+/// Byte and row counts gathered while parsing one source
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ParseStats {
+    /// Decoded bytes read from the source
+    pub bytes: u64,
+    /// Rows or lines the format could not turn into entries
+    pub skipped_rows: usize,
+    /// Character encoding the source was decoded with
+    pub encoding: Option<&'static str>,
+}
+
+/// Report for one file of an import
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileReport {
+    pub file_path: String,
+    /// Index from the file's `---N` suffix, if it has one
+    pub sequence_index: Option<usize>,
+    pub status: FileParseStatus,
+    pub bytes: u64,
+    /// Entries produced
+    pub rows: usize,
+    pub skipped_rows: usize,
+    /// Character encoding the file was decoded with, e.g. `UTF-8` or `GBK`
+    pub encoding: Option<String>,
+    /// Parse error, including the message of a panic raised by the parser
+    pub error: Option<String>,
+    /// Fingerprint taken before parsing; `None` if the file could not be read
+    pub fingerprint: Option<FileFingerprint>,
+}
+
+impl FileReport {
+    /// Report of a file that produced `rows` entries, before `error` if it failed
+    pub fn new(file_path: &str, rows: usize, error: Option<String>, stats: ParseStats) -> Self {
+        let status = match (&error, rows) {
+            (Some(_), _) => FileParseStatus::Failed,
+            (None, 0) => FileParseStatus::Empty,
+            (None, _) => FileParseStatus::Parsed,
+        };
+        Self {
+            file_path: file_path.to_string(),
+            sequence_index: sequence_index(file_path),
+            status,
+            bytes: stats.bytes,
+            rows,
+            skipped_rows: stats.skipped_rows,
+            encoding: stats.encoding.map(str::to_string),
+            error,
+            fingerprint: None,
+        }
+    }
+}
+
+/// Import report of a session, as stored with it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportReport {
+    pub session_id: String,
+    pub files: Vec<FileReport>,
+    /// `---N` indexes absent between the lowest and highest index present
+    pub missing_indexes: Vec<usize>,
+    /// Some file failed or the file sequence has gaps
+    pub incomplete: bool,
+}
+
+impl ImportReport {
+    pub fn new(session_id: &str, files: Vec<FileReport>) -> Self {
+        let missing_indexes = missing_indexes(&files);
+        let incomplete = is_incomplete(&files);
+        Self {
+            session_id: session_id.to_string(),
+            files,
+            missing_indexes,
+            incomplete,
+        }
+    }
+}
+
+/// Whether a session built from `files` is missing data
+pub fn is_incomplete(files: &[FileReport]) -> bool {
+    files.iter().any(|f| f.status == FileParseStatus::Failed) || !missing_indexes(files).is_empty()
+}
+
+/// Index `N` of a `<name>---N.<ext>` file, or `None` without such a suffix
+///
+/// # Examples
+/// ```
+/// use logterminator_lib::import_report::sequence_index;
+/// assert_eq!(sequence_index("/logs/TestA_ID_1---3.html"), Some(3));
+/// assert_eq!(sequence_index("run.zip!/TestA_ID_1---12.log.gz"), Some(12));
+/// assert_eq!(sequence_index("/logs/service.log"), None);
+/// ```
+pub fn sequence_index(file_path: &str) -> Option<usize> {
+    let name = archive::logical_file_name(file_path);
+    let after_dash = &name[name.rfind("---")? + 3..];
+    after_dash.split('.').next()?.parse().ok()
+}
+
+/// Indexes missing from the `---N` sequence of `files`
+pub fn missing_indexes(files: &[FileReport]) -> Vec<usize> {
+    let mut present: Vec<usize> = files.iter().filter_map(|f| f.sequence_index).collect();
+    present.sort_unstable();
+    present.dedup();
+
+    present
+        .windows(2)
+        .flat_map(|pair| pair[0] + 1..pair[1])
+        .collect()
+}
+
+/// Text of a panic payload caught by `catch_unwind`
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(path: &str, status: FileParseStatus) -> FileReport {
+        FileReport {
+            file_path: path.to_string(),
+            sequence_index: sequence_index(path),
+            status,
+            bytes: 0,
+            rows: 0,
+            skipped_rows: 0,
+            encoding: None,
+            error: None,
+            fingerprint: None,
+        }
+    }
+
+    #[test]
+    fn test_missing_indexes() {
+        let files = vec![
+            report("T_ID_1---1.html", FileParseStatus::Parsed),
+            report("T_ID_1---4.html", FileParseStatus::Parsed),
+            report("T_ID_1---2.html", FileParseStatus::Empty),
+            report("extra.html", FileParseStatus::Parsed),
+        ];
+        assert_eq!(missing_indexes(&files), vec![3]);
+        assert!(ImportReport::new("s", files).incomplete);
+
+        let complete = vec![report("T---0.html", FileParseStatus::Parsed), report("T---1.html", FileParseStatus::Empty)];
+        assert!(!is_incomplete(&complete));
+    }
+
+    #[test]
+    fn test_failed_file_is_incomplete() {
+        let failed = FileReport::new(
+            "T---0.html",
+            0,
+            Some("bad table".to_string()),
+            ParseStats { bytes: 10, skipped_rows: 0, encoding: None },
+        );
+        assert_eq!(failed.status, FileParseStatus::Failed);
+        assert_eq!(failed.error.as_deref(), Some("bad table"));
+        assert!(is_incomplete(&[failed]));
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("row {} broke", 7)).unwrap_err();
+        assert_eq!(panic_message(&*payload), "row 7 broke");
+    }
+}
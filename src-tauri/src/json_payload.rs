@@ -0,0 +1,302 @@
+//! JSON payloads embedded in log messages
+//!
+//! The first JSON object or array found in a message is stored next to the
+//! entry in compact form, so entries can be filtered on its fields with
+//! SQLite's JSON functions, e.g. `$.status == 500 && $.device.id = "abc"`.
+
+use serde_json::Value;
+
+/// First non-empty JSON object or array inside `message`, re-serialized compactly
+///
+/// The "add JSON payloads" migration backfilled older rows with this, so
+/// the payload found for a message must stay the same (see the `migrations` module).
+///
+/// # Examples
+/// ```
+/// use logterminator_lib::json_payload::extract_json_payload;
+/// let payload = extract_json_payload(r#"[INFO] response: { "status": 500 } (12ms)"#);
+/// assert_eq!(payload.as_deref(), Some(r#"{"status":500}"#));
+/// ```
+pub fn extract_json_payload(message: &str) -> Option<String> {
+    let mut start = 0;
+    while let Some(offset) = message[start..].find(['{', '[']) {
+        let begin = start + offset;
+        // Objects and arrays end at their closing bracket, whatever follows
+        let value = serde_json::Deserializer::from_str(&message[begin..])
+            .into_iter::<Value>()
+            .next()
+            .and_then(Result::ok);
+        match value {
+            Some(Value::Object(map)) if !map.is_empty() => return Some(Value::Object(map).to_string()),
+            Some(Value::Array(items)) if !items.is_empty() => return Some(Value::Array(items).to_string()),
+            _ => start = begin + 1,
+        }
+    }
+    None
+}
+
+/// Comparison in a JSON path predicate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JsonOp {
+    /// Bare path: the field is present
+    Exists,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl JsonOp {
+    fn sql(self) -> &'static str {
+        match self {
+            Self::Exists => "IS NOT NULL",
+            Self::Eq => "=",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        }
+    }
+}
+
+/// One `<path> <op> <value>` test against an entry's JSON payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPredicate {
+    /// SQLite JSON path, e.g. `$.device.id` or `$.items[0]`
+    pub path: String,
+    pub op: JsonOp,
+    pub value: Value,
+}
+
+impl JsonPredicate {
+    /// Parse predicates joined by `&&`; blank input yields no predicates.
+    ///
+    /// Values are JSON literals; a bare word is taken as a string, so
+    /// `$.state = ok` and `$.state = "ok"` are the same. `=` and `==` are
+    /// interchangeable.
+    pub fn parse_all(input: &str) -> Result<Vec<Self>, String> {
+        let mut rest = input.trim();
+        let mut predicates = Vec::new();
+
+        while !rest.is_empty() {
+            let (predicate, after) = Self::parse_one(rest)
+                .map_err(|e| format!("Invalid JSON filter '{}': {}", input.trim(), e))?;
+            predicates.push(predicate);
+
+            rest = after.trim_start();
+            if let Some(next) = rest.strip_prefix("&&") {
+                rest = next.trim_start();
+                if rest.is_empty() {
+                    return Err(format!("Invalid JSON filter '{}': expected a predicate after '&&'", input.trim()));
+                }
+            } else if !rest.is_empty() {
+                return Err(format!("Invalid JSON filter '{}': unexpected '{}'", input.trim(), rest));
+            }
+        }
+
+        Ok(predicates)
+    }
+
+    fn parse_one(input: &str) -> Result<(Self, &str), String> {
+        let (path, rest) = split_path(input)?;
+        let rest = rest.trim_start();
+
+        let Some((op, rest)) = split_op(rest) else {
+            return Ok((Self { path, op: JsonOp::Exists, value: Value::Null }, rest));
+        };
+        let (value, rest) = split_value(rest.trim_start())?;
+
+        let comparable = match &value {
+            Value::Number(_) | Value::String(_) => true,
+            Value::Bool(_) | Value::Null => matches!(op, JsonOp::Eq | JsonOp::Ne),
+            _ => false,
+        };
+        if !comparable {
+            return Err(format!("cannot compare with {} using '{}'", value, op.sql()));
+        }
+
+        Ok((Self { path, op, value }, rest))
+    }
+
+    /// Append the SQL condition and its bound parameters
+    pub fn push_condition(&self, where_conditions: &mut Vec<String>, params: &mut Vec<Box<dyn rusqlite::ToSql>>) {
+        params.push(Box::new(self.path.clone()));
+
+        // json_extract turns JSON true/false/null into 1/0/NULL, so those are
+        // tested through json_type instead
+        let (condition, value): (String, Option<Box<dyn rusqlite::ToSql>>) = match (&self.value, self.op) {
+            (_, JsonOp::Exists) => ("json_type(json_payload, ?) IS NOT NULL".to_string(), None),
+            (Value::Null | Value::Bool(_), op) => (
+                format!("json_type(json_payload, ?) {} ?", op.sql()),
+                Some(Box::new(self.value.to_string())),
+            ),
+            (Value::Number(n), op) => {
+                let number: Box<dyn rusqlite::ToSql> = match n.as_i64() {
+                    Some(i) => Box::new(i),
+                    None => Box::new(n.as_f64().unwrap_or_default()),
+                };
+                (format!("json_extract(json_payload, ?) {} ?", op.sql()), Some(number))
+            }
+            (Value::String(s), op) => (
+                format!("json_extract(json_payload, ?) {} ?", op.sql()),
+                Some(Box::new(s.clone())),
+            ),
+            // Objects and arrays come back from json_extract as compact JSON text
+            (other, op) => (
+                format!("json_extract(json_payload, ?) {} ?", op.sql()),
+                Some(Box::new(other.to_string())),
+            ),
+        };
+
+        where_conditions.push(format!("(json_payload IS NOT NULL AND {})", condition));
+        params.extend(value);
+    }
+}
+
+/// Split a `$`-rooted path made of `.key`, `."quoted key"` and `[n]` steps
+fn split_path(input: &str) -> Result<(String, &str), String> {
+    let Some(mut rest) = input.strip_prefix('$') else {
+        return Err("path must start with '$'".to_string());
+    };
+
+    loop {
+        if let Some(after) = rest.strip_prefix(".\"") {
+            let end = after.find('"').ok_or("unclosed quoted key")?;
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix('.') {
+            let end = after
+                .find(|c: char| c.is_whitespace() || ".[=!<>&".contains(c))
+                .unwrap_or(after.len());
+            if end == 0 {
+                return Err("empty key in path".to_string());
+            }
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or("unclosed '[' in path")?;
+            if after[..end].parse::<usize>().is_err() {
+                return Err(format!("invalid array index '{}'", &after[..end]));
+            }
+            rest = &after[end + 1..];
+        } else {
+            break;
+        }
+    }
+
+    let path = &input[..input.len() - rest.len()];
+    Ok((path.to_string(), rest))
+}
+
+fn split_op(input: &str) -> Option<(JsonOp, &str)> {
+    // Two-character operators first so `<=` is not read as `<`
+    const OPS: [(&str, JsonOp); 7] = [
+        ("==", JsonOp::Eq),
+        ("!=", JsonOp::Ne),
+        ("<=", JsonOp::Le),
+        (">=", JsonOp::Ge),
+        ("=", JsonOp::Eq),
+        ("<", JsonOp::Lt),
+        (">", JsonOp::Gt),
+    ];
+    OPS.iter()
+        .find_map(|(text, op)| input.strip_prefix(text).map(|rest| (*op, rest)))
+}
+
+fn split_value(input: &str) -> Result<(Value, &str), String> {
+    let token_end = input
+        .find(|c: char| c.is_whitespace() || c == '&')
+        .unwrap_or(input.len());
+    if token_end == 0 && !input.starts_with('"') {
+        return Err("missing value".to_string());
+    }
+
+    if input.starts_with('"') {
+        let mut values = serde_json::Deserializer::from_str(input).into_iter::<Value>();
+        return match values.next() {
+            Some(Ok(value)) => Ok((value, &input[values.byte_offset()..])),
+            _ => Err("unterminated string value".to_string()),
+        };
+    }
+
+    let (token, rest) = input.split_at(token_end);
+    match serde_json::from_str::<Value>(token) {
+        Ok(value @ (Value::Number(_) | Value::Bool(_) | Value::Null)) => Ok((value, rest)),
+        _ => Ok((Value::String(token.to_string()), rest)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_payload() {
+        assert_eq!(
+            extract_json_payload(r#"POST /api -> {"status": 500, "body": {"err": "x}"}} done"#).as_deref(),
+            Some(r#"{"body":{"err":"x}"},"status":500}"#)
+        );
+        assert_eq!(extract_json_payload("ids: [1, 2]").as_deref(), Some("[1,2]"));
+        // Bracketed tags and empty structures are not payloads
+        assert_eq!(extract_json_payload("[STEP 1] {} started"), None);
+        assert_eq!(extract_json_payload("{unterminated"), None);
+    }
+
+    #[test]
+    fn test_parse_predicates() {
+        let predicates = JsonPredicate::parse_all(r#"$.status == 500 && $.device.id = "a b" && $.items[0].ok"#).unwrap();
+        assert_eq!(
+            predicates,
+            vec![
+                JsonPredicate { path: "$.status".to_string(), op: JsonOp::Eq, value: Value::from(500) },
+                JsonPredicate { path: "$.device.id".to_string(), op: JsonOp::Eq, value: Value::from("a b") },
+                JsonPredicate { path: "$.items[0].ok".to_string(), op: JsonOp::Exists, value: Value::Null },
+            ]
+        );
+
+        let bare = JsonPredicate::parse_all(r#"$."user name"!=bob"#).unwrap();
+        assert_eq!(bare[0].path, r#"$."user name""#);
+        assert_eq!(bare[0].op, JsonOp::Ne);
+        assert_eq!(bare[0].value, Value::from("bob"));
+
+        assert!(JsonPredicate::parse_all("   ").unwrap().is_empty());
+        assert!(JsonPredicate::parse_all("status == 500").is_err());
+        assert!(JsonPredicate::parse_all("$.ok < true").is_err());
+        assert!(JsonPredicate::parse_all("$.a == 1 &&").is_err());
+        assert!(JsonPredicate::parse_all("$.a == 1 $.b").is_err());
+    }
+
+    #[test]
+    fn test_predicates_in_sqlite() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"CREATE TABLE log_entries (id INTEGER, json_payload TEXT);
+               INSERT INTO log_entries VALUES
+                 (1, '{"status":500,"device":{"id":"abc"},"retry":true}'),
+                 (2, '{"status":200,"device":{"id":"xyz"},"retry":false}'),
+                 (3, NULL);"#,
+        )
+        .unwrap();
+
+        let matching = |filter: &str| -> Vec<i64> {
+            let mut conditions = Vec::new();
+            let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+            for predicate in JsonPredicate::parse_all(filter).unwrap() {
+                predicate.push_condition(&mut conditions, &mut params);
+            }
+            let sql = format!("SELECT id FROM log_entries WHERE {} ORDER BY id", conditions.join(" AND "));
+            let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+            let mut stmt = conn.prepare(&sql).unwrap();
+            let ids = stmt.query_map(&param_refs[..], |row| row.get(0)).unwrap();
+            ids.map(Result::unwrap).collect()
+        };
+
+        assert_eq!(matching("$.status == 500"), vec![1]);
+        assert_eq!(matching("$.status >= 200 && $.status < 500"), vec![2]);
+        assert_eq!(matching(r#"$.device.id = "abc""#), vec![1]);
+        assert_eq!(matching("$.retry == true"), vec![1]);
+        assert_eq!(matching("$.device"), vec![1, 2]);
+        assert_eq!(matching("$.missing"), Vec::<i64>::new());
+    }
+}
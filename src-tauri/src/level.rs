@@ -0,0 +1,101 @@
+//! Canonical log levels and their severities
+//!
+//! Levels are normalized once, when entries are parsed: surrounding brackets
+//! and case are dropped and synonyms map to one name, so `[warn]` and
+//! `WARNING` are both stored as `WARNING`. Each canonical level has a numeric
+//! severity that the minimum-severity filter compares against. Other levels,
+//! such as `MARKER`, are kept as they are (upper-cased) and have no severity.
+
+/// Canonical levels with their severities, lowest first
+pub const CANONICAL_LEVELS: [(&str, i64); 6] = [
+    ("TRACE", 5),
+    ("DEBUG", 10),
+    ("INFO", 20),
+    ("WARNING", 30),
+    ("ERROR", 40),
+    ("CRITICAL", 50),
+];
+
+/// Other spellings of the canonical levels
+const SYNONYMS: [(&str, &str); 7] = [
+    ("WARN", "WARNING"),
+    ("ERR", "ERROR"),
+    ("SEVERE", "ERROR"),
+    ("FATAL", "CRITICAL"),
+    ("CRIT", "CRITICAL"),
+    ("INFORMATION", "INFO"),
+    ("DBG", "DEBUG"),
+];
+
+/// Canonical name of a known level or synonym, e.g. `warn` -> `WARNING`
+fn canonical(level: &str) -> Option<&'static str> {
+    let upper = level.to_ascii_uppercase();
+    CANONICAL_LEVELS
+        .iter()
+        .map(|(name, _)| *name)
+        .find(|name| *name == upper)
+        .or_else(|| SYNONYMS.iter().find(|(alias, _)| *alias == upper).map(|(_, name)| *name))
+}
+
+/// Normalize a level as written in a log, e.g. `[Warn]` -> `WARNING`.
+///
+/// Unknown levels are upper-cased but otherwise kept. The levels of rows
+/// stored before normalization were rewritten with this by a migration, so
+/// a level it already maps must keep mapping to the same name; see
+/// the `migrations` module.
+pub fn normalize_level(raw: &str) -> String {
+    let level = raw.trim().trim_matches(&['[', ']', ':'] as &[char]).trim();
+    match canonical(level) {
+        Some(name) => name.to_string(),
+        None => level.to_uppercase(),
+    }
+}
+
+/// Severity of a normalized level; `None` for levels outside the canonical set.
+/// Stored with every entry, so existing values must not change.
+pub fn severity(level: &str) -> Option<i64> {
+    CANONICAL_LEVELS
+        .iter()
+        .find(|(name, _)| *name == level)
+        .map(|(_, severity)| *severity)
+}
+
+/// Canonical level named by a CSS class such as `<tr class="INFO">`.
+///
+/// Unlike cell text, a class only counts as a level if it is a known one, so
+/// styling classes like `odd` are not taken for levels.
+pub fn level_from_class(class: &str) -> Option<&'static str> {
+    canonical(class)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_level() {
+        assert_eq!(normalize_level("[INFO]"), "INFO");
+        assert_eq!(normalize_level(" warn "), "WARNING");
+        assert_eq!(normalize_level("Warning:"), "WARNING");
+        assert_eq!(normalize_level("FATAL"), "CRITICAL");
+        assert_eq!(normalize_level("critical"), "CRITICAL");
+        assert_eq!(normalize_level("[marker]"), "MARKER");
+        assert_eq!(normalize_level(""), "");
+    }
+
+    #[test]
+    fn test_severity() {
+        assert_eq!(severity("WARNING"), Some(30));
+        assert!(severity("CRITICAL") > severity("ERROR"));
+        assert_eq!(severity(&normalize_level("FATAL")), severity("CRITICAL"));
+        assert_eq!(severity("MARKER"), None);
+        assert_eq!(severity(""), None);
+    }
+
+    #[test]
+    fn test_level_from_class() {
+        assert_eq!(level_from_class("ERROR"), Some("ERROR"));
+        assert_eq!(level_from_class("warn"), Some("WARNING"));
+        assert_eq!(level_from_class("odd"), None);
+    }
+}
@@ -1,21 +1,98 @@
+pub mod archive;
 pub mod bookmark_utils;
 mod database;
+mod db_pool;
+pub mod encoding;
+pub mod fingerprint;
+pub mod follow;
+pub mod grouping;
 mod history;
+pub mod html_profile;
+pub mod html_stream;
 pub mod http_log_fetcher;
 pub mod http_async;
+pub mod import_report;
+pub mod json_payload;
+pub mod level;
+pub mod log_formats;
 pub mod log_parser;
-
-use crate::bookmark_utils::{create_auto_bookmark, find_auto_bookmark_markers};
-use crate::database::DatabaseManager;
-use crate::log_parser::{Bookmark, HtmlLogParser, LogEntry, ScanResult, TestSession};
+mod migrations;
+pub mod search_index;
+pub mod session_attributes;
+pub mod settings;
+pub mod storage;
+pub mod timestamp;
+pub mod traceback;
+mod worker_pool;
+mod workspace;
+
+use crate::archive::ArchiveCache;
+use crate::database::{DatabaseManager, EntryFilter, EntryPage, PageAnchor, UnparsedTimestamps};
+use crate::db_pool::DbPool;
+use crate::fingerprint::FileFingerprint;
+use crate::follow::{FollowUpdate, SessionFollower};
+use crate::http_async::ProgressStatus;
+use crate::import_report::{is_incomplete, FileParseStatus, FileReport, ImportReport};
+use crate::log_formats::StreamedFile;
+use crate::json_payload::JsonPredicate;
+use crate::log_parser::{Bookmark, DirectoryScan, LogEntry, ScanResult, TestSession};
+use crate::search_index::{fts_conditions, fts_query};
+use crate::session_attributes::ATTRIBUTE_ENTRY_LIMIT;
+use crate::storage::{expired_sessions, is_from_source, MaintenanceReport, StorageUsage};
+use crate::traceback::StackFrame;
+use crate::workspace::{Workspace, WorkspaceDirectory, WorkspaceList};
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
-use tauri::{Emitter, State};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use tauri::{Emitter, Manager, State};
 use regex::RegexBuilder;
 
+/// Database file of versions before workspaces, relative to the working directory
+const LEGACY_DATABASE: &str = "logterminator.db";
+
 // App state
 struct AppState {
-    db_manager: Mutex<DatabaseManager>,
+    /// Per-user app data directory, which holds the workspaces unless the
+    /// settings name another directory
+    data_dir: PathBuf,
+    /// Open workspace, whose database every command uses
+    workspace: RwLock<Workspace>,
+    /// Set by `cancel_import` to stop the running directory import
+    import_cancelled: Arc<AtomicBool>,
+    /// Session currently followed by `start_follow`, if any
+    follow: Mutex<Option<FollowHandle>>,
+}
+
+impl AppState {
+    /// Database of the open workspace. Work started before a switch, such as
+    /// an import, carries on in the workspace it started in.
+    fn db(&self) -> Arc<DbPool> {
+        self.workspace.read().unwrap_or_else(|e| e.into_inner()).db.clone()
+    }
+
+    /// The followed session. A command that panicked while holding the lock
+    /// leaves at worst a stale handle, which stopping or replacing deals with.
+    fn follow(&self) -> MutexGuard<'_, Option<FollowHandle>> {
+        self.follow.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Make `workspace` the open one, stopping a follow of a session in the previous one
+    fn switch_workspace(&self, workspace: Workspace) -> WorkspaceList {
+        if let Some(handle) = self.follow().take() {
+            println!("Stopping follow of session {} to switch workspace", handle.session_id);
+            handle.stop.store(true, Ordering::Relaxed);
+        }
+        let list = workspace.directory.list(&workspace.name);
+        *self.workspace.write().unwrap_or_else(|e| e.into_inner()) = workspace;
+        list
+    }
+}
+
+struct FollowHandle {
+    session_id: String,
+    stop: Arc<AtomicBool>,
 }
 
 #[derive(Deserialize)]
@@ -51,7 +128,16 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+/// WHERE clause restricting a search to one session plus the optional entry filters
+fn search_scope(session_id: &str, filter: &EntryFilter) -> (String, Vec<Box<dyn rusqlite::ToSql>>) {
+    let mut conditions = vec!["test_session_id = ?".to_string()];
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(session_id.to_string())];
+    filter.push_conditions(&mut conditions, &mut params);
+    (conditions.join(" AND "), params)
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn search_entries(
     search_type: String,
     search_term: Option<String>,
@@ -59,20 +145,32 @@ async fn search_entries(
     is_regex: bool,
     case_sensitive: Option<bool>,
     session_id: String,
+    thread_filter: Option<String>,
+    module_filter: Option<String>,
+    json_filter: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Vec<SearchResult>, String> {
     let case_sensitive = case_sensitive.unwrap_or(false);
-    let db_manager = state.db_manager.lock()
-        .map_err(|e| e.to_string())?;
+    let db = state.db();
+    let db_manager = db.read();
+
+    let entry_filter = EntryFilter {
+        thread: thread_filter,
+        module: module_filter,
+        json: JsonPredicate::parse_all(json_filter.as_deref().unwrap_or_default())?,
+        ..Default::default()
+    };
 
     // For regex search, fetch all entries and filter in Rust
     if is_regex {
-        let mut query = String::from(
+        let (scope, scope_params) = search_scope(&session_id, &entry_filter);
+        let query = format!(
             "SELECT id, timestamp, line_number, message
              FROM log_entries
-             WHERE test_session_id = ? ORDER BY timestamp ASC, id ASC"
+             WHERE {} ORDER BY timestamp_ms ASC, id ASC",
+            scope
         );
-        let params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(session_id.clone())];
+        let params = scope_params;
 
         let all_results = db_manager.search_entries_custom(&query, &params)
             .map_err(|e| e.to_string())?;
@@ -145,26 +243,71 @@ async fn search_entries(
         };
     }
 
-    // For non-regex search, use database LIKE query
-    let mut query = String::from(
+    // Words are looked up in the full-text index by prefix. Only terms it
+    // cannot answer, such as CJK text or punctuation, use the LIKE scan.
+    let fts_expression = match search_type.as_str() {
+        "simple" => search_term.as_deref().and_then(fts_query),
+        "advanced" => conditions.as_ref().and_then(|conds| {
+            conds
+                .iter()
+                .all(|c| matches!(c.operator.as_str(), "AND" | "OR"))
+                .then(|| fts_conditions(conds.iter().map(|c| (c.term.as_str(), c.operator.as_str()))))
+                .flatten()
+        }),
+        _ => None,
+    };
+    sql_search(
+        &db_manager,
+        &session_id,
+        &entry_filter,
+        &search_type,
+        search_term.as_deref(),
+        conditions.as_deref(),
+        case_sensitive,
+        fts_expression.as_deref(),
+    )
+}
+
+/// Non-regex search of a session, narrowed by the full-text `fts_expression`
+/// if given and by `LIKE` otherwise
+#[allow(clippy::too_many_arguments)]
+fn sql_search(
+    db_manager: &DatabaseManager,
+    session_id: &str,
+    entry_filter: &EntryFilter,
+    search_type: &str,
+    search_term: Option<&str>,
+    conditions: Option<&[SearchCondition]>,
+    case_sensitive: bool,
+    fts_expression: Option<&str>,
+) -> Result<Vec<SearchResult>, String> {
+    let (scope, scope_params) = search_scope(session_id, entry_filter);
+    let mut query = format!(
         "SELECT id, timestamp, line_number, message
          FROM log_entries
-         WHERE test_session_id = ?"
+         WHERE {}",
+        scope
     );
-    let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(session_id)];
+    let mut params = scope_params;
+    if let Some(expression) = fts_expression {
+        query.push_str(" AND id IN (SELECT rowid FROM log_entries_fts WHERE log_entries_fts MATCH ?)");
+        params.push(Box::new(expression.to_string()));
+    }
 
-    match search_type.as_str() {
+    match search_type {
         "simple" => {
             if let Some(term) = search_term {
                 if case_sensitive {
                     // For case-sensitive search, we need to filter in Rust
-                    query.push_str(" ORDER BY timestamp ASC, id ASC");
+                    query.push_str(" ORDER BY timestamp_ms ASC, id ASC");
                     let all_results = db_manager.search_entries_custom(&query, &params)
                         .map_err(|e| e.to_string())?;
                     let filtered: Vec<SearchResult> = all_results.into_iter()
-                        .filter(|r| r.message.contains(&term))
+                        .filter(|r| r.message.contains(term))
                         .collect();
                     return Ok(filtered);
+                } else if fts_expression.is_some() {
+                    query.push_str(" ORDER BY timestamp_ms ASC, id ASC");
                 } else {
                     query.push_str(" AND message LIKE ?");
                     params.push(Box::new(format!("%{}%", term)));
@@ -178,7 +321,7 @@ async fn search_entries(
                 }
                 // For case-sensitive search, we need to filter in Rust
                 if case_sensitive {
-                    query.push_str(" ORDER BY timestamp ASC, id ASC");
+                    query.push_str(" ORDER BY timestamp_ms ASC, id ASC");
                     let all_results = db_manager.search_entries_custom(&query, &params)
                         .map_err(|e| e.to_string())?;
 
@@ -213,25 +356,28 @@ async fn search_entries(
                     return Ok(results.into_values().collect());
                 }
 
-                // Case-insensitive: use database LIKE
+                // Case-insensitive: use the full-text match or database LIKE
                 // Validate operators to prevent SQL injection
-                for cond in &conds {
+                for cond in conds {
                     if !matches!(cond.operator.as_str(), "AND" | "OR") {
                         return Err(format!("Invalid operator: '{}'. Must be AND or OR", cond.operator));
                     }
                 }
-                query.push_str(" AND (");
-                for (i, cond) in conds.iter().enumerate() {
-                    if i > 0 {
-                        // The operator selector in UI is shown for each condition (except first)
-                        // and represents how THIS condition connects to the PREVIOUS one
-                        // So for condition at index i, its operator connects i-1 and i
-                        query.push_str(&format!(" {} ", cond.operator));
+                if fts_expression.is_none() {
+                    query.push_str(" AND (");
+                    for (i, cond) in conds.iter().enumerate() {
+                        if i > 0 {
+                            // The operator selector in UI is shown for each condition (except first)
+                            // and represents how THIS condition connects to the PREVIOUS one
+                            // So for condition at index i, its operator connects i-1 and i
+                            query.push_str(&format!(" {} ", cond.operator));
+                        }
+                        query.push_str("message LIKE ?");
+                        params.push(Box::new(format!("%{}%", cond.term)));
                     }
-                    query.push_str("message LIKE ?");
-                    params.push(Box::new(format!("%{}%", cond.term)));
+                    query.push(')');
                 }
-                query.push_str(") ORDER BY timestamp ASC, id ASC");
+                query.push_str(" ORDER BY timestamp_ms ASC, id ASC");
                 log::info!("Advanced search query: {}", query);
                 log::info!("Conditions: {:?}", conds.iter().map(|c| (&c.term, &c.operator)).collect::<Vec<_>>());
             } else {
@@ -254,8 +400,8 @@ fn find_entry_page(
     items_per_page: usize,
     state: State<'_, AppState>,
 ) -> Result<usize, String> {
-    let db_manager = state.db_manager.lock()
-        .map_err(|e| e.to_string())?;
+    let db = state.db();
+    let db_manager = db.read();
 
     db_manager.find_entry_page_simple(&session_id, entry_id, items_per_page)
         .map_err(|e| format!("Failed to find entry page: {}", e))
@@ -266,37 +412,40 @@ fn find_entry_page(
 fn scan_log_directory(
     state: State<'_, AppState>,
     directory_path: String,
-) -> Result<Vec<ScanResult>, String> {
-    log::info!("Scanning directory: {}", directory_path);
+    grammar: Option<String>,
+) -> Result<DirectoryScan, String> {
+    log::info!("Scanning directory: {} (grammar: {:?})", directory_path, grammar);
 
     // Get existing sessions to check which tests are already loaded
     let existing_sessions = {
-        let db_manager = state.db_manager.lock().unwrap();
+        let db = state.db();
+        let db_manager = db.read();
         db_manager.get_sessions()
             .map_err(|e| format!("Failed to get existing sessions: {}", e))?
     };
 
-    // Create a map of existing session names to their info
-    let mut existing_map: std::collections::HashMap<String, (String, usize)> = std::collections::HashMap::new();
+    // Create a map of existing sessions keyed by name and directory
+    let mut existing_map: std::collections::HashMap<(String, String), (String, usize)> = std::collections::HashMap::new();
     for session in &existing_sessions {
-        if session.directory_path == directory_path {
-            existing_map.insert(session.name.clone(), (session.id.clone(), session.total_entries));
-        }
+        existing_map.insert(
+            (session.name.clone(), session.directory_path.clone()),
+            (session.id.clone(), session.total_entries),
+        );
     }
 
     // Scan directory for test groups
-    let test_groups = HtmlLogParser::scan_html_files(&directory_path)
+    let scanned = settings::load_settings()
+        .format_registry_for(grammar.as_deref())?
+        .scan_directory(&directory_path)
         .map_err(|e| format!("Failed to scan directory: {}", e))?;
 
-    if test_groups.is_empty() {
-        return Err("No test log files found in the directory".to_string());
-    }
-
-    // Create scan results
+    // Create scan results; an empty list is returned with the ignored files
+    // so the reasons can be shown
     let mut results = Vec::new();
-    for (test_name, html_files) in test_groups {
+    for (test_name, html_files) in scanned.groups {
+        let session_dir = session_directory(&directory_path, &html_files);
         let (existing_session_id, estimated_entries) = existing_map
-            .get(&test_name)
+            .get(&(test_name.clone(), session_dir))
             .map(|(id, entries)| (Some(id.clone()), Some(*entries)))
             .unwrap_or((None, None));
 
@@ -310,22 +459,64 @@ fn scan_log_directory(
     }
 
     println!("Scanned {} test sessions", results.len());
-    Ok(results)
+    Ok(DirectoryScan {
+        sessions: results,
+        ignored_files: scanned.ignored,
+    })
+}
+
+/// Error returned when an import is stopped through `cancel_import`
+const IMPORT_CANCELLED: &str = "Import cancelled";
+
+/// Directory recorded on a session: the scanned directory, or the archive
+/// path plus inner directory when the files come from an archive
+fn session_directory(directory_path: &str, files: &[String]) -> String {
+    files
+        .first()
+        .and_then(|f| archive::source_directory(f))
+        .unwrap_or_else(|| directory_path.to_string())
+}
+
+/// What a reload does with one file of a test group
+enum FileUpdate {
+    /// Same content as in the previous import; its entries are kept
+    Unchanged(FileReport),
+    /// Entries of a new or changed file, parsed again
+    Entries(Vec<LogEntry>),
+    /// Report of a new or changed file, after all its entries
+    Parsed(StreamedFile),
+}
+
+/// Whether `file_path` can keep the entries of its `previous` import, with
+/// its report updated to the current fingerprint
+fn unchanged_report(archives: &ArchiveCache, file_path: &str, previous: Option<&FileReport>) -> Option<FileReport> {
+    // Files that failed last time are always retried
+    let previous = previous.filter(|p| p.status != FileParseStatus::Failed)?;
+    let stored = previous.fingerprint.as_ref()?;
+    let current = FileFingerprint::take_with(file_path, Some(stored), || archives.open_source(file_path)).ok()?;
+    current.same_content(stored).then(|| FileReport {
+        fingerprint: Some(current),
+        ..previous.clone()
+    })
 }
 
 // Helper function to do the actual parsing work (can run in blocking thread pool)
 fn parse_directory_blocking(
-    db_path: String,
+    db: &DbPool,
     directory_path: String,
     selected_tests: Option<Vec<String>>,
+    grammar: Option<String>,
+    progress: &(dyn Fn(ProgressStatus) + Sync),
+    cancelled: &AtomicBool,
 ) -> Result<Vec<(String, String, usize, usize)>, String> {
     println!("[BLOCKING] Starting to parse: {}", directory_path);
 
-    let mut db_manager = DatabaseManager::new(&db_path)
-        .map_err(|e| format!("Failed to initialize database: {}", e))?;
-
-    let test_groups = HtmlLogParser::scan_html_files(&directory_path)
-        .map_err(|e| format!("Failed to scan directory: {}", e))?;
+    let app_settings = settings::load_settings();
+    let formats = app_settings.format_registry_for(grammar.as_deref())?;
+    let test_groups = formats
+        .scan_directory(&directory_path)
+        .map_err(|e| format!("Failed to scan directory: {}", e))?
+        .groups;
 
     if test_groups.is_empty() {
         return Err("No test log files found in the directory".to_string());
@@ -333,6 +524,9 @@ fn parse_directory_blocking(
 
     println!("[BLOCKING] Found {} test groups", test_groups.len());
 
+    let timestamps = app_settings.timestamp_parser();
+    let extractors = app_settings.attribute_extractors();
+
     let mut session_results = Vec::new();
 
     // Filter tests if selected_tests is provided
@@ -356,123 +550,225 @@ fn parse_directory_blocking(
         return Err("No matching test sessions found for the selected tests".to_string());
     }
 
-    for (test_name, html_files) in test_groups_to_parse {
-        println!(
-            "[BLOCKING] Processing test group: {} ({} files)",
-            test_name,
-            html_files.len()
-        );
-
-        // Delete existing session with the same name and directory path if it exists
-        println!("[BLOCKING] Checking for existing session: name={}, dir={}", test_name, directory_path);
-
-        // Use a separate database connection for deletion to avoid borrow issues
-        let db_path_for_delete = "logterminator.db";
-        match DatabaseManager::new(db_path_for_delete) {
-            Ok(delete_db_manager) => {
-                match delete_db_manager.delete_session_by_name_and_path(&test_name, &directory_path) {
-                    Ok(Some(deleted_session_id)) => {
-                        println!("[BLOCKING] Deleted existing session: {}", deleted_session_id);
+    // Sessions are written in name order so repeated imports behave the same
+    let mut test_groups_to_parse = test_groups_to_parse;
+    test_groups_to_parse.sort_by(|a, b| a.0.cmp(&b.0));
+
+    // Files of each group's previous import, keyed by path; a reload keeps
+    // the session ID and only re-parses files that were added or changed
+    let mut previous_files: Vec<HashMap<String, FileReport>> = Vec::new();
+    let mut session_ids: Vec<String> = Vec::new();
+    for (test_name, files) in &test_groups_to_parse {
+        let session_dir = session_directory(&directory_path, files);
+        let existing_id = db
+            .read()
+            .find_session_id(test_name, &session_dir)
+            .map_err(|e| format!("Failed to look up session {}: {}", test_name, e))?;
+
+        // Session IDs are needed by the parser, so generate them up front
+        let session_id = existing_id.clone().unwrap_or_else(|| {
+            format!(
+                "session_{}_{}",
+                test_name.replace(|c: char| !c.is_alphanumeric() && c != '_', "_"),
+                chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+            )
+        });
+        let previous = match existing_id {
+            Some(id) => db
+                .read()
+                .get_import_report(&id)
+                .map_err(|e| format!("Failed to read import report of {}: {}", test_name, e))?
+                .files
+                .into_iter()
+                .map(|file| (file.file_path.clone(), file))
+                .collect(),
+            None => HashMap::new(),
+        };
+        session_ids.push(session_id);
+        previous_files.push(previous);
+    }
+    let group_files: Vec<Vec<String>> = test_groups_to_parse.iter().map(|(_, files)| files.clone()).collect();
+    // Tar archives are read once for all their members, not once per member
+    let archives = ArchiveCache::new(group_files.iter().flatten().map(String::as_str));
+
+    let workers = worker_pool::default_worker_count();
+    let total_files = group_files.iter().map(Vec::len).sum::<usize>();
+    println!("[BLOCKING] Parsing {} files on {} workers", total_files, workers);
+
+    let parsed_files = AtomicUsize::new(0);
+    let parsed_entries = AtomicUsize::new(0);
+
+    // Files are parsed in parallel; their entries are written here, on this
+    // thread, batch by batch as they arrive
+    worker_pool::for_each_group_parallel(
+        &group_files,
+        workers,
+        |group, index, file_path, send| {
+            if cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+            let previous = previous_files[group].get(file_path);
+            let (update, entries) = match unchanged_report(&archives, file_path, previous) {
+                Some(report) => (FileUpdate::Unchanged(report), 0),
+                None => {
+                    // Taken before parsing, so a file that grows meanwhile is
+                    // picked up again by the next reload
+                    let fingerprint =
+                        FileFingerprint::take_with(file_path, None, || archives.open_source(file_path)).ok();
+                    let mut streamed = formats.stream_file_with_report(
+                        &archives,
+                        file_path,
+                        &session_ids[group],
+                        index,
+                        &timestamps,
+                        &mut |batch| {
+                            if !cancelled.load(Ordering::Relaxed) && send(FileUpdate::Entries(batch)) {
+                                Ok(())
+                            } else {
+                                Err(IMPORT_CANCELLED.into())
+                            }
+                        },
+                    );
+                    streamed.report.fingerprint = fingerprint;
+                    let entries = streamed.report.rows;
+                    (FileUpdate::Parsed(streamed), entries)
+                }
+            };
+
+            progress(ProgressStatus::ParsingFiles {
+                current_test: test_groups_to_parse[group].0.clone(),
+                total_files,
+                parsed_files: parsed_files.fetch_add(1, Ordering::Relaxed) + 1,
+                entries: parsed_entries.fetch_add(entries, Ordering::Relaxed) + entries,
+            });
+            send(update);
+        },
+        |group, parsed_files| {
+            if cancelled.load(Ordering::Relaxed) {
+                return Err(IMPORT_CANCELLED.to_string());
+            }
+            let (test_name, html_files) = &test_groups_to_parse[group];
+            let session_id = session_ids[group].clone();
+            println!(
+                "[BLOCKING] Processing test group: {} ({} files)",
+                test_name,
+                html_files.len()
+            );
+
+            let session_dir = session_directory(&directory_path, html_files);
+
+            println!("[BLOCKING] Using session_id: {}", session_id);
+
+            let mut session = TestSession {
+                id: session_id.clone(),
+                name: test_name.clone(),
+                directory_path: session_dir.clone(),
+                file_count: html_files.len(),
+                total_entries: 0,
+                created_at: Some(chrono::Utc::now()),
+                last_parsed_at: Some(chrono::Utc::now()),
+                source_type: Some("local".to_string()),
+                incomplete: false,
+                attributes: Default::default(),
+            };
+
+            // The session is written in one transaction as entries arrive, so
+            // a cancelled or failed import leaves the database as it was. A
+            // reload keeps the stored session and replaces only the files
+            // that were parsed again.
+            let reload = !previous_files[group].is_empty();
+            let write_error = |e: rusqlite::Error| format!("Failed to write session: {}", e);
+            let mut db_manager = db.write();
+            let mut import = db_manager.begin_session_import(&session, reload).map_err(write_error)?;
+
+            let mut file_reports = Vec::new();
+            let mut kept_files = 0;
+            let mut kept_entries = 0;
+            let mut inserted_entries = 0;
+            // Header entries and the title come from the first file; if it was
+            // kept by a reload, what they yielded is already stored
+            let mut first_file = None;
+            let mut header_entries = Vec::new();
+
+            let mut index = 0;
+            while let Some(updates) = parsed_files.next_file() {
+                let file_path = &html_files[index];
+                let mut dropped = false;
+                for update in updates {
+                    if cancelled.load(Ordering::Relaxed) {
+                        return Err(IMPORT_CANCELLED.to_string());
                     }
-                    Ok(None) => {
-                        println!("[BLOCKING] No existing session found for {}", test_name);
+                    if reload && !dropped && !matches!(update, FileUpdate::Unchanged(_)) {
+                        import.drop_file(file_path).map_err(write_error)?;
+                        dropped = true;
                     }
-                    Err(e) => {
-                        println!("[BLOCKING] Warning: Failed to delete existing session: {}", e);
-                        // Continue anyway - try to create the new session
+                    match update {
+                        FileUpdate::Unchanged(report) => {
+                            kept_files += 1;
+                            kept_entries += report.rows;
+                            file_reports.push(report);
+                        }
+                        FileUpdate::Entries(batch) => {
+                            let batch = import.insert_parsed(batch).map_err(write_error)?;
+                            inserted_entries += batch.len();
+                            let wanted = ATTRIBUTE_ENTRY_LIMIT.saturating_sub(header_entries.len());
+                            header_entries.extend(batch.into_iter().take(wanted));
+                        }
+                        FileUpdate::Parsed(streamed) => {
+                            if let Some(e) = &streamed.report.error {
+                                println!("Warning: Failed to parse {}: {}", streamed.report.file_path, e);
+                            }
+                            if index == 0 {
+                                first_file = Some(streamed.title);
+                            }
+                            file_reports.push(streamed.report);
+                        }
                     }
                 }
+                index += 1;
             }
-            Err(e) => {
-                println!("[BLOCKING] Warning: Could not create database connection for deletion: {}", e);
-                // Continue anyway
+            if cancelled.load(Ordering::Relaxed) {
+                return Err(IMPORT_CANCELLED.to_string());
             }
-        }
-
-        let session_id = format!(
-            "session_{}_{}",
-            test_name.replace(|c: char| !c.is_alphanumeric() && c != '_', "_"),
-            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
-        );
-        println!("[BLOCKING] Generated new session_id: {}", session_id);
-
-        let mut all_entries = Vec::new();
-        let mut total_entries = 0;
-
-        for (index, file_path) in html_files.iter().enumerate() {
-            match HtmlLogParser::parse_file(file_path, &session_id, index) {
-                Ok(entries) => {
-                    total_entries += entries.len();
-                    all_entries.extend(entries);
-                }
-                Err(e) => {
-                    println!("Warning: Failed to parse {}: {}", file_path, e);
-                }
-            }
-        }
 
-        if all_entries.is_empty() {
-            println!("Warning: No valid log entries found for test {}", test_name);
-            continue;
-        }
-
-        println!("[BLOCKING] Creating session: id={}, name={}", session_id, test_name);
-        let session = TestSession {
-            id: session_id.clone(),
-            name: test_name.clone(),
-            directory_path: directory_path.clone(),
-            file_count: html_files.len(),
-            total_entries,
-            created_at: Some(chrono::Utc::now()),
-            last_parsed_at: Some(chrono::Utc::now()),
-            source_type: Some("local".to_string()),
-        };
-
-        db_manager
-            .create_test_session(&session)
-            .map_err(|e| format!("Failed to create session: {}", e))?;
-        println!("[BLOCKING] Session created successfully in database");
-
-        let inserted_ids = db_manager
-            .insert_entries(&all_entries)
-            .map_err(|e| format!("Failed to insert entries: {}", e))?;
-        println!("[BLOCKING] Inserted {} entries into database", inserted_ids.len());
-
-        // Assign IDs to entries for auto-bookmark detection
-        let mut entries_with_ids = all_entries;
-        for (i, entry_id) in inserted_ids.iter().enumerate() {
-            if i < entries_with_ids.len() {
-                entries_with_ids[i].id = Some(*entry_id);
+            // Files of the previous import that are gone now
+            for gone in previous_files[group].keys().filter(|path| !html_files.contains(path)) {
+                import.drop_file(gone).map_err(write_error)?;
             }
-        }
+            let total_entries = kept_entries + inserted_entries;
 
-        // Find and create auto-bookmarks for ###MARKER### patterns
-        let auto_markers = find_auto_bookmark_markers(&entries_with_ids);
-        if !auto_markers.is_empty() {
-            println!("[BLOCKING] Found {} auto-bookmark markers", auto_markers.len());
-            for (entry_id, title) in &auto_markers {
-                let bookmark = create_auto_bookmark(*entry_id, title.clone());
-                match db_manager.add_bookmark(&bookmark) {
-                    Ok(_) => {
-                                        log::info!("[BLOCKING] Created auto-bookmark: '{}'", title);
-                    }
-                    Err(e) => {
-                        log::warn!("Failed to create auto-bookmark '{}': {}", title, e);
-                    }
-                }
+            // A group whose files all failed is still recorded so the failures show up
+            if total_entries == 0 && !is_incomplete(&file_reports) {
+                println!("Warning: No valid log entries found for test {}", test_name);
+                return Ok(());
             }
-        }
 
-        println!(
-            "[BLOCKING] Completed test {}: {} files, {} entries, {} auto-bookmarks",
-            test_name,
-            html_files.len(),
-            total_entries,
-            auto_markers.len()
-        );
-
-        session_results.push((session_id, test_name, html_files.len(), total_entries));
-    }
+            session.attributes = match &first_file {
+                Some(title) => extractors.extract(&session_dir, title.as_deref(), &header_entries),
+                None => extractors.extract(&session_dir, None, &[]),
+            };
+            session.total_entries = total_entries;
+            session.incomplete = is_incomplete(&file_reports);
+
+            println!(
+                "[BLOCKING] Writing session: id={}, name={}, {} unchanged files kept, {} entries inserted",
+                session_id, test_name, kept_files, inserted_entries
+            );
+            let auto_bookmarks = import.auto_bookmarks();
+            import.finish(&session, &file_reports).map_err(write_error)?;
+
+            println!(
+                "[BLOCKING] Completed test {}: {} files, {} entries, {} auto-bookmarks",
+                test_name,
+                html_files.len(),
+                total_entries,
+                auto_bookmarks
+            );
+
+            session_results.push((session_id, test_name.clone(), html_files.len(), total_entries));
+            Ok(())
+        },
+    )?;
 
     println!("[BLOCKING] Total session results: {}", session_results.len());
     for (id, name, files, entries) in &session_results {
@@ -489,28 +785,96 @@ fn parse_directory_blocking(
 // Parse log directory and create test sessions
 #[tauri::command]
 async fn parse_log_directory(
-    _state: State<'_, AppState>,
+    state: State<'_, AppState>,
+    window: tauri::Window,
     directory_path: String,
     selected_tests: Option<Vec<String>>,
+    grammar: Option<String>,
 ) -> Result<Vec<String>, String> {
     println!("Starting async parse for: {}", directory_path);
     println!("selected_tests: {:?}", selected_tests);
 
-    let db_path = "logterminator.db".to_string();
+    let db = state.db();
+    let cancelled = state.import_cancelled.clone();
+    cancelled.store(false, Ordering::Relaxed);
+
+    // Same progress payloads as HTTP imports, on their own event
+    let progress = move |status: ProgressStatus| {
+        let msg = serde_json::to_string(&status).unwrap_or_else(|_| "{}".to_string());
+        let _ = window.emit("import-progress", msg);
+    };
 
     println!("Spawning blocking task...");
 
     // Run blocking work in thread pool
-    let result =
-        tokio::task::spawn_blocking(move || parse_directory_blocking(db_path, directory_path, selected_tests))
-            .await
-            .map_err(|e| format!("Task failed: {:?}", e))?;
+    let result = tokio::task::spawn_blocking(move || {
+        let result =
+            parse_directory_blocking(&db, directory_path, selected_tests, grammar, &progress, &cancelled);
+        progress(match &result {
+            _ if cancelled.load(Ordering::Relaxed) => ProgressStatus::Cancelled,
+            Ok(_) => ProgressStatus::Complete,
+            Err(e) => ProgressStatus::Failed { error: e.clone() },
+        });
+        result
+    })
+    .await
+    .map_err(|e| format!("Task failed: {:?}", e))?;
 
     println!("Blocking task completed successfully");
 
     result.map(|sessions| sessions.into_iter().map(|(id, _, _, _)| id).collect())
 }
 
+// Stop the running directory import; the session being written is rolled back
+#[tauri::command]
+fn cancel_import(state: State<'_, AppState>) -> Result<(), String> {
+    println!("Cancelling directory import");
+    state.import_cancelled.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+// Follow a session's directory, storing rows as the test writes them. New
+// entries are sent as "follow-entries" events; "follow-stopped" is sent once
+// following ends, with an error message if it failed.
+#[tauri::command]
+fn start_follow(state: State<'_, AppState>, window: tauri::Window, session_id: String) -> Result<(), String> {
+    let mut follow = state.follow();
+    if let Some(previous) = follow.take() {
+        previous.stop.store(true, Ordering::Relaxed);
+    }
+
+    let follower = SessionFollower::new(state.db(), &session_id)?;
+    let stop = Arc::new(AtomicBool::new(false));
+    *follow = Some(FollowHandle {
+        session_id: session_id.clone(),
+        stop: stop.clone(),
+    });
+
+    std::thread::spawn(move || {
+        let on_update = |update: FollowUpdate| {
+            let msg = serde_json::to_string(&update).unwrap_or_else(|_| "{}".to_string());
+            let _ = window.emit("follow-entries", msg);
+        };
+        let result = follower.run(&stop, &on_update);
+        if let Err(e) = &result {
+            log::error!("Following session {} failed: {}", session_id, e);
+        }
+        let msg = serde_json::json!({ "session_id": session_id, "error": result.err() });
+        let _ = window.emit("follow-stopped", msg.to_string());
+    });
+    Ok(())
+}
+
+// Stop following; rows held back as possibly incomplete are stored on the way out
+#[tauri::command]
+fn stop_follow(state: State<'_, AppState>) -> Result<(), String> {
+    if let Some(handle) = state.follow().take() {
+        println!("Stopping follow of session {}", handle.session_id);
+        handle.stop.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
 // Scan HTTP URL for test sessions without loading them
 #[tauri::command]
 async fn scan_log_http_url(
@@ -521,7 +885,8 @@ async fn scan_log_http_url(
 
     // Get existing sessions to check which tests are already loaded
     let existing_sessions = {
-        let db_manager = state.db_manager.lock().unwrap();
+        let db = state.db();
+        let db_manager = db.read();
         db_manager.get_sessions()
             .map_err(|e| format!("Failed to get existing sessions: {}", e))?
     };
@@ -542,14 +907,14 @@ async fn scan_log_http_url(
 // Parse logs from HTTP server and create test sessions
 #[tauri::command]
 async fn parse_log_http_url(
-    _state: State<'_, AppState>,
+    state: State<'_, AppState>,
     window: tauri::Window,
     url: String,
     selected_tests: Option<Vec<String>>,
 ) -> Result<Vec<String>, String> {
     println!("Starting async HTTP parse for: {}", url);
 
-    let db_path = "logterminator.db".to_string();
+    let db = state.db();
 
     // Use spawn_blocking to avoid blocking async runtime
     let (tx, rx) = tokio::sync::oneshot::channel();
@@ -559,7 +924,7 @@ async fn parse_log_http_url(
 
     std::thread::spawn(move || {
         let result = crate::http_log_fetcher::fetch_logs_from_http(
-            db_path,
+            &db,
             url_clone,
             |msg| {
                 let _ = window_clone.emit("http-progress", msg);
@@ -577,18 +942,17 @@ async fn parse_log_http_url(
 // Parse logs from HTTP server using async parallel downloads
 #[tauri::command]
 async fn parse_log_http_url_async(
-    _state: State<'_, AppState>,
+    state: State<'_, AppState>,
     window: tauri::Window,
     url: String,
     selected_tests: Option<Vec<String>>,
 ) -> Result<Vec<String>, String> {
-    use crate::http_async::{SessionDownloadCoordinator, ProgressStatus};
-    use std::sync::Arc;
+    use crate::http_async::SessionDownloadCoordinator;
 
     println!("[ASYNC] Starting async parallel HTTP parse for: {}", url);
     println!("[ASYNC] selected_tests: {:?}", selected_tests);
 
-    let db_path = "logterminator.db".to_string();
+    let db = state.db();
 
     // Create progress callback that emits to frontend
     let window_clone = window.clone();
@@ -598,11 +962,11 @@ async fn parse_log_http_url_async(
     });
 
     // Create coordinator with configured limits
-    let coordinator = SessionDownloadCoordinator::new(2, 4, 2);
+    let coordinator = SessionDownloadCoordinator::new(2, 2);
 
     // Run the download
     coordinator.download_sessions(
-        db_path,
+        db,
         url,
         selected_tests,
         progress_callback,
@@ -610,25 +974,33 @@ async fn parse_log_http_url_async(
     .map_err(|e| e.to_string())
 }
 
-// Get paginated log entries
+// Get a page of log entries next to a cursor, at a position or around an entry
 #[tauri::command]
-fn get_log_entries(
+#[allow(clippy::too_many_arguments)]
+fn get_entries_page(
     state: State<'_, AppState>,
     session_id: String,
-    offset: usize,
+    anchor: PageAnchor,
     limit: usize,
     level_filter: Option<Vec<String>>, // Changed to Vec for multi-select
+    min_level: Option<String>,
     search_term: Option<String>,
-) -> Result<(Vec<LogEntry>, usize), String> {
-    let db_manager = state.db_manager.lock().unwrap();
-    db_manager
-        .get_entries_paginated(
-            &session_id,
-            offset,
-            limit,
-            level_filter.as_deref(),
-            search_term.as_deref(),
-        )
+    thread_filter: Option<String>,
+    module_filter: Option<String>,
+    json_filter: Option<String>,
+) -> Result<Option<EntryPage>, String> {
+    let filter = EntryFilter {
+        levels: level_filter,
+        min_level,
+        search_term,
+        thread: thread_filter,
+        module: module_filter,
+        json: JsonPredicate::parse_all(json_filter.as_deref().unwrap_or_default())?,
+    };
+
+    state
+        .db()
+        .entries_page(&session_id, anchor, limit, &filter)
         .map_err(|e| format!("Database query error: {}", e))
 }
 
@@ -650,7 +1022,8 @@ fn add_bookmark(
         created_at: Some(chrono::Utc::now()),
     };
 
-    let db_manager = state.db_manager.lock().unwrap();
+    let db = state.db();
+    let db_manager = db.write();
     db_manager
         .add_bookmark(&bookmark)
         .map_err(|e| format!("Failed to add bookmark: {}", e))
@@ -662,52 +1035,184 @@ fn get_bookmarks(
     state: State<'_, AppState>,
     session_id: String,
 ) -> Result<Vec<(Bookmark, LogEntry)>, String> {
-    let db_manager = state.db_manager.lock().unwrap();
+    let db = state.db();
+    let db_manager = db.read();
     db_manager
         .get_bookmarks(&session_id)
         .map_err(|e| format!("Failed to get bookmarks: {}", e))
 }
 
-// Get all test sessions
+// Get all test sessions, or those with the given attribute values
 #[tauri::command]
-fn get_sessions(state: State<'_, AppState>) -> Result<Vec<TestSession>, String> {
-    let db_manager = state.db_manager.lock().unwrap();
+fn get_sessions(
+    state: State<'_, AppState>,
+    attributes: Option<HashMap<String, String>>,
+) -> Result<Vec<TestSession>, String> {
+    let db = state.db();
+    let db_manager = db.read();
     db_manager
-        .get_sessions()
+        .get_sessions_with_attributes(&attributes.unwrap_or_default())
         .map_err(|e| format!("Failed to get sessions: {}", e))
 }
 
 // Get all unique log levels for a session
 #[tauri::command]
 fn get_session_log_levels(state: State<'_, AppState>, session_id: String) -> Result<Vec<String>, String> {
-    let db_manager = state.db_manager.lock().unwrap();
+    let db = state.db();
+    let db_manager = db.read();
     db_manager
         .get_session_log_levels(&session_id)
         .map_err(|e| format!("Failed to get session log levels: {}", e))
 }
 
+// Get all thread names seen in a session
+#[tauri::command]
+fn get_session_threads(state: State<'_, AppState>, session_id: String) -> Result<Vec<String>, String> {
+    let db = state.db();
+    let db_manager = db.read();
+    db_manager
+        .get_session_threads(&session_id)
+        .map_err(|e| format!("Failed to get session threads: {}", e))
+}
+
+// Get the traceback frames parsed from a log entry
+#[tauri::command]
+fn get_stack_frames(state: State<'_, AppState>, entry_id: i64) -> Result<Vec<StackFrame>, String> {
+    let db = state.db();
+    let db_manager = db.read();
+    db_manager
+        .get_stack_frames(entry_id)
+        .map_err(|e| format!("Failed to get stack frames: {}", e))
+}
+
+// Get all exception types raised in a session's tracebacks
+#[tauri::command]
+fn get_exception_types(state: State<'_, AppState>, session_id: String) -> Result<Vec<String>, String> {
+    let db = state.db();
+    let db_manager = db.read();
+    db_manager
+        .get_exception_types(&session_id)
+        .map_err(|e| format!("Failed to get exception types: {}", e))
+}
+
+// Get the per-file import report of a session
+#[tauri::command]
+fn get_import_report(state: State<'_, AppState>, session_id: String) -> Result<ImportReport, String> {
+    let db = state.db();
+    let db_manager = db.read();
+    db_manager
+        .get_import_report(&session_id)
+        .map_err(|e| format!("Failed to get import report: {}", e))
+}
+
 // Ensure auto-bookmarks are created for a session (called when switching sessions)
 #[tauri::command]
 fn ensure_auto_bookmarks(state: State<'_, AppState>, session_id: String) -> Result<Vec<Bookmark>, String> {
-    let db_manager = state.db_manager.lock().unwrap();
+    let db = state.db();
+    let db_manager = db.write();
     db_manager
         .ensure_auto_bookmarks_for_session(&session_id)
         .map_err(|e| format!("Failed to ensure auto-bookmarks: {}", e))
 }
 
-// Delete test session
+// Delete test session, unless it is being followed
 #[tauri::command]
 fn delete_session(state: State<'_, AppState>, session_id: String) -> Result<(), String> {
-    let db_manager = state.db_manager.lock().unwrap();
+    if state.follow().as_ref().is_some_and(|handle| handle.session_id == session_id) {
+        return Err("The session is being followed; stop following it before deleting it".to_string());
+    }
+    let db = state.db();
+    let db_manager = db.write();
     db_manager
         .delete_session(&session_id)
         .map_err(|e| format!("Failed to delete session: {}", e))
 }
 
+// Disk usage of the open workspace's database, per session and in total
+#[tauri::command]
+fn get_storage_usage(state: State<'_, AppState>) -> Result<StorageUsage, String> {
+    let db = state.db();
+    let db_manager = db.read();
+    db_manager
+        .get_storage_usage()
+        .map_err(|e| format!("Failed to get storage usage: {}", e))
+}
+
+// Delete the sessions the retention rules in the settings expire, or with
+// `dry_run` only list them. The followed session is never deleted.
+#[tauri::command]
+fn apply_retention(state: State<'_, AppState>, dry_run: bool) -> Result<Vec<TestSession>, String> {
+    let policy = settings::load_settings().retention;
+    let followed = state.follow().as_ref().map(|handle| handle.session_id.clone());
+
+    let db = state.db();
+    let (sessions, expired) = {
+        let db_manager = db.read();
+        let candidates = db_manager
+            .retention_candidates()
+            .map_err(|e| format!("Failed to read sessions: {}", e))?;
+        let expired: Vec<String> = expired_sessions(&policy, &candidates)
+            .into_iter()
+            .filter(|id| followed.as_ref() != Some(id))
+            .collect();
+        let sessions = db_manager.get_sessions().map_err(|e| format!("Failed to read sessions: {}", e))?;
+        (sessions, expired)
+    };
+
+    if !dry_run && !expired.is_empty() {
+        db.write()
+            .delete_sessions(&expired)
+            .map_err(|e| format!("Failed to delete sessions: {}", e))?;
+    }
+    Ok(sessions.into_iter().filter(|session| expired.contains(&session.id)).collect())
+}
+
+// Delete every session imported from a directory or URL, including its
+// subdirectories and archives; returns the ids of the deleted sessions. The
+// followed session is never deleted.
+#[tauri::command]
+fn delete_sessions_by_source(state: State<'_, AppState>, source: String) -> Result<Vec<String>, String> {
+    let followed = state.follow().as_ref().map(|handle| handle.session_id.clone());
+
+    let db = state.db();
+    let mut db_manager = db.write();
+    let session_ids: Vec<String> = db_manager
+        .get_sessions()
+        .map_err(|e| format!("Failed to read sessions: {}", e))?
+        .into_iter()
+        .filter(|session| is_from_source(&session.directory_path, &source))
+        .map(|session| session.id)
+        .filter(|id| followed.as_ref() != Some(id))
+        .collect();
+
+    db_manager
+        .delete_sessions(&session_ids)
+        .map_err(|e| format!("Failed to delete sessions: {}", e))?;
+    Ok(session_ids)
+}
+
+// Analyze and compact the database. Holds the writer until done, so imports
+// wait; progress is sent as "maintenance-progress" events.
+#[tauri::command]
+async fn run_maintenance(state: State<'_, AppState>, window: tauri::Window) -> Result<MaintenanceReport, String> {
+    let db = state.db();
+    tokio::task::spawn_blocking(move || {
+        let progress = |progress: storage::MaintenanceProgress| {
+            let msg = serde_json::to_string(&progress).unwrap_or_else(|_| "{}".to_string());
+            let _ = window.emit("maintenance-progress", msg);
+        };
+        db.write().run_maintenance(&progress)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {:?}", e))?
+    .map_err(|e| format!("Database maintenance failed: {}", e))
+}
+
 // Delete bookmark
 #[tauri::command]
 fn delete_bookmark(state: State<'_, AppState>, bookmark_id: i64) -> Result<(), String> {
-    let db_manager = state.db_manager.lock().unwrap();
+    let db = state.db();
+    let db_manager = db.write();
     db_manager
         .delete_bookmark(bookmark_id)
         .map_err(|e| format!("Failed to delete bookmark: {}", e))
@@ -720,30 +1225,64 @@ async fn update_bookmark_title(
     bookmark_id: i64,
     title: &str,
 ) -> Result<(), String> {
-    let db_manager = state.db_manager.lock().unwrap();
+    let db = state.db();
+    let db_manager = db.write();
     db_manager
         .update_bookmark_title(bookmark_id, title)
         .map_err(|e| format!("Failed to update bookmark title: {}", e))
 }
 
-// Get the page number for a specific log entry (for bookmark jumping)
+// Report entries of a session whose timestamps matched no configured format
 #[tauri::command]
-fn get_entry_page(
-    state: State<'_, AppState>,
-    entry_id: i64,
-    items_per_page: usize,
-    level_filter: Option<Vec<String>>, // Changed to Vec for multi-select
-    search_term: Option<String>,
-) -> Result<Option<usize>, String> {
-    let db_manager = state.db_manager.lock().unwrap();
+fn get_unparsed_timestamps(state: State<'_, AppState>, session_id: String) -> Result<UnparsedTimestamps, String> {
+    let db = state.db();
+    let db_manager = db.read();
     db_manager
-        .get_entry_page(
-            entry_id,
-            items_per_page,
-            level_filter.as_deref(),
-            search_term.as_deref(),
-        )
-        .map_err(|e| format!("Failed to get entry page: {}", e))
+        .get_unparsed_timestamps(&session_id, 20)
+        .map_err(|e| format!("Failed to get unparsed timestamps: {}", e))
+}
+
+/// Get the application settings
+#[tauri::command]
+fn get_settings() -> settings::AppSettings {
+    settings::load_settings()
+}
+
+/// Validate and save the application settings. A new workspace directory
+/// takes effect at once, opening the workspace last active there.
+#[tauri::command]
+fn save_settings(state: State<'_, AppState>, settings: settings::AppSettings) -> Result<(), String> {
+    settings::save_settings(&settings)?;
+
+    let directory = WorkspaceDirectory::configured(settings.workspace_directory.as_deref(), &state.data_dir);
+    let moved = state.workspace.read().unwrap_or_else(|e| e.into_inner()).directory != directory;
+    if moved {
+        state.switch_workspace(directory.open_active()?);
+    }
+    Ok(())
+}
+
+/// Workspaces in the workspace directory and the open one
+#[tauri::command]
+fn get_workspaces(state: State<'_, AppState>) -> WorkspaceList {
+    let workspace = state.workspace.read().unwrap_or_else(|e| e.into_inner());
+    workspace.directory.list(&workspace.name)
+}
+
+/// Create a workspace with an empty database and switch to it
+#[tauri::command]
+fn create_workspace(state: State<'_, AppState>, name: String) -> Result<WorkspaceList, String> {
+    let directory = state.workspace.read().unwrap_or_else(|e| e.into_inner()).directory.clone();
+    let workspace = directory.create(&name)?;
+    Ok(state.switch_workspace(workspace))
+}
+
+/// Switch to an existing workspace
+#[tauri::command]
+fn switch_workspace(state: State<'_, AppState>, name: String) -> Result<WorkspaceList, String> {
+    let directory = state.workspace.read().unwrap_or_else(|e| e.into_inner()).directory.clone();
+    let workspace = directory.open(&name)?;
+    Ok(state.switch_workspace(workspace))
 }
 
 /// Save the last used log directory
@@ -868,36 +1407,66 @@ pub fn run() {
         eprintln!("Failed to initialize logging: {}", e);
     }
 
-    // Initialize database
     log::info!("Starting logTerminator application");
-    let db_path = "logterminator.db";
-    let db_manager = DatabaseManager::new(db_path).expect("Failed to initialize database");
-
-    let app_state = AppState {
-        db_manager: Mutex::new(db_manager),
-    };
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
-        .manage(app_state)
+        .setup(|app| {
+            // Open the workspace last active in the per-user data directory
+            // or the one chosen in the settings
+            let data_dir = app.path().app_data_dir()?;
+            let directory = WorkspaceDirectory::configured(
+                settings::load_settings().workspace_directory.as_deref(),
+                &data_dir,
+            );
+            // Older versions kept the database in the working directory
+            if let Err(e) = directory.adopt_legacy_database(std::path::Path::new(LEGACY_DATABASE)) {
+                log::warn!("Failed to copy {} into the default workspace: {}", LEGACY_DATABASE, e);
+            }
+            let workspace = directory.open_active()?;
+
+            app.manage(AppState {
+                data_dir,
+                workspace: RwLock::new(workspace),
+                import_cancelled: Arc::new(AtomicBool::new(false)),
+                follow: Mutex::new(None),
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             scan_log_directory,
             scan_log_http_url,
             parse_log_directory,
+            cancel_import,
+            start_follow,
+            stop_follow,
             parse_log_http_url,
             parse_log_http_url_async,
-            get_log_entries,
+            get_entries_page,
             add_bookmark,
             get_bookmarks,
             delete_bookmark,
             update_bookmark_title,
-            get_entry_page,
+            get_unparsed_timestamps,
+            get_settings,
+            save_settings,
+            get_workspaces,
+            create_workspace,
+            switch_workspace,
             get_sessions,
             get_session_log_levels,
+            get_session_threads,
+            get_stack_frames,
+            get_exception_types,
+            get_import_report,
             ensure_auto_bookmarks,
             delete_session,
+            get_storage_usage,
+            apply_retention,
+            delete_sessions_by_source,
+            run_maintenance,
             save_last_directory,
             get_last_directory,
             get_log_history,
@@ -0,0 +1,62 @@
+use super::{EntrySink, LogFormat, ParseContext};
+use crate::html_profile::HtmlTableProfile;
+use crate::log_parser::HtmlLogParser;
+use std::io::Read;
+
+/// HTML table logs, mapped to entries by the profile matching each file's header row
+pub struct HtmlLogFormat {
+    profiles: Vec<HtmlTableProfile>,
+}
+
+impl Default for HtmlLogFormat {
+    fn default() -> Self {
+        Self::with_profiles(&[])
+    }
+}
+
+impl HtmlLogFormat {
+    /// Built-in profiles followed by `custom` ones, which take precedence on detection
+    pub fn with_profiles(custom: &[HtmlTableProfile]) -> Self {
+        let mut profiles = HtmlTableProfile::builtin();
+        profiles.extend(custom.iter().cloned());
+        Self { profiles }
+    }
+}
+
+impl LogFormat for HtmlLogFormat {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn detect(&self, file_name: &str, _head: &[u8]) -> bool {
+        let lower = file_name.to_ascii_lowercase();
+        lower.ends_with(".html") || lower.ends_with(".htm")
+    }
+
+    fn session_name(&self, file_name: &str) -> Option<String> {
+        HtmlLogParser::is_test_log_file(file_name)
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn Read,
+        ctx: &ParseContext,
+        sink: &mut EntrySink,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let stream = HtmlLogParser::stream_entries(
+            reader,
+            ctx.file_path,
+            ctx.test_session_id,
+            ctx.file_index,
+            ctx.timestamps,
+            &self.profiles,
+        );
+        let mut stream = if ctx.growing { stream.still_growing() } else { stream };
+        let result = HtmlLogParser::drain_stream(&mut stream, ctx.file_path, sink);
+        ctx.skip_rows(stream.skipped_rows());
+        if let Some(title) = stream.title() {
+            ctx.set_title(title);
+        }
+        result.map(|_| ())
+    }
+}
@@ -0,0 +1,185 @@
+use super::{EntrySink, LogFormat, ParseContext};
+use crate::log_parser::LogEntry;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use std::io::{BufRead, BufReader, Read};
+
+const TIMESTAMP_KEYS: &[&str] = &["timestamp", "@timestamp", "time", "ts", "asctime"];
+const LEVEL_KEYS: &[&str] = &["level", "levelname", "severity", "lvl"];
+const MESSAGE_KEYS: &[&str] = &["message", "msg", "event"];
+const THREAD_KEYS: &[&str] = &["thread", "threadName", "thread_name"];
+const MODULE_KEYS: &[&str] = &["logger", "logger_name", "name", "module"];
+const LINE_KEYS: &[&str] = &["line", "lineno", "line_number"];
+const STACK_KEYS: &[&str] = &["stack", "stack_trace", "exc_info", "exception"];
+
+/// Numeric timestamps above this are taken as milliseconds, below as seconds
+const MILLIS_THRESHOLD: f64 = 1e11;
+
+/// One JSON object per line, as written by structured loggers
+pub struct JsonLinesFormat;
+
+impl LogFormat for JsonLinesFormat {
+    fn name(&self) -> &str {
+        "jsonl"
+    }
+
+    fn detect(&self, file_name: &str, head: &[u8]) -> bool {
+        let lower = file_name.to_ascii_lowercase();
+        if lower.ends_with(".jsonl") || lower.ends_with(".ndjson") {
+            return true;
+        }
+        if !(lower.ends_with(".log") || lower.ends_with(".json")) {
+            return false;
+        }
+
+        // Generic extensions need a look at the content
+        let head = String::from_utf8_lossy(head);
+        let mut lines = head.lines().filter(|l| !l.trim().is_empty());
+        match lines.next() {
+            Some(first) if lines.next().is_some() || head.ends_with('\n') => {
+                matches!(serde_json::from_str::<Value>(first), Ok(Value::Object(_)))
+            }
+            Some(first) => first.trim_start().starts_with('{'),
+            None => false,
+        }
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn Read,
+        ctx: &ParseContext,
+        sink: &mut EntrySink,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut reader = BufReader::new(reader);
+        let mut count = 0;
+        let mut skipped = 0;
+        let mut line = String::new();
+        let mut line_number = 0;
+
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            if ctx.growing && !line.ends_with('\n') {
+                break;
+            }
+
+            let text = line.trim();
+            if !text.is_empty() {
+                match serde_json::from_str::<Value>(text) {
+                    Ok(Value::Object(fields)) => {
+                        sink(entry_from_object(&fields, text, line_number, ctx))?;
+                        count += 1;
+                    }
+                    _ => skipped += 1,
+                }
+            }
+            line_number += 1;
+        }
+
+        if skipped > 0 {
+            log::warn!("Skipped {} non-JSON lines in {}", skipped, ctx.file_path);
+            ctx.skip_rows(skipped);
+        }
+
+        println!("Parsed {} log entries from {}", count, ctx.file_path);
+        Ok(())
+    }
+}
+
+fn first_field<'a>(fields: &'a Map<String, Value>, keys: &[&str]) -> Option<&'a Value> {
+    keys.iter().find_map(|key| fields.get(*key).filter(|v| !v.is_null()))
+}
+
+fn field_text(fields: &Map<String, Value>, keys: &[&str]) -> Option<String> {
+    first_field(fields, keys).map(|value| match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    })
+}
+
+fn entry_from_object(
+    fields: &Map<String, Value>,
+    raw_line: &str,
+    line_number: usize,
+    ctx: &ParseContext,
+) -> LogEntry {
+    let (timestamp, timestamp_ms) = match first_field(fields, TIMESTAMP_KEYS) {
+        Some(Value::Number(n)) => {
+            let value = n.as_f64().unwrap_or_default();
+            let ms = if value.abs() >= MILLIS_THRESHOLD { value } else { value * 1000.0 } as i64;
+            let text = DateTime::<Utc>::from_timestamp_millis(ms)
+                .map(|dt| dt.to_rfc3339_opts(SecondsFormat::Millis, true))
+                .unwrap_or_else(|| n.to_string());
+            (text, Some(ms))
+        }
+        Some(Value::String(s)) => (s.clone(), ctx.timestamps.parse_millis(s)),
+        _ => (String::new(), None),
+    };
+
+    LogEntry {
+        id: None,
+        test_session_id: ctx.test_session_id.to_string(),
+        file_path: ctx.file_path.to_string(),
+        file_index: ctx.file_index,
+        timestamp,
+        timestamp_ms,
+        level: field_text(fields, LEVEL_KEYS)
+            .map(|l| l.to_uppercase())
+            .unwrap_or_else(|| "INFO".to_string()),
+        stack: field_text(fields, STACK_KEYS).unwrap_or_default(),
+        message: field_text(fields, MESSAGE_KEYS).unwrap_or_else(|| raw_line.to_string()),
+        line_number,
+        thread: field_text(fields, THREAD_KEYS),
+        module: field_text(fields, MODULE_KEYS),
+        source_line: first_field(fields, LINE_KEYS).and_then(|v| match v {
+            Value::Number(n) => n.as_u64().map(|n| n as usize),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }),
+        is_failure: false,
+        json_payload: None,
+        created_at: Some(Utc::now()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::TimestampParser;
+
+    #[test]
+    fn test_parse_json_lines() {
+        let content = concat!(
+            "{\"timestamp\": \"2026-01-14 07:17:37.370\", \"levelname\": \"warning\", \"msg\": \"disk low\", \"threadName\": \"MainThread\", \"name\": \"app.disk\", \"lineno\": 42}\n",
+            "not json\n",
+            "\n",
+            "{\"ts\": 1768375057, \"message\": \"epoch seconds\", \"exc_info\": \"Traceback\"}\n",
+            "{\"time\": 1768375057370}\n",
+        );
+        let timestamps = TimestampParser::default();
+        let ctx = ParseContext::new("app.jsonl", "s1", 2, &timestamps);
+
+        let entries = JsonLinesFormat.parse_all(&mut content.as_bytes(), &ctx).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(entries[0].level, "WARNING");
+        assert_eq!(entries[0].message, "disk low");
+        assert_eq!(entries[0].thread.as_deref(), Some("MainThread"));
+        assert_eq!(entries[0].module.as_deref(), Some("app.disk"));
+        assert_eq!(entries[0].source_line, Some(42));
+        assert_eq!(entries[0].timestamp_ms, Some(1768375057370));
+        assert_eq!(entries[0].file_index, 2);
+
+        assert_eq!(entries[1].line_number, 3);
+        assert_eq!(entries[1].timestamp_ms, Some(1768375057000));
+        assert_eq!(entries[1].timestamp, "2026-01-14T07:17:37.000Z");
+        assert_eq!(entries[1].stack, "Traceback");
+        assert_eq!(entries[1].level, "INFO");
+
+        // Without a message field the raw line is kept
+        assert_eq!(entries[2].timestamp_ms, Some(1768375057370));
+        assert_eq!(entries[2].message, "{\"time\": 1768375057370}");
+    }
+}
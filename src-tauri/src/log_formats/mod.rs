@@ -0,0 +1,777 @@
+//! Pluggable log formats
+//!
+//! Each supported log format implements [`LogFormat`], which decides whether it
+//! can read a file and turns its contents into [`LogEntry`] rows. Directory
+//! scans, local imports and HTTP downloads all go through a [`FormatRegistry`]
+//! so adding a format does not require touching the import code.
+
+mod html;
+mod json_lines;
+mod text;
+
+pub use html::*;
+pub use json_lines::*;
+pub use text::*;
+
+use crate::archive::{self, ArchiveCache, ArchiveKind};
+use crate::encoding::{decode_head, detect_encoding, DecodingReader, ENCODING_SNIFF_SIZE};
+use crate::grouping::{FileGroup, GroupingRule, SessionGrouper};
+use crate::html_profile::HtmlTableProfile;
+use crate::import_report::{panic_message, FileReport, ParseStats};
+use crate::json_payload::extract_json_payload;
+use crate::level::normalize_level;
+use crate::log_parser::{IgnoredFile, LogEntry};
+use crate::timestamp::TimestampParser;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Number of leading bytes handed to [`LogFormat::detect`] when sniffing a file
+pub const DETECT_HEAD_SIZE: usize = 4096;
+
+/// Where the entries being parsed belong
+pub struct ParseContext<'a> {
+    /// Path or URL of the source, stored on every entry
+    pub file_path: &'a str,
+    pub test_session_id: &'a str,
+    /// Position of the file within its session
+    pub file_index: usize,
+    pub timestamps: &'a TimestampParser,
+    /// The file is still being written: a trailing row or line that may be
+    /// incomplete, and an entry that may still be continued, are left out
+    pub growing: bool,
+    skipped_rows: Cell<usize>,
+    title: RefCell<Option<String>>,
+}
+
+impl<'a> ParseContext<'a> {
+    pub fn new(
+        file_path: &'a str,
+        test_session_id: &'a str,
+        file_index: usize,
+        timestamps: &'a TimestampParser,
+    ) -> Self {
+        Self {
+            file_path,
+            test_session_id,
+            file_index,
+            timestamps,
+            growing: false,
+            skipped_rows: Cell::new(0),
+            title: RefCell::new(None),
+        }
+    }
+
+    /// Record rows the format could not turn into entries, for the import report
+    pub fn skip_rows(&self, count: usize) {
+        self.skipped_rows.set(self.skipped_rows.get() + count);
+    }
+
+    pub fn skipped_rows(&self) -> usize {
+        self.skipped_rows.get()
+    }
+
+    /// Record the document title of the source, e.g. an HTML `<title>`
+    pub fn set_title(&self, title: String) {
+        *self.title.borrow_mut() = Some(title);
+    }
+
+    fn take_title(&self) -> Option<String> {
+        self.title.borrow_mut().take()
+    }
+}
+
+/// Receives the entries of a source one at a time, in order
+pub type EntrySink<'a> = dyn FnMut(LogEntry) -> Result<(), Box<dyn std::error::Error>> + 'a;
+
+/// Entries handed to a [`BatchSink`] at a time, so an import holds at most
+/// this many entries of a file in memory
+pub const ENTRY_BATCH_SIZE: usize = 1000;
+
+/// Receives the entries of a source in order, in batches of up to [`ENTRY_BATCH_SIZE`]
+pub type BatchSink<'a> = dyn FnMut(Vec<LogEntry>) -> Result<(), Box<dyn std::error::Error>> + 'a;
+
+/// Entries of one source together with the report of how parsing it went
+pub struct ParsedFile {
+    /// Entries read before any error
+    pub entries: Vec<LogEntry>,
+    pub report: FileReport,
+    /// Document title of the source, if its format has one
+    pub title: Option<String>,
+}
+
+/// Report of a source whose entries were handed to a [`BatchSink`]
+pub struct StreamedFile {
+    pub report: FileReport,
+    /// Document title of the source, if its format has one
+    pub title: Option<String>,
+}
+
+/// A log file format that can be detected and parsed into entries
+pub trait LogFormat: Send + Sync {
+    /// Short identifier, e.g. `"html"`
+    fn name(&self) -> &str;
+
+    /// Whether this format can read the file.
+    ///
+    /// `head` holds the first bytes of the content, or is empty when only the
+    /// file name is known (e.g. entries of a remote directory listing).
+    fn detect(&self, file_name: &str, head: &[u8]) -> bool;
+
+    /// Test session the file belongs to, or `None` if it should be ignored
+    fn session_name(&self, file_name: &str) -> Option<String> {
+        test_name_from_file_name(file_name).or_else(|| {
+            let stem = file_name.split('.').next().unwrap_or(file_name);
+            (!stem.is_empty()).then(|| stem.to_string())
+        })
+    }
+
+    /// Parse the source, handing each entry to `sink` as soon as it is
+    /// complete. An error returned by `sink` stops parsing and is returned.
+    fn parse(
+        &self,
+        reader: &mut dyn Read,
+        ctx: &ParseContext,
+        sink: &mut EntrySink,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Parse the whole source into entries
+    fn parse_all(
+        &self,
+        reader: &mut dyn Read,
+        ctx: &ParseContext,
+    ) -> Result<Vec<LogEntry>, Box<dyn std::error::Error>> {
+        let mut entries = Vec::new();
+        self.parse(reader, ctx, &mut |entry| {
+            entries.push(entry);
+            Ok(())
+        })?;
+        Ok(entries)
+    }
+}
+
+/// Extract `<TestName>_ID_<X>` from a `<TestName>_ID_<X>---<Y>.<ext>` file name
+///
+/// # Examples
+/// ```
+/// use logterminator_lib::log_formats::test_name_from_file_name;
+/// assert_eq!(
+///     test_name_from_file_name("TestSimpleIO_ID_1---2.jsonl"),
+///     Some("TestSimpleIO_ID_1".to_string())
+/// );
+/// assert_eq!(test_name_from_file_name("service.jsonl"), None);
+/// ```
+pub fn test_name_from_file_name(file_name: &str) -> Option<String> {
+    let dash_pos = file_name.rfind("---")?;
+    let after_dash = &file_name[dash_pos + 3..];
+    let index_str = after_dash.split('.').next()?;
+    if after_dash.len() == index_str.len() {
+        return None;
+    }
+    index_str.parse::<usize>().ok()?;
+
+    let test_name_part = &file_name[..dash_pos];
+    let id_pos = test_name_part
+        .rfind("_ID_")
+        .or_else(|| test_name_part.rfind("_AXID_"))?;
+    if id_pos == 0 {
+        return None;
+    }
+
+    Some(test_name_part.to_string())
+}
+
+/// Files grouped into test sessions by a directory scan
+#[derive(Debug, Default)]
+pub struct ScannedGroups {
+    /// Test name to its files, ordered by index
+    pub groups: HashMap<String, Vec<String>>,
+    pub ignored: Vec<IgnoredFile>,
+}
+
+/// Ordered set of known formats; the first one whose `detect` matches wins
+pub struct FormatRegistry {
+    formats: Vec<Box<dyn LogFormat>>,
+    grouping: SessionGrouper,
+}
+
+impl Default for FormatRegistry {
+    /// Registry with all built-in formats
+    fn default() -> Self {
+        Self::configured(&[], &[], &[])
+    }
+}
+
+impl FormatRegistry {
+    /// Empty registry
+    pub fn new() -> Self {
+        Self {
+            formats: Vec::new(),
+            grouping: SessionGrouper::default(),
+        }
+    }
+
+    /// Built-in formats extended with user HTML profiles, followed by text
+    /// formats for the given grammars. Files are grouped into sessions by the
+    /// grouping rules first and by each format's naming scheme otherwise.
+    ///
+    /// Grammars that fail to compile are skipped with a warning; settings are
+    /// validated on save, so this only happens for hand-edited files.
+    pub fn configured(
+        html_profiles: &[HtmlTableProfile],
+        grammars: &[LogGrammar],
+        grouping_rules: &[GroupingRule],
+    ) -> Self {
+        let mut registry = Self::new();
+        registry.grouping = SessionGrouper::new(grouping_rules);
+        registry.register(Box::new(HtmlLogFormat::with_profiles(html_profiles)));
+        registry.register(Box::new(JsonLinesFormat));
+        for grammar in grammars {
+            match TextLogFormat::new(grammar) {
+                Ok(format) => registry.register(Box::new(format)),
+                Err(e) => log::warn!("Skipping log grammar: {}", e),
+            }
+        }
+        registry
+    }
+
+    pub fn register(&mut self, format: Box<dyn LogFormat>) {
+        self.formats.push(format);
+    }
+
+    pub fn formats(&self) -> impl Iterator<Item = &dyn LogFormat> {
+        self.formats.iter().map(|f| f.as_ref())
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&dyn LogFormat> {
+        self.formats().find(|f| f.name() == name)
+    }
+
+    /// First format that accepts the file
+    pub fn detect(&self, file_name: &str, head: &[u8]) -> Option<&dyn LogFormat> {
+        self.formats().find(|f| f.detect(file_name, head))
+    }
+
+    /// Format and session assignment for a source, given its path and first
+    /// bytes; the error explains why the file is not part of any session
+    fn classify(&self, path: &str, head: &[u8]) -> Result<(&dyn LogFormat, FileGroup), String> {
+        let file_name = archive::logical_file_name(path);
+        let format = self
+            .detect(file_name, &decode_head(file_name, head))
+            .ok_or_else(|| "Unrecognized log format".to_string())?;
+
+        if let Some(group) = self.grouping.match_path(archive::strip_gz(path)) {
+            return Ok((format, group));
+        }
+
+        match format.session_name(file_name) {
+            Some(test) => Ok((format, FileGroup { test, index: None })),
+            None if self.grouping.is_empty() => Err(format!(
+                "Name does not match the {} test log naming pattern",
+                format.name()
+            )),
+            None => Err(format!(
+                "Name matches no grouping rule and not the {} test log naming pattern",
+                format.name()
+            )),
+        }
+    }
+
+    /// Format and session name for a remote file, judged by its name only
+    pub fn classify_url(&self, url: &str) -> Option<(&dyn LogFormat, String)> {
+        self.classify(url, &[])
+            .ok()
+            .map(|(format, group)| (format, group.test))
+    }
+
+    /// Group the log files under a directory by test session.
+    ///
+    /// Zip and tar archives found under the directory (or passed as the
+    /// directory itself) are scanned like subdirectories; their files are
+    /// returned as virtual `archive!/inner` paths. Files in each group are
+    /// sorted by the index from the grouping rule or their `---<index>`
+    /// suffix. Every file not assigned to a session is reported with the
+    /// reason.
+    pub fn scan_directory(&self, directory_path: &str) -> Result<ScannedGroups, Box<dyn std::error::Error>> {
+        if !Path::new(directory_path).exists() {
+            return Err(format!("Path does not exist: {}", directory_path).into());
+        }
+
+        // Path and first bytes of every readable file, archive members included
+        let mut sources: Vec<(String, Vec<u8>)> = Vec::new();
+        let mut ignored = Vec::new();
+
+        for entry in WalkDir::new(directory_path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            let Some(path_str) = path.to_str() else {
+                continue;
+            };
+
+            if ArchiveKind::from_path(path_str).is_some() {
+                match archive::list_members(path_str, DETECT_HEAD_SIZE) {
+                    Ok(members) => sources.extend(members.into_iter().map(|m| (m.path, m.head))),
+                    Err(e) => ignored.push(IgnoredFile {
+                        path: path_str.to_string(),
+                        reason: format!("Cannot read archive: {}", e),
+                    }),
+                }
+                continue;
+            }
+
+            match archive::open_source(path_str).and_then(|mut reader| read_head(&mut reader)) {
+                Ok(head) => sources.push((path_str.to_string(), head)),
+                Err(e) => ignored.push(IgnoredFile {
+                    path: path_str.to_string(),
+                    reason: format!("Cannot read file: {}", e),
+                }),
+            }
+        }
+
+        let mut indexed: HashMap<String, Vec<(usize, String)>> = HashMap::new();
+        for (path, head) in sources {
+            match self.classify(&path, &head) {
+                Ok((_, group)) => {
+                    let index = group
+                        .index
+                        .unwrap_or_else(|| crate::log_parser::HtmlLogParser::extract_file_index(&path));
+                    indexed.entry(group.test).or_default().push((index, path));
+                }
+                Err(reason) => ignored.push(IgnoredFile { path, reason }),
+            }
+        }
+
+        let groups: HashMap<String, Vec<String>> = indexed
+            .into_iter()
+            .map(|(test, mut files)| {
+                files.sort();
+                (test, files.into_iter().map(|(_, path)| path).collect())
+            })
+            .collect();
+
+        if !ignored.is_empty() {
+            println!("Ignored {} files", ignored.len());
+            for file in ignored.iter().take(5) {
+                println!("  - {} ({})", file.path, file.reason);
+            }
+            if ignored.len() > 5 {
+                println!("  - ... and {} more", ignored.len() - 5);
+            }
+        }
+
+        println!(
+            "Found {} test groups with {} total files",
+            groups.len(),
+            groups.values().map(|v| v.len()).sum::<usize>()
+        );
+        Ok(ScannedGroups { groups, ignored })
+    }
+
+    /// Detect the format of a local file or archive member and parse it
+    pub fn parse_file(
+        &self,
+        file_path: &str,
+        test_session_id: &str,
+        file_index: usize,
+        timestamps: &TimestampParser,
+    ) -> Result<Vec<LogEntry>, Box<dyn std::error::Error>> {
+        let reader = archive::open_source(file_path)?;
+        let ctx = ParseContext::new(file_path, test_session_id, file_index, timestamps);
+        self.collect_decoded(reader, &ctx)
+    }
+
+    /// Like [`stream_file_with_report`](Self::stream_file_with_report), for
+    /// a local file that may still be written: with `growing` set it is
+    /// parsed as such (see [`ParseContext::growing`])
+    pub fn stream_followed_file_with_report(
+        &self,
+        file_path: &str,
+        test_session_id: &str,
+        file_index: usize,
+        timestamps: &TimestampParser,
+        growing: bool,
+        sink: &mut BatchSink,
+    ) -> StreamedFile {
+        let mut ctx = ParseContext::new(file_path, test_session_id, file_index, timestamps);
+        ctx.growing = growing;
+        self.stream_with_report(|| archive::open_source(file_path), &ctx, sink)
+    }
+
+    /// Like [`parse_content`](Self::parse_content), but errors and panics end
+    /// up in the report instead of being returned
+    pub fn parse_content_with_report(
+        &self,
+        content: &[u8],
+        file_url: &str,
+        test_session_id: &str,
+        file_index: usize,
+        timestamps: &TimestampParser,
+    ) -> ParsedFile {
+        let ctx = ParseContext::new(file_url, test_session_id, file_index, timestamps);
+        self.parse_with_report(|| Ok(archive::decompress_if_gzipped(file_url, content)), &ctx)
+    }
+
+    /// Parse downloaded content, detecting the format from its name and first bytes
+    pub fn parse_content(
+        &self,
+        content: &[u8],
+        file_url: &str,
+        test_session_id: &str,
+        file_index: usize,
+        timestamps: &TimestampParser,
+    ) -> Result<Vec<LogEntry>, Box<dyn std::error::Error>> {
+        self.parse_reader(content, file_url, test_session_id, file_index, timestamps)
+    }
+
+    /// Parse a byte stream named `source`, decompressing it first if the name ends in `.gz`
+    pub fn parse_reader<R: Read + Send>(
+        &self,
+        reader: R,
+        source: &str,
+        test_session_id: &str,
+        file_index: usize,
+        timestamps: &TimestampParser,
+    ) -> Result<Vec<LogEntry>, Box<dyn std::error::Error>> {
+        let reader = archive::decompress_if_gzipped(source, reader);
+        let ctx = ParseContext::new(source, test_session_id, file_index, timestamps);
+        self.collect_decoded(reader, &ctx)
+    }
+
+    fn collect_decoded(
+        &self,
+        reader: Box<dyn Read + Send + '_>,
+        ctx: &ParseContext,
+    ) -> Result<Vec<LogEntry>, Box<dyn std::error::Error>> {
+        let mut entries = Vec::new();
+        self.parse_decoded(reader, ctx, &mut ParseStats::default(), &mut |entry| {
+            entries.push(entry);
+            Ok(())
+        })?;
+        Ok(entries)
+    }
+
+    /// Like [`parse_file`](Self::parse_file), but the file is opened through
+    /// `archives`, the entries are handed to `sink` in batches as they are
+    /// parsed, and errors and panics end up in the report instead of being
+    /// returned
+    pub fn stream_file_with_report(
+        &self,
+        archives: &ArchiveCache,
+        file_path: &str,
+        test_session_id: &str,
+        file_index: usize,
+        timestamps: &TimestampParser,
+        sink: &mut BatchSink,
+    ) -> StreamedFile {
+        let ctx = ParseContext::new(file_path, test_session_id, file_index, timestamps);
+        self.stream_with_report(|| archives.open_source(file_path), &ctx, sink)
+    }
+
+    /// Like [`parse_content_with_report`](Self::parse_content_with_report),
+    /// but reading a byte stream named `source`, decompressed first if the
+    /// name ends in `.gz`. The entries are handed to `sink` in batches as
+    /// they are parsed.
+    pub fn stream_reader_with_report<R: Read + Send>(
+        &self,
+        reader: R,
+        source: &str,
+        test_session_id: &str,
+        file_index: usize,
+        timestamps: &TimestampParser,
+        sink: &mut BatchSink,
+    ) -> StreamedFile {
+        let ctx = ParseContext::new(source, test_session_id, file_index, timestamps);
+        self.stream_with_report(|| Ok(archive::decompress_if_gzipped(source, reader)), &ctx, sink)
+    }
+
+    fn parse_with_report<'r>(
+        &self,
+        open: impl FnOnce() -> std::io::Result<Box<dyn Read + Send + 'r>>,
+        ctx: &ParseContext,
+    ) -> ParsedFile {
+        let mut entries = Vec::new();
+        let streamed = self.stream_with_report(open, ctx, &mut |batch| {
+            entries.extend(batch);
+            Ok(())
+        });
+        ParsedFile {
+            entries,
+            report: streamed.report,
+            title: streamed.title,
+        }
+    }
+
+    fn stream_with_report<'r>(
+        &self,
+        open: impl FnOnce() -> std::io::Result<Box<dyn Read + Send + 'r>>,
+        ctx: &ParseContext,
+        sink: &mut BatchSink,
+    ) -> StreamedFile {
+        let source = ctx.file_path;
+        let mut stats = ParseStats::default();
+        let mut rows = 0;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let reader = open()?;
+            let mut batch = Vec::with_capacity(ENTRY_BATCH_SIZE);
+            self.parse_decoded(reader, ctx, &mut stats, &mut |entry| {
+                batch.push(entry);
+                if batch.len() == ENTRY_BATCH_SIZE {
+                    rows += batch.len();
+                    sink(std::mem::replace(&mut batch, Vec::with_capacity(ENTRY_BATCH_SIZE)))?;
+                }
+                Ok(())
+            })?;
+            rows += batch.len();
+            if !batch.is_empty() {
+                sink(batch)?;
+            }
+            Ok::<_, Box<dyn std::error::Error>>(())
+        }));
+
+        let error = match result {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(panic) => Some(format!("Panic while parsing: {}", panic_message(&*panic))),
+        };
+        if let Some(e) = &error {
+            log::warn!("Failed to parse {}: {}", source, e);
+        }
+
+        StreamedFile {
+            report: FileReport::new(source, rows, error, stats),
+            title: ctx.take_title(),
+        }
+    }
+
+    fn parse_decoded(
+        &self,
+        reader: Box<dyn Read + Send + '_>,
+        ctx: &ParseContext,
+        stats: &mut ParseStats,
+        sink: &mut EntrySink,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let source = ctx.file_path;
+        let file_name = archive::logical_file_name(source);
+        let mut reader = CountingReader { inner: reader, bytes: 0 };
+
+        let mut prefix = Vec::with_capacity(ENCODING_SNIFF_SIZE);
+        (&mut reader).take(ENCODING_SNIFF_SIZE as u64).read_to_end(&mut prefix)?;
+        let encoding = detect_encoding(file_name, &prefix, prefix.len() < ENCODING_SNIFF_SIZE);
+        stats.encoding = Some(encoding.name());
+        // Formats only ever see UTF-8; the sniffed bytes are replayed first
+        let mut decoded = DecodingReader::new(prefix.as_slice().chain(&mut reader), encoding);
+
+        let head = read_head(&mut decoded)?;
+        let format = self
+            .detect(file_name, &head)
+            .ok_or_else(|| format!("Unrecognized log format: {}", source))?;
+
+        println!("Parsing {} (format: {}, encoding: {})", source, format.name(), encoding.name());
+        let result = format.parse(&mut head.as_slice().chain(&mut decoded), ctx, &mut |mut entry| {
+            entry.level = normalize_level(&entry.level);
+            entry.json_payload = extract_json_payload(&entry.message);
+            sink(entry)
+        });
+        stats.bytes = reader.bytes;
+        stats.skipped_rows = ctx.skipped_rows();
+        result
+    }
+}
+
+/// Counts the bytes read through it
+struct CountingReader<R> {
+    inner: R,
+    bytes: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.bytes += read as u64;
+        Ok(read)
+    }
+}
+
+fn read_head(reader: &mut dyn Read) -> std::io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(DETECT_HEAD_SIZE);
+    reader.take(DETECT_HEAD_SIZE as u64).read_to_end(&mut head)?;
+    Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_by_name_and_content() {
+        let registry = FormatRegistry::default();
+
+        assert_eq!(registry.detect("T_ID_1---0.html", b"").map(|f| f.name()), Some("html"));
+        assert_eq!(registry.detect("T_ID_1---0.jsonl", b"").map(|f| f.name()), Some("jsonl"));
+        assert_eq!(
+            registry.detect("app.log", b"{\"msg\": \"hi\"}\n").map(|f| f.name()),
+            Some("jsonl")
+        );
+        assert!(registry.detect("app.log", b"plain text line\n").is_none());
+        assert!(registry.detect("notes.txt", b"").is_none());
+    }
+
+    #[test]
+    fn test_session_names() {
+        let registry = FormatRegistry::default();
+
+        let (format, session) = registry
+            .classify_url("http://host/logs/TestA_ID_3---1.html")
+            .unwrap();
+        assert_eq!(format.name(), "html");
+        assert_eq!(session, "TestA_ID_3");
+
+        // HTML files outside the naming scheme (rollups, indexes) are ignored
+        assert!(registry.classify_url("http://host/logs/MainRollup.html").is_none());
+
+        let (_, session) = registry.classify_url("http://host/logs/service.jsonl").unwrap();
+        assert_eq!(session, "service");
+    }
+
+    #[test]
+    fn test_scan_directory_grouping_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("smoke/TestLogin")).unwrap();
+        for name in ["smoke/TestLogin/part_2.html", "smoke/TestLogin/part_10.html", "TestA_ID_1---0.html", "MainRollup.html", "image.png"] {
+            std::fs::write(root.join(name), "<table></table>").unwrap();
+        }
+
+        let rules = [GroupingRule::Template { template: "{suite}/{test}/part_{index}.html".to_string() }];
+        let scanned = FormatRegistry::configured(&[], &[], &rules)
+            .scan_directory(root.to_str().unwrap())
+            .unwrap();
+
+        assert_eq!(scanned.groups.len(), 2);
+        let login = &scanned.groups["TestLogin"];
+        assert!(login[0].ends_with("part_2.html"));
+        assert!(login[1].ends_with("part_10.html"));
+        assert_eq!(scanned.groups["TestA_ID_1"].len(), 1);
+
+        let mut ignored: Vec<_> = scanned
+            .ignored
+            .iter()
+            .map(|f| (archive::logical_file_name(&f.path), f.reason.as_str()))
+            .collect();
+        ignored.sort();
+        assert_eq!(ignored[0].0, "MainRollup.html");
+        assert!(ignored[0].1.contains("grouping rule"));
+        assert_eq!(ignored[1], ("image.png", "Unrecognized log format"));
+    }
+
+    #[test]
+    fn test_parse_content_dispatch() {
+        let registry = FormatRegistry::default();
+        let timestamps = TimestampParser::default();
+        let content = b"{\"timestamp\": \"2026-01-14 07:17:37.370\", \"level\": \"info\", \"message\": \"hello\"}\n";
+
+        let entries = registry
+            .parse_content(content, "http://host/T_ID_1---0.jsonl", "s1", 0, &timestamps)
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].message, "hello");
+
+        assert!(registry
+            .parse_content(b"???", "http://host/readme.md", "s1", 0, &timestamps)
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_non_utf8_content() {
+        let registry = FormatRegistry::default();
+        let timestamps = TimestampParser::default();
+
+        let html = r#"<html><head><meta charset="gb2312"></head><table>
+<tr><th>Timestamp</th><th>Level</th><th>Message</th></tr>
+<tr><td class="date">2026/01/14 07:17:37,370</td><td class="level">ERROR</td><td class="message">设备温度过高</td></tr>
+</table></html>"#;
+        let (gbk, _, _) = encoding_rs::GBK.encode(html);
+        let parsed = registry.parse_content_with_report(&gbk, "T_ID_1---0.html", "s1", 0, &timestamps);
+        assert_eq!(parsed.report.error, None);
+        assert_eq!(parsed.report.encoding.as_deref(), Some("GBK"));
+        assert_eq!(parsed.entries[0].message, "设备温度过高");
+
+        // Latin-1 bytes without any declaration no longer fail the file
+        let jsonl = b"{\"level\": \"info\", \"message\": \"Ger\xE4t bereit\"}\n";
+        let parsed = registry.parse_content_with_report(jsonl, "T_ID_1---0.jsonl", "s1", 0, &timestamps);
+        assert_eq!(parsed.report.error, None);
+        assert_eq!(parsed.report.encoding.as_deref(), Some("windows-1252"));
+        assert_eq!(parsed.entries[0].message, "Gerät bereit");
+    }
+
+    #[test]
+    fn test_stream_reader_reports_read_errors() {
+        struct Reset;
+        impl Read for Reset {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "connection reset"))
+            }
+        }
+
+        let mut html = String::from("<table><tr><th>Timestamp</th><th>Level</th><th>Message</th></tr>");
+        for i in 0..ENTRY_BATCH_SIZE + 10 {
+            html.push_str(&format!(
+                r#"<tr><td class="date">2026/01/14 07:17:37,370</td><td class="level">INFO</td><td class="message">m{}</td></tr>"#,
+                i
+            ));
+        }
+        let mut batches = Vec::new();
+        let streamed = FormatRegistry::default().stream_reader_with_report(
+            html.as_bytes().chain(Reset),
+            "http://ci/logs/T_ID_1---0.html",
+            "s1",
+            0,
+            &TimestampParser::default(),
+            &mut |batch| {
+                batches.push(batch.len());
+                Ok(())
+            },
+        );
+        assert_eq!(streamed.report.status, crate::import_report::FileParseStatus::Failed);
+        assert!(streamed.report.error.unwrap().contains("connection reset"));
+        // Rows read before the error are still delivered
+        assert_eq!(batches[0], ENTRY_BATCH_SIZE);
+        assert_eq!(streamed.report.rows, batches.iter().sum::<usize>());
+    }
+
+    #[test]
+    fn test_parse_reports_html_title() {
+        let registry = FormatRegistry::default();
+        let timestamps = TimestampParser::default();
+
+        let html = r#"<html><head><title> Smoke run on tb-lab-07 </title></head><table>
+<tr><th>Timestamp</th><th>Level</th><th>Message</th></tr>
+<tr><td class="date">2026/01/14 07:17:37,370</td><td class="level">INFO</td><td class="message">start</td></tr>
+</table></html>"#;
+        let parsed = registry.parse_content_with_report(html.as_bytes(), "T_ID_1---0.html", "s1", 0, &timestamps);
+        assert_eq!(parsed.title.as_deref(), Some("Smoke run on tb-lab-07"));
+        assert_eq!(parsed.entries.len(), 1);
+        assert_eq!(parsed.entries[0].message, "start");
+
+        let parsed = registry.parse_content_with_report(b"{\"message\": \"hi\"}\n", "a.jsonl", "s1", 0, &timestamps);
+        assert_eq!(parsed.title, None);
+    }
+
+    #[test]
+    fn test_parse_normalizes_levels() {
+        let registry = FormatRegistry::default();
+        let timestamps = TimestampParser::default();
+
+        let jsonl = b"{\"level\": \"warn\", \"message\": \"a\"}\n{\"level\": \"fatal\", \"message\": \"b\"}\n{\"level\": \"marker\", \"message\": \"c\"}\n";
+        let parsed = registry.parse_content_with_report(jsonl, "run.jsonl", "s1", 0, &timestamps);
+        let levels: Vec<&str> = parsed.entries.iter().map(|e| e.level.as_str()).collect();
+        assert_eq!(levels, ["WARNING", "CRITICAL", "MARKER"]);
+    }
+}
@@ -0,0 +1,245 @@
+use super::{EntrySink, LogFormat, ParseContext};
+use crate::log_parser::LogEntry;
+use chrono::Utc;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read};
+
+/// Capture groups a grammar may define; only `message` is required
+pub const GRAMMAR_CAPTURES: &[&str] = &["timestamp", "level", "thread", "module", "line", "message"];
+
+/// Grammar shipped with the app, matching Python `logging` and log4j style lines
+/// such as `2026-01-14 07:17:37,370 [MainThread] INFO app.disk - disk low`
+pub const DEFAULT_GRAMMAR_PATTERN: &str = r"^(?P<timestamp>\d{4}[-/]\d{2}[-/]\d{2}[ T]\d{2}:\d{2}:\d{2}(?:[.,]\d+)?(?: ?(?:Z|UTC|[+-]\d{2}:?\d{2}))?)\s+(?:-\s+)?(?:\[(?P<thread>[^\]]+)\]\s+)?(?P<level>TRACE|DEBUG|INFO|NOTICE|WARN|WARNING|ERROR|CRITICAL|FATAL)\s+(?:-\s+)?(?:(?P<module>[\w.$]+)(?::(?P<line>\d+))?\s+-\s+)?(?P<message>.*)$";
+
+/// A named regex describing one log line, saved in the app settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LogGrammar {
+    pub name: String,
+    /// Regex with named captures from [`GRAMMAR_CAPTURES`]
+    pub pattern: String,
+}
+
+impl LogGrammar {
+    pub fn builtin() -> Self {
+        Self {
+            name: "default".to_string(),
+            pattern: DEFAULT_GRAMMAR_PATTERN.to_string(),
+        }
+    }
+
+    /// Compile the pattern, checking it captures a `message`
+    pub fn compile(&self) -> Result<Regex, String> {
+        if self.name.trim().is_empty() {
+            return Err("Grammar name cannot be empty".to_string());
+        }
+        let regex = Regex::new(&self.pattern)
+            .map_err(|e| format!("Invalid pattern for grammar '{}': {}", self.name, e))?;
+        if !regex.capture_names().flatten().any(|name| name == "message") {
+            return Err(format!("Grammar '{}' must have a named 'message' capture", self.name));
+        }
+        Ok(regex)
+    }
+}
+
+/// Line-oriented `.log`/`.txt` files parsed with a [`LogGrammar`].
+///
+/// Lines that do not match the grammar are continuation lines (e.g. Python
+/// tracebacks) and are appended to the message of the previous entry.
+pub struct TextLogFormat {
+    name: String,
+    regex: Regex,
+}
+
+impl TextLogFormat {
+    pub fn new(grammar: &LogGrammar) -> Result<Self, String> {
+        Ok(Self {
+            name: format!("text:{}", grammar.name),
+            regex: grammar.compile()?,
+        })
+    }
+
+    fn entry_from_line(&self, line: &str, line_number: usize, ctx: &ParseContext) -> Option<LogEntry> {
+        let caps = self.regex.captures(line)?;
+        let text = |name: &str| caps.name(name).map(|m| m.as_str().trim().to_string());
+
+        let timestamp = text("timestamp").unwrap_or_default();
+        Some(LogEntry {
+            id: None,
+            test_session_id: ctx.test_session_id.to_string(),
+            file_path: ctx.file_path.to_string(),
+            file_index: ctx.file_index,
+            timestamp_ms: ctx.timestamps.parse_millis(&timestamp),
+            timestamp,
+            level: text("level")
+                .filter(|l| !l.is_empty())
+                .map(|l| l.to_uppercase())
+                .unwrap_or_else(|| "INFO".to_string()),
+            stack: String::new(),
+            message: caps.name("message").map(|m| m.as_str().to_string()).unwrap_or_default(),
+            line_number,
+            thread: text("thread").filter(|t| !t.is_empty()),
+            module: text("module").filter(|m| !m.is_empty()),
+            source_line: text("line").and_then(|l| l.parse().ok()),
+            is_failure: false,
+            json_payload: None,
+            created_at: Some(Utc::now()),
+        })
+    }
+}
+
+impl LogFormat for TextLogFormat {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn detect(&self, file_name: &str, head: &[u8]) -> bool {
+        let lower = file_name.to_ascii_lowercase();
+        if !(lower.ends_with(".log") || lower.ends_with(".txt")) {
+            return false;
+        }
+        if head.is_empty() {
+            return true;
+        }
+
+        String::from_utf8_lossy(head)
+            .lines()
+            .find(|l| !l.trim().is_empty())
+            .is_some_and(|first| self.regex.is_match(first))
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn Read,
+        ctx: &ParseContext,
+        sink: &mut EntrySink,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut reader = BufReader::new(reader);
+        // Held back until the next entry starts, as more lines may continue it
+        let mut pending: Option<LogEntry> = None;
+        let mut count = 0;
+        let mut orphaned = 0;
+        let mut line = String::new();
+        let mut line_number = 0;
+
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            if ctx.growing && !line.ends_with('\n') {
+                break;
+            }
+            let text = line.trim_end_matches(['\r', '\n']);
+
+            if let Some(entry) = self.entry_from_line(text, line_number, ctx) {
+                if let Some(mut previous) = pending.replace(entry) {
+                    trim_trailing_blank_lines(&mut previous.message);
+                    sink(previous)?;
+                    count += 1;
+                }
+            } else if let Some(previous) = pending.as_mut() {
+                previous.message.push('\n');
+                previous.message.push_str(text);
+            } else if !text.trim().is_empty() {
+                orphaned += 1;
+            }
+            line_number += 1;
+        }
+
+        // More continuation lines may still be written for the last entry
+        if let Some(mut last) = pending.filter(|_| !ctx.growing) {
+            trim_trailing_blank_lines(&mut last.message);
+            sink(last)?;
+            count += 1;
+        }
+
+        if orphaned > 0 {
+            log::warn!("Skipped {} lines before the first entry in {}", orphaned, ctx.file_path);
+            ctx.skip_rows(orphaned);
+        }
+
+        println!("Parsed {} log entries from {}", count, ctx.file_path);
+        Ok(())
+    }
+}
+
+/// Blank lines separating entries are not part of the preceding message
+fn trim_trailing_blank_lines(message: &mut String) {
+    let trimmed_len = message.trim_end().len();
+    message.truncate(trimmed_len);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::TimestampParser;
+
+    #[test]
+    fn test_default_grammar_with_traceback() {
+        let content = "\
+preamble without timestamp
+2026-01-14 07:17:37,370 [MainThread] INFO app.disk:42 - disk low
+2026-01-14 07:17:38,000 ERROR failed to connect
+Traceback (most recent call last):
+  File \"app.py\", line 3, in <module>
+ConnectionError: refused
+
+2026-01-14 07:17:39,000 - WARNING - retrying
+";
+        let format = TextLogFormat::new(&LogGrammar::builtin()).unwrap();
+        let timestamps = TimestampParser::default();
+        let ctx = ParseContext::new("app.log", "s1", 0, &timestamps);
+
+        let entries = format.parse_all(&mut content.as_bytes(), &ctx).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(entries[0].thread.as_deref(), Some("MainThread"));
+        assert_eq!(entries[0].module.as_deref(), Some("app.disk"));
+        assert_eq!(entries[0].source_line, Some(42));
+        assert_eq!(entries[0].message, "disk low");
+        assert_eq!(entries[0].timestamp_ms, Some(1768375057370));
+        assert_eq!(entries[0].line_number, 1);
+
+        assert_eq!(entries[1].level, "ERROR");
+        assert!(entries[1].message.starts_with("failed to connect\nTraceback"));
+        assert!(entries[1].message.ends_with("ConnectionError: refused"));
+
+        assert_eq!(entries[2].level, "WARNING");
+        assert_eq!(entries[2].message, "retrying");
+    }
+
+    #[test]
+    fn test_custom_grammar() {
+        let grammar = LogGrammar {
+            name: "pipe".to_string(),
+            pattern: r"^(?P<level>\w+)\|(?P<timestamp>[^|]+)\|(?P<message>.*)$".to_string(),
+        };
+        let format = TextLogFormat::new(&grammar).unwrap();
+        assert!(format.detect("run.txt", b"warn|2026-01-14 07:17:37|hello\n"));
+        assert!(!format.detect("run.txt", b"hello\n"));
+        assert!(!format.detect("run.html", b""));
+
+        let timestamps = TimestampParser::default();
+        let ctx = ParseContext::new("run.txt", "s1", 0, &timestamps);
+        let entries = format.parse_all(&mut "warn|2026-01-14 07:17:37|hello\n".as_bytes(), &ctx).unwrap();
+        assert_eq!(entries[0].level, "WARN");
+        assert_eq!(entries[0].message, "hello");
+        assert!(entries[0].thread.is_none());
+    }
+
+    #[test]
+    fn test_grammar_validation() {
+        let missing_message = LogGrammar {
+            name: "bad".to_string(),
+            pattern: r"^(?P<level>\w+)".to_string(),
+        };
+        assert!(missing_message.compile().is_err());
+
+        let invalid = LogGrammar {
+            name: "bad".to_string(),
+            pattern: r"^(?P<message>".to_string(),
+        };
+        assert!(invalid.compile().is_err());
+    }
+}
@@ -1,7 +1,14 @@
+use crate::html_profile::{CellSelector, HtmlTableProfile};
+use crate::html_stream::{HtmlRow, HtmlRowReader};
+use crate::import_report::panic_message;
+use crate::log_formats::EntrySink;
+use crate::level::{level_from_class, normalize_level};
+use crate::session_attributes::SessionAttributes;
+use crate::timestamp::TimestampParser;
 use chrono::{DateTime, Utc};
-use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
-use walkdir::WalkDir;
+use std::fs::File;
+use std::io::Read;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogEntry {
@@ -10,10 +17,22 @@ pub struct LogEntry {
     pub file_path: String,
     pub file_index: usize,
     pub timestamp: String,
+    /// `timestamp` parsed into epoch milliseconds; `None` if no format matched
+    pub timestamp_ms: Option<i64>,
     pub level: String,
     pub stack: String,
     pub message: String,
     pub line_number: usize,
+    /// Thread name from the hierarchy column, e.g. `MainThread`
+    pub thread: Option<String>,
+    /// Logger/module path from the hierarchy column
+    pub module: Option<String>,
+    /// Source line reported next to the module path
+    pub source_line: Option<usize>,
+    /// The row carried a failure anchor (`id="failureAnchor"`)
+    pub is_failure: bool,
+    /// First JSON object or array found in the message, in compact form
+    pub json_payload: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
 }
 
@@ -38,6 +57,12 @@ pub struct TestSession {
     pub last_parsed_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source_type: Option<String>,
+    /// Some file failed to parse or the `---N` sequence has gaps; see the import report
+    #[serde(default)]
+    pub incomplete: bool,
+    /// Run metadata such as package versions, testbed and hostname
+    #[serde(default)]
+    pub attributes: SessionAttributes,
 }
 
 /// Result of scanning a directory or HTTP URL for test sessions
@@ -58,181 +83,289 @@ pub struct ScanResult {
     pub estimated_entries: Option<usize>,
 }
 
-pub struct HtmlLogParser;
+/// A file skipped while scanning, with the reason it was skipped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IgnoredFile {
+    pub path: String,
+    pub reason: String,
+}
 
-impl HtmlLogParser {
-    pub fn parse_file(
-        file_path: &str,
-        test_session_id: &str,
-        file_index: usize,
-    ) -> Result<Vec<LogEntry>, Box<dyn std::error::Error>> {
-        println!("Parsing file: {}", file_path);
+/// Result of scanning a local directory: the sessions found plus every file
+/// that was not assigned to one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryScan {
+    pub sessions: Vec<ScanResult>,
+    pub ignored_files: Vec<IgnoredFile>,
+}
 
-        let content = std::fs::read_to_string(file_path)?;
-        println!("  File content loaded: {} bytes", content.len());
+/// Structured content of a `td.hierarchy` cell.
+///
+/// The cell looks like `Thread: <MainThread>` followed by a line with
+/// `<module.path> -- <line>`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HierarchyInfo {
+    pub thread: Option<String>,
+    pub module: Option<String>,
+    pub source_line: Option<usize>,
+}
 
-        // Parse the HTML content
-        let entries = Self::parse_content(&content, file_path, test_session_id, file_index)?;
+impl HierarchyInfo {
+    /// Parse the text of a hierarchy cell; missing parts are left as `None`.
+    ///
+    /// # Examples
+    /// ```
+    /// use logterminator_lib::log_parser::HierarchyInfo;
+    /// let info = HierarchyInfo::parse("Thread: <MainThread>\npytest_test_engine.pytest_test_engine -- 871");
+    /// assert_eq!(info.thread.as_deref(), Some("MainThread"));
+    /// assert_eq!(info.module.as_deref(), Some("pytest_test_engine.pytest_test_engine"));
+    /// assert_eq!(info.source_line, Some(871));
+    /// ```
+    pub fn parse(text: &str) -> Self {
+        let mut info = HierarchyInfo::default();
+
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if let Some(thread) = line.strip_prefix("Thread:") {
+                let thread = thread.trim().trim_start_matches('<').trim_end_matches('>').trim();
+                if !thread.is_empty() {
+                    info.thread = Some(thread.to_string());
+                }
+            } else if info.module.is_none() {
+                let (module, source_line) = match line.rsplit_once(" -- ") {
+                    Some((module, number)) => (module.trim(), number.trim().parse::<usize>().ok()),
+                    None => (line, None),
+                };
+                if !module.is_empty() {
+                    info.module = Some(module.to_string());
+                }
+                info.source_line = source_line;
+            }
+        }
 
-        println!("  Total parsed {} entries", entries.len());
-        Ok(entries)
+        info
     }
+}
 
-    // Helper function to parse HTML content
-    fn parse_content(
-        content: &str,
-        file_path: &str,
-        test_session_id: &str,
-        file_index: usize,
-    ) -> Result<Vec<LogEntry>, Box<dyn std::error::Error>> {
-        println!("  Starting HTML parsing...");
+pub struct HtmlLogParser;
 
-        let document = std::panic::catch_unwind(|| {
-            Html::parse_document(content)
-        }).map_err(|e| {
-            println!("  PANIC in Html::parse_document: {:?}", e);
-            format!("Panic during HTML parsing: {:?}", e)
-        })?;
+/// Iterator that yields `LogEntry` values row by row from an HTML byte stream
+pub struct HtmlEntryStream<R: Read> {
+    rows: HtmlRowReader<R>,
+    file_path: String,
+    test_session_id: String,
+    file_index: usize,
+    timestamps: TimestampParser,
+    profiles: Vec<HtmlTableProfile>,
+    /// Index into `profiles` of the mapping in effect
+    profile: usize,
+    /// Texts of the most recent header row, for header-based selectors
+    columns: Vec<String>,
+    /// Entry held back until we know no continuation rows follow it
+    pending: Option<LogEntry>,
+    line_number: usize,
+    entry_count: usize,
+    skipped_rows: usize,
+    /// The source is still being written, see [`HtmlEntryStream::still_growing`]
+    growing: bool,
+}
+
+impl<R: Read> HtmlEntryStream<R> {
+    /// Total number of bytes consumed from the underlying reader so far
+    pub fn bytes_read(&self) -> u64 {
+        self.rows.bytes_read()
+    }
+
+    /// Data rows dropped so far because no entry could be built from them
+    pub fn skipped_rows(&self) -> usize {
+        self.skipped_rows
+    }
+
+    /// Text of the page's `<title>`, once the stream has read past it
+    pub fn title(&self) -> Option<String> {
+        self.rows.title()
+    }
 
-        println!("  HTML document parsed, extracting entries...");
+    /// Treat the source as a file that is still being written: a row that
+    /// is not closed yet is left out, and so is an entry that may still get
+    /// continuation rows
+    pub fn still_growing(self) -> Self {
+        Self {
+            rows: self.rows.hold_open_row(),
+            growing: true,
+            ..self
+        }
+    }
+
+    /// Name of the table profile currently used to map cells
+    pub fn profile_name(&self) -> &str {
+        &self.profiles[self.profile].name
+    }
 
-        // Select table rows
-        let row_selector = Selector::parse("table tr").ok();
-        let row_selector = match row_selector {
-            Some(s) => s,
-            None => {
-                println!("Warning: Could not parse row selector");
-                return Ok(Vec::new());
+    /// Remember the column names and switch to the profile they identify.
+    ///
+    /// When several profiles match, the last one wins so that user profiles
+    /// listed after the built-ins can override them.
+    fn handle_header_row(&mut self, row: &HtmlRow) {
+        self.columns = row.cells.iter().map(|cell| cell.text.trim().to_string()).collect();
+        if let Some(index) = self.profiles.iter().rposition(|p| p.matches_headers(&self.columns)) {
+            if index != self.profile {
+                println!("  Using '{}' table profile", self.profiles[index].name);
             }
-        };
+            self.profile = index;
+        }
+    }
 
-        // Direct selectors for specific cell types
-        let date_selector = Selector::parse("td.date").ok();
-        let level_selector = Selector::parse("td.level").ok();
-        let message_selector = Selector::parse("td.message").ok();
-        let stack_selector = Selector::parse("td.stack[hidden]").ok();
-        let th_selector = Selector::parse("th").ok();
-        let td_any_selector = Selector::parse("td").ok();
-
-        let th_selector = match th_selector {
-            Some(s) => s,
-            None => return Ok(Vec::new()),
-        };
+    /// A lone cell spanning the table, e.g. a log4j exception row
+    fn is_continuation_row(&self, row: &HtmlRow) -> bool {
+        self.profiles[self.profile].continuation_rows
+            && self.columns.len() > 1
+            && row.cells.iter().filter(|c| !c.is_header).count() == 1
+    }
 
-        let td_any_selector = match td_any_selector {
-            Some(s) => s,
-            None => return Ok(Vec::new()),
+    /// Convert a table row into a log entry using the active profile's cell mapping
+    fn entry_from_row(&self, row: &HtmlRow) -> Option<LogEntry> {
+        let profile = &self.profiles[self.profile];
+        let cell_text = |selector: Option<&CellSelector>| {
+            selector
+                .and_then(|s| s.find(row, &self.columns))
+                .map(|cell| cell.text.trim().to_string())
         };
 
-        let mut entries = Vec::new();
-        let mut line_number = 0;
+        let timestamp_text = cell_text(Some(&profile.timestamp)).unwrap_or_default();
 
-        for row in document.select(&row_selector) {
-            if row.select(&th_selector).next().is_some() {
-                line_number += 1;
-                continue;
-            }
+        if timestamp_text.is_empty() || timestamp_text == "Timestamp" {
+            return None;
+        }
 
-            let timestamp_text = if let Some(sel) = &date_selector {
-                row.select(sel)
-                    .next()
-                    .map(|el| el.text().collect::<String>().trim().to_string())
-                    .unwrap_or_default()
-            } else {
-                row.select(&td_any_selector)
-                    .next()
-                    .map(|el| el.text().collect::<String>().trim().to_string())
-                    .unwrap_or_default()
+        // Without a level cell, fall back to a level class on the row or
+        // inside a cell, as in <tr class="INFO"> or <pre class="ERROR">
+        let level_text = cell_text(profile.level.as_ref())
+            .filter(|level| !level.is_empty())
+            .or_else(|| {
+                row.classes
+                    .iter()
+                    .chain(row.cells.iter().flat_map(|cell| &cell.descendant_classes))
+                    .find_map(|class| level_from_class(class))
+                    .map(str::to_string)
+            })
+            .map(|level| normalize_level(&level))
+            .unwrap_or_default();
+
+        // Extract message, checking its markup for a failure anchor
+        let (message_text, has_failure_anchor) = profile
+            .message
+            .find(row, &self.columns)
+            .map(|cell| (cell.text.trim().to_string(), cell.has_failure_anchor))
+            .unwrap_or_default();
+
+        let stack_text = cell_text(profile.stack.as_ref()).unwrap_or_default();
+
+        // Split the hierarchy cell into thread, module and source line
+        let mut hierarchy = profile
+            .hierarchy
+            .as_ref()
+            .and_then(|s| s.find(row, &self.columns))
+            .map(|cell| HierarchyInfo::parse(&cell.text))
+            .unwrap_or_default();
+        if let Some(thread) = cell_text(profile.thread.as_ref()).filter(|t| !t.is_empty()) {
+            hierarchy.thread = Some(thread);
+        }
+        if let Some(module) = cell_text(profile.module.as_ref()).filter(|m| !m.is_empty()) {
+            hierarchy.module = Some(module);
+        }
+        if let Some(location) = cell_text(profile.location.as_ref()) {
+            hierarchy.source_line = location
+                .rsplit_once(':')
+                .and_then(|(_, line)| line.trim().parse().ok());
+        }
+
+        Some(LogEntry {
+            id: None,
+            test_session_id: self.test_session_id.clone(),
+            file_path: self.file_path.clone(),
+            file_index: self.file_index,
+            timestamp_ms: self.timestamps.parse_millis(&timestamp_text),
+            timestamp: timestamp_text,
+            level: level_text,
+            stack: stack_text,
+            message: message_text,
+            line_number: self.line_number,
+            thread: hierarchy.thread,
+            module: hierarchy.module,
+            source_line: hierarchy.source_line,
+            is_failure: has_failure_anchor,
+            json_payload: None,
+            created_at: Some(Utc::now()),
+        })
+    }
+}
+
+impl<R: Read> Iterator for HtmlEntryStream<R> {
+    type Item = std::io::Result<LogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let row = match self.rows.next() {
+                Some(Ok(row)) => row,
+                Some(Err(e)) => return Some(Err(e)),
+                None if self.growing => return None,
+                None => return self.pending.take().map(Ok),
             };
 
-            if timestamp_text.is_empty() || timestamp_text == "Timestamp" {
-                line_number += 1;
+            if row.has_header_cell() {
+                self.handle_header_row(&row);
+                self.line_number += 1;
                 continue;
             }
 
-            if line_number % 500 == 0 && line_number > 0 {
-                println!("  Parsed {} rows, {} entries so far", line_number, entries.len());
+            if self.is_continuation_row(&row) {
+                if let Some(pending) = self.pending.as_mut() {
+                    let text = row.cells.iter().map(|c| c.text.trim()).collect::<String>();
+                    if !pending.stack.is_empty() {
+                        pending.stack.push('\n');
+                    }
+                    pending.stack.push_str(&text);
+                }
+                self.line_number += 1;
+                continue;
             }
 
-            // Extract level from td.level and remove surrounding brackets
-            let level_text = if let Some(sel) = &level_selector {
-                row.select(sel)
-                    .next()
-                    .map(|el| el.text().collect::<String>().trim().to_string())
-                    .map(|s| s.trim_matches(&['[', ']'] as &[char]).to_string())
-                    .unwrap_or_default()
-            } else {
-                let cells: Vec<_> = row.select(&td_any_selector).collect();
-                if cells.len() > 1 {
-                    cells[1]
-                        .text()
-                        .collect::<String>()
-                        .trim()
-                        .to_string()
-                        .trim_matches(&['[', ']'] as &[char])
-                        .to_string()
-                } else {
-                    String::new()
-                }
-            };
+            let entry = self.entry_from_row(&row);
+            self.line_number += 1;
 
-            // Extract message from td.message
-            let (message_text, has_failure_anchor) = if let Some(sel) = &message_selector {
-                row.select(sel).next().map(|el| {
-                    let text = el.text().collect::<String>().trim().to_string();
-                    // Check inner HTML for failure anchor
-                    let inner_html = el.inner_html().to_string();
-                    let has_failure = inner_html.contains(r#"id="failureAnchor""#);
-                    (text, has_failure)
-                }).unwrap_or_default()
-            } else {
-                let cells: Vec<_> = row.select(&td_any_selector).collect();
-                if !cells.is_empty() {
-                    let cell = cells[cells.len() - 1];
-                    let text = cell.text().collect::<String>().trim().to_string();
-                    let inner_html = cell.inner_html().to_string();
-                    let has_failure = inner_html.contains(r#"id="failureAnchor""#);
-                    (text, has_failure)
-                } else {
-                    (String::new(), false)
+            if let Some(entry) = entry {
+                self.entry_count += 1;
+                if self.line_number.is_multiple_of(500) {
+                    println!("  Parsed {} rows, {} entries so far", self.line_number, self.entry_count);
                 }
-            };
 
-            // Append [FAIL] marker if failure anchor was detected
-            let message_text = if has_failure_anchor {
-                format!("{} [FAIL]", message_text)
+                if !self.profiles[self.profile].continuation_rows {
+                    return Some(Ok(entry));
+                }
+                if let Some(previous) = self.pending.replace(entry) {
+                    return Some(Ok(previous));
+                }
             } else {
-                message_text
-            };
+                self.skipped_rows += 1;
+            }
+        }
+    }
+}
 
-            // Try to find hidden stack trace
-            let stack_text = if let Some(sel) = &stack_selector {
-                row.select(sel)
-                    .next()
-                    .map(|el| el.text().collect::<String>().trim().to_string())
-                    .unwrap_or_default()
-            } else {
-                String::new()
-            };
+impl HtmlLogParser {
+    pub fn parse_file(
+        file_path: &str,
+        test_session_id: &str,
+        file_index: usize,
+        timestamps: &TimestampParser,
+    ) -> Result<Vec<LogEntry>, Box<dyn std::error::Error>> {
+        println!("Parsing file: {}", file_path);
 
-            let entry = LogEntry {
-                id: None,
-                test_session_id: test_session_id.to_string(),
-                file_path: file_path.to_string(),
-                file_index,
-                timestamp: timestamp_text,
-                level: level_text,
-                stack: stack_text,
-                message: message_text,
-                line_number,
-                created_at: Some(Utc::now()),
-            };
-            entries.push(entry);
+        let file = File::open(file_path)?;
+        println!("  File opened: {} bytes", file.metadata().map(|m| m.len()).unwrap_or(0));
 
-            line_number += 1;
-        }
+        let entries = Self::parse_reader(file, file_path, test_session_id, file_index, timestamps)?;
 
-        println!("Parsed {} log entries from {}", entries.len(), file_path);
+        println!("  Total parsed {} entries", entries.len());
         Ok(entries)
     }
 
@@ -242,258 +375,141 @@ impl HtmlLogParser {
         file_url: &str,
         test_session_id: &str,
         file_index: usize,
+        timestamps: &TimestampParser,
     ) -> Result<Vec<LogEntry>, Box<dyn std::error::Error>> {
         println!("Parsing HTML from URL: {} (content length: {})", file_url, html_content.len());
+        Self::parse_reader(html_content.as_bytes(), file_url, test_session_id, file_index, timestamps)
+    }
 
-        // For large files (>5MB), save to temp file and use parse_file to avoid memory issues
-        if html_content.len() > 5 * 1024 * 1024 {
-            println!("  Large file detected (>5MB), using temp file approach...");
-
-            // Create a temporary file
-            let temp_dir = std::env::temp_dir();
-            let temp_file = temp_dir.join(format!("temp_log_{}_{:x}.html",
-                std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs(),
-                std::process::id()
-            ));
-
-            println!("  Writing to temp file: {:?}", temp_file);
-            std::fs::write(&temp_file, html_content)?;
-            println!("  Temp file written successfully");
-
-            // Parse using the file-based method
-            println!("  Parsing from temp file...");
-            let result = Self::parse_file(temp_file.to_str().unwrap_or("temp.html"), test_session_id, file_index);
-
-            // Clean up temp file
-            let _ = std::fs::remove_file(&temp_file);
-            println!("  Temp file removed");
-
-            return result;
-        }
-
-        // Use catch_unwind to capture any panics during HTML parsing
-        let document = std::panic::catch_unwind(|| {
-            println!("  Starting HTML document parsing...");
-            Html::parse_document(html_content)
-        }).map_err(|e| {
-            println!("  PANIC during HTML parsing: {:?}", e);
-            format!("Panic during HTML parsing: {:?}", e)
-        })?;
-        println!("  HTML document parsed successfully");
-
-        // Select table rows
-        let row_selector = Selector::parse("table tr").ok();
-        let row_selector = match row_selector {
-            Some(s) => s,
-            None => {
-                println!("Warning: Could not parse row selector");
-                return Ok(Vec::new());
-            }
-        };
-
-        // Direct selectors for specific cell types
-        let date_selector = Selector::parse("td.date").ok();
-        let level_selector = Selector::parse("td.level").ok();
-        let message_selector = Selector::parse("td.message").ok();
-        let stack_selector = Selector::parse("td.stack[hidden]").ok();
-        let th_selector = Selector::parse("th").ok();
-        let td_any_selector = Selector::parse("td").ok();
-
-        let th_selector = match th_selector {
-            Some(s) => s,
-            None => return Ok(Vec::new()),
-        };
+    /// Parse HTML log content from any byte stream using the built-in table profiles
+    pub fn parse_reader<R: Read>(
+        reader: R,
+        file_path: &str,
+        test_session_id: &str,
+        file_index: usize,
+        timestamps: &TimestampParser,
+    ) -> Result<Vec<LogEntry>, Box<dyn std::error::Error>> {
+        Self::parse_reader_with_profiles(
+            reader,
+            file_path,
+            test_session_id,
+            file_index,
+            timestamps,
+            &HtmlTableProfile::builtin(),
+        )
+    }
 
-        let td_any_selector = match td_any_selector {
-            Some(s) => s,
-            None => return Ok(Vec::new()),
-        };
+    /// Parse HTML log content from any byte stream, collecting all entries.
+    ///
+    /// Panics raised while tokenizing are caught and reported as errors.
+    pub fn parse_reader_with_profiles<R: Read>(
+        reader: R,
+        file_path: &str,
+        test_session_id: &str,
+        file_index: usize,
+        timestamps: &TimestampParser,
+        profiles: &[HtmlTableProfile],
+    ) -> Result<Vec<LogEntry>, Box<dyn std::error::Error>> {
+        let mut stream = Self::stream_entries(reader, file_path, test_session_id, file_index, timestamps, profiles);
+        Self::collect_stream(&mut stream, file_path)
+    }
 
+    /// Drain an entry stream, reporting panics raised while tokenizing as errors
+    pub fn collect_stream<R: Read>(
+        stream: &mut HtmlEntryStream<R>,
+        file_path: &str,
+    ) -> Result<Vec<LogEntry>, Box<dyn std::error::Error>> {
         let mut entries = Vec::new();
-        let mut line_number = 0;
-
-        for row in document.select(&row_selector) {
-            if row.select(&th_selector).next().is_some() {
-                line_number += 1;
-                continue;
-            }
-
-            let timestamp_text = if let Some(sel) = &date_selector {
-                row.select(sel)
-                    .next()
-                    .map(|el| el.text().collect::<String>().trim().to_string())
-                    .unwrap_or_default()
-            } else {
-                row.select(&td_any_selector)
-                    .next()
-                    .map(|el| el.text().collect::<String>().trim().to_string())
-                    .unwrap_or_default()
-            };
-
-            if timestamp_text.is_empty() || timestamp_text == "Timestamp" {
-                line_number += 1;
-                continue;
-            }
-
-            if line_number % 500 == 0 && line_number > 0 {
-                println!("  Parsed {} rows, {} entries so far", line_number, entries.len());
-            }
-
-            // Extract level from td.level and remove surrounding brackets
-            let level_text = if let Some(sel) = &level_selector {
-                row.select(sel)
-                    .next()
-                    .map(|el| el.text().collect::<String>().trim().to_string())
-                    .map(|s| s.trim_matches(&['[', ']'] as &[char]).to_string())
-                    .unwrap_or_default()
-            } else {
-                let cells: Vec<_> = row.select(&td_any_selector).collect();
-                if cells.len() > 1 {
-                    cells[1]
-                        .text()
-                        .collect::<String>()
-                        .trim()
-                        .to_string()
-                        .trim_matches(&['[', ']'] as &[char])
-                        .to_string()
-                } else {
-                    String::new()
-                }
-            };
-
-            // Extract message from td.message
-            let (message_text, has_failure_anchor) = if let Some(sel) = &message_selector {
-                row.select(sel).next().map(|el| {
-                    let text = el.text().collect::<String>().trim().to_string();
-                    // Check inner HTML for failure anchor
-                    let inner_html = el.inner_html().to_string();
-                    let has_failure = inner_html.contains(r#"id="failureAnchor""#);
-                    (text, has_failure)
-                }).unwrap_or_default()
-            } else {
-                let cells: Vec<_> = row.select(&td_any_selector).collect();
-                if !cells.is_empty() {
-                    let cell = cells[cells.len() - 1];
-                    let text = cell.text().collect::<String>().trim().to_string();
-                    let inner_html = cell.inner_html().to_string();
-                    let has_failure = inner_html.contains(r#"id="failureAnchor""#);
-                    (text, has_failure)
-                } else {
-                    (String::new(), false)
-                }
-            };
-
-            // Append [FAIL] marker if failure anchor was detected
-            let message_text = if has_failure_anchor {
-                format!("{} [FAIL]", message_text)
-            } else {
-                message_text
-            };
-
-            // Try to find hidden stack trace
-            let stack_text = if let Some(sel) = &stack_selector {
-                row.select(sel)
-                    .next()
-                    .map(|el| el.text().collect::<String>().trim().to_string())
-                    .unwrap_or_default()
-            } else {
-                String::new()
-            };
-
-            let entry = LogEntry {
-                id: None,
-                test_session_id: test_session_id.to_string(),
-                file_path: file_url.to_string(),
-                file_index,
-                timestamp: timestamp_text,
-                level: level_text,
-                stack: stack_text,
-                message: message_text,
-                line_number,
-                created_at: Some(Utc::now()),
-            };
+        Self::drain_stream(stream, file_path, &mut |entry| {
             entries.push(entry);
-
-            line_number += 1;
-        }
-
-        println!("Parsed {} log entries from {}", entries.len(), file_url);
+            Ok(())
+        })?;
         Ok(entries)
     }
 
-    pub fn scan_html_files(
-        directory_path: &str,
-    ) -> Result<std::collections::HashMap<String, Vec<String>>, Box<dyn std::error::Error>> {
-        use std::collections::HashMap;
-
-        let mut test_groups: HashMap<String, Vec<String>> = HashMap::new();
-        let mut ignored_files = Vec::new();
-
-        for entry in WalkDir::new(directory_path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-            if path.is_file() {
-                if let Some(extension) = path.extension() {
-                    if extension == "html" {
-                        if let Some(path_str) = path.to_str() {
-                            if let Some(test_name) = Self::extract_test_name(path_str) {
-                                test_groups
-                                    .entry(test_name)
-                                    .or_default()
-                                    .push(path_str.to_string());
-                            } else {
-                                ignored_files.push(path_str.to_string());
-                            }
-                        }
-                    }
+    /// Hand every entry of a stream to `sink` and return how many there
+    /// were, reporting panics raised while tokenizing as errors
+    pub fn drain_stream<R: Read>(
+        stream: &mut HtmlEntryStream<R>,
+        file_path: &str,
+        sink: &mut EntrySink,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut count = 0;
+        let mut unparsed = 0;
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            for entry in stream {
+                let entry = entry?;
+                if entry.timestamp_ms.is_none() {
+                    unparsed += 1;
                 }
+                sink(entry)?;
+                count += 1;
             }
+            Ok::<_, Box<dyn std::error::Error>>(())
+        }))
+        .map_err(|e| {
+            let message = panic_message(&*e);
+            println!("  PANIC during HTML parsing: {}", message);
+            format!("Panic during HTML parsing: {}", message)
+        })??;
+
+        if unparsed > 0 {
+            log::warn!("{} of {} entries in {} have unparseable timestamps", unparsed, count, file_path);
         }
 
-        for (_, files) in test_groups.iter_mut() {
-            files.sort_by(|a, b| {
-                let a_index = Self::extract_file_index(a);
-                let b_index = Self::extract_file_index(b);
-                a_index.cmp(&b_index)
-            });
-        }
+        println!("Parsed {} log entries from {}", count, file_path);
+        Ok(count)
+    }
 
-        if !ignored_files.is_empty() {
-            println!("Ignored {} non-test HTML files", ignored_files.len());
-            for file in ignored_files.iter().take(5) {
-                println!("  - {}", file);
-            }
-            if ignored_files.len() > 5 {
-                println!("  - ... and {} more", ignored_files.len() - 5);
-            }
-        }
+    /// Stream log entries row by row from an HTML byte source.
+    ///
+    /// Entries are produced as soon as their `<tr>` is closed, so memory use does
+    /// not grow with the size of the document. Cells are mapped with the first
+    /// profile until a header row selects another one. An empty profile list
+    /// falls back to the built-in profiles.
+    pub fn stream_entries<R: Read>(
+        reader: R,
+        file_path: &str,
+        test_session_id: &str,
+        file_index: usize,
+        timestamps: &TimestampParser,
+        profiles: &[HtmlTableProfile],
+    ) -> HtmlEntryStream<R> {
+        let profiles = if profiles.is_empty() {
+            HtmlTableProfile::builtin()
+        } else {
+            profiles.to_vec()
+        };
 
-        println!(
-            "Found {} test groups with {} total files",
-            test_groups.len(),
-            test_groups.values().map(|v| v.len()).sum::<usize>()
-        );
-        Ok(test_groups)
+        HtmlEntryStream {
+            rows: HtmlRowReader::new(reader),
+            file_path: file_path.to_string(),
+            test_session_id: test_session_id.to_string(),
+            file_index,
+            timestamps: timestamps.clone(),
+            profiles,
+            profile: 0,
+            columns: Vec::new(),
+            pending: None,
+            line_number: 0,
+            entry_count: 0,
+            skipped_rows: 0,
+            growing: false,
+        }
     }
 
+    /// Index from the `---<index>` suffix of a log file name, 0 if absent
     pub fn extract_file_index(file_path: &str) -> usize {
         use std::path::Path;
 
-        if let Some(filename) = Path::new(file_path).file_name() {
-            if let Some(filename_str) = filename.to_str() {
-                if let Some(dash_pos) = filename_str.rfind("---") {
-                    if let Some(dot_pos) = filename_str.rfind(".html") {
-                        let index_str = &filename_str[dash_pos + 3..dot_pos];
-                        if let Ok(index) = index_str.parse::<usize>() {
-                            return index;
-                        }
-                    }
-                }
-            }
-        }
-        0
+        Path::new(file_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| {
+                let after_dash = &name[name.rfind("---")? + 3..];
+                after_dash.split('.').next()?.parse::<usize>().ok()
+            })
+            .unwrap_or(0)
     }
 
     /// Validates if a filename matches the test log pattern and extracts the test name.
@@ -525,23 +541,7 @@ impl HtmlLogParser {
             return None;
         }
 
-        let dash_pos = filename.rfind("---")?;
-        let after_dash = &filename[dash_pos + 3..];
-        let dot_pos = after_dash.find(".html")?;
-
-        let index_str = &after_dash[..dot_pos];
-        index_str.parse::<usize>().ok()?;
-
-        let test_name_part = &filename[..dash_pos];
-
-        let id_pos = test_name_part
-            .rfind("_ID_")
-            .or_else(|| test_name_part.rfind("_AXID_"))?;
-        if id_pos == 0 {
-            return None;
-        }
-
-        Some(test_name_part.to_string())
+        crate::log_formats::test_name_from_file_name(filename)
     }
 
     /// Extracts the test name from a file path.
@@ -563,3 +563,185 @@ impl HtmlLogParser {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_LOG: &str = r#"<!DOCTYPE html>
+<html><head><title>Test Log File</title></head>
+<body>
+<table id="log">
+    <tr class="HEADER"><th>Timestamp</th><th>Level</th><th>Message</th></tr>
+<tr class="INFO"><td class="date">2026/01/14 07:17:37,370 UTC</td><td class="level">[INFO]</td><td class="hierarchy">Thread: &lt;MainThread&gt;
+pytest_test_engine.pytest_test_engine -- 871</td><td class="message"><pre class="INFO">Package version info:
+atlas-framework: 7.0.378</pre></td></tr>
+<tr class="ERROR"><td class="date">2026/01/14 07:17:37,400 UTC</td><td class="level">[ERROR]</td><td class="message"><a id="failureAnchor"></a>Connection failed &amp; retried</td><td class="stack" hidden>Traceback</td></tr>
+<tr><td class="date"></td><td class="level">INFO</td></tr>
+<tr class="DEBUG"><td class="date">2026/01/14 07:17:38,000 UTC<td class="level">DEBUG<td class="message">No closing tags
+</table>
+<script>var row = "<tr><td class='date'>not a row</td></tr>";</script>
+</body></html>"#;
+
+    /// Reader that hands out one byte per call to exercise chunk boundaries
+    struct OneByteReader<'a>(&'a [u8]);
+
+    impl Read for OneByteReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = self.0[0];
+            self.0 = &self.0[1..];
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn test_parse_html_string_extracts_cells() {
+        let entries = HtmlLogParser::parse_html_string(SAMPLE_LOG, "test.html", "session", 2, &TimestampParser::default()).unwrap();
+
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(entries[0].timestamp, "2026/01/14 07:17:37,370 UTC");
+        assert_eq!(entries[0].timestamp_ms, Some(1768375057370));
+        assert_eq!(entries[0].level, "INFO");
+        assert_eq!(entries[0].message, "Package version info:\natlas-framework: 7.0.378");
+        assert_eq!(entries[0].line_number, 1);
+        assert_eq!(entries[0].file_index, 2);
+        assert_eq!(entries[0].thread.as_deref(), Some("MainThread"));
+        assert_eq!(entries[0].module.as_deref(), Some("pytest_test_engine.pytest_test_engine"));
+        assert_eq!(entries[0].source_line, Some(871));
+
+        assert_eq!(entries[1].level, "ERROR");
+        assert_eq!(entries[1].message, "Connection failed & retried");
+        assert!(entries[1].is_failure);
+        assert!(!entries[0].is_failure);
+        assert_eq!(entries[1].stack, "Traceback");
+        assert_eq!(entries[1].line_number, 2);
+        assert_eq!(entries[1].thread, None);
+
+        // The row without a timestamp is skipped but still counted
+        assert_eq!(entries[2].message, "No closing tags");
+        assert_eq!(entries[2].level, "DEBUG");
+        assert_eq!(entries[2].line_number, 4);
+    }
+
+    #[test]
+    fn test_stream_entries_across_chunk_boundaries() {
+        let content = SAMPLE_LOG.replace("retried", "retried \u{2713} \u{4e2d}\u{6587}");
+        let expected = HtmlLogParser::parse_html_string(&content, "test.html", "session", 0, &TimestampParser::default()).unwrap();
+
+        let streamed: Vec<LogEntry> =
+            HtmlLogParser::stream_entries(
+                OneByteReader(content.as_bytes()),
+                "test.html",
+                "session",
+                0,
+                &TimestampParser::default(),
+                &[],
+            )
+                .collect::<std::io::Result<_>>()
+                .unwrap();
+
+        assert_eq!(streamed.len(), expected.len());
+        for (a, b) in streamed.iter().zip(expected.iter()) {
+            assert_eq!(a.timestamp, b.timestamp);
+            assert_eq!(a.message, b.message);
+            assert_eq!(a.line_number, b.line_number);
+        }
+        assert!(streamed[1].message.contains("\u{4e2d}\u{6587}"));
+    }
+
+    #[test]
+    fn test_growing_stream_holds_back_open_row() {
+        // Written up to the middle of the fourth data row, no </table> yet
+        let head_end = SAMPLE_LOG.find("No closing tags").unwrap();
+        let partial = &SAMPLE_LOG[..head_end + 3];
+
+        let growing: Vec<LogEntry> = HtmlLogParser::stream_entries(
+            partial.as_bytes(),
+            "test.html",
+            "session",
+            0,
+            &TimestampParser::default(),
+            &[],
+        )
+        .still_growing()
+        .collect::<std::io::Result<_>>()
+        .unwrap();
+        assert_eq!(growing.len(), 2);
+        assert_eq!(growing[1].message, "Connection failed & retried");
+
+        // Read as complete, the open row counts as finished
+        let complete = HtmlLogParser::parse_html_string(partial, "test.html", "session", 0, &TimestampParser::default()).unwrap();
+        assert_eq!(complete.len(), 3);
+        assert_eq!(complete[2].message, "No");
+    }
+
+    #[test]
+    fn test_log4j_html_layout() {
+        let content = r##"<html><body>
+<table cellspacing="0" cellpadding="4" border="1" width="100%">
+<tr>
+<th>Time</th><th>Thread</th><th>Level</th><th>Category</th><th>File:Line</th><th>Message</th>
+</tr>
+<tr>
+<td>2026-01-14 07:17:37,370</td><td title="main thread">main</td><td title="Level">INFO</td><td title="com.acme.Server category">com.acme.Server</td><td>Server.java:42</td><td title="Message">Started</td>
+</tr>
+<tr>
+<td>2026-01-14 07:17:38,000</td><td title="pool-1 thread">pool-1</td><td title="Level"><font color="#993300"><strong>ERROR</strong></font></td><td title="com.acme.Db category">com.acme.Db</td><td>Db.java:7</td><td title="Message">Query failed</td>
+</tr>
+<tr><td bgcolor="#993300" colspan="6">java.sql.SQLException: timeout<br>&nbsp;&nbsp;&nbsp;&nbsp;at com.acme.Db.query(Db.java:7)</td></tr>
+</table></body></html>"##;
+
+        let entries = HtmlLogParser::parse_html_string(content, "server.html", "session", 0, &TimestampParser::default()).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].thread.as_deref(), Some("main"));
+        assert_eq!(entries[0].module.as_deref(), Some("com.acme.Server"));
+        assert_eq!(entries[0].source_line, Some(42));
+        assert_eq!(entries[0].message, "Started");
+        assert!(entries[0].timestamp_ms.is_some());
+        assert_eq!(entries[0].stack, "");
+
+        assert_eq!(entries[1].level, "ERROR");
+        assert!(entries[1].stack.starts_with("java.sql.SQLException: timeout"));
+        assert!(entries[1].stack.contains("at com.acme.Db.query"));
+    }
+
+    #[test]
+    fn test_level_falls_back_to_row_class() {
+        let content = r#"<table>
+<tr><th>Timestamp</th><th>Level</th><th>Message</th></tr>
+<tr class="WARN"><td class="date">2026/01/14 07:17:37,370</td><td class="message">disk low</td></tr>
+<tr class="odd"><td class="date">2026/01/14 07:17:37,400</td><td class="message"><pre class="FATAL">gave up</pre></td></tr>
+<tr class="odd"><td class="date">2026/01/14 07:17:37,500</td><td class="level">[Marker]</td><td class="message">step</td></tr>
+</table>"#;
+
+        let entries = HtmlLogParser::parse_html_string(content, "test.html", "session", 0, &TimestampParser::default()).unwrap();
+
+        let levels: Vec<&str> = entries.iter().map(|e| e.level.as_str()).collect();
+        assert_eq!(levels, ["WARNING", "CRITICAL", "MARKER"]);
+    }
+
+    #[test]
+    fn test_hierarchy_parse_partial() {
+        let info = HierarchyInfo::parse("Thread: <Worker-3>");
+        assert_eq!(info.thread.as_deref(), Some("Worker-3"));
+        assert_eq!(info.module, None);
+        assert_eq!(info.source_line, None);
+
+        let info = HierarchyInfo::parse("atlas_framework.ctd_testbed.base_services");
+        assert_eq!(info.thread, None);
+        assert_eq!(info.module.as_deref(), Some("atlas_framework.ctd_testbed.base_services"));
+        assert_eq!(info.source_line, None);
+    }
+
+    #[test]
+    fn test_stream_entries_rejects_invalid_utf8() {
+        let bytes = b"<table><tr><td class=\"date\">\xff\xfe</td></tr></table>".to_vec();
+        let result = HtmlLogParser::parse_reader(bytes.as_slice(), "bad.html", "session", 0, &TimestampParser::default());
+        assert!(result.is_err());
+    }
+}
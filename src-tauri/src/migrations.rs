@@ -0,0 +1,634 @@
+//! Versioned schema migrations for the log database
+//!
+//! The schema version of a database is kept in `PRAGMA user_version`: it is
+//! the number of steps in [`MIGRATIONS`] applied so far. Opening a database
+//! applies the pending steps in order, each in its own transaction together
+//! with the version bump, so an interrupted upgrade resumes at the step that
+//! failed. Before the first pending step runs, the database is copied next to
+//! itself as `<db>.v<old version>.bak`.
+//!
+//! Databases written before versioning existed report version 0 but may
+//! already contain any prefix of these changes, so the steps that replay
+//! that history check for the columns and tables they add.
+//!
+//! To change the schema, append a step. Never edit or reorder existing ones.
+//!
+//! Steps that backfill existing rows derive the new values with the same
+//! helpers imports use, so upgraded rows match freshly imported ones: the
+//! default timestamp formats, level normalization, JSON payload extraction
+//! and traceback parsing. Those helpers may learn to handle new input, but
+//! what they return for input they already handle is frozen; changing it
+//! means appending a step that rewrites the stored values. What the steps
+//! write is spelled out here against the schema of their own version, never
+//! through the current database code.
+
+use crate::json_payload::extract_json_payload;
+use crate::level::{normalize_level, severity};
+use crate::timestamp::TimestampParser;
+use crate::traceback::entry_frames;
+use rusqlite::{params, Connection, Result as SqlResult};
+
+/// One upgrade step, taking the schema from version `n` to `n + 1`
+pub struct Migration {
+    pub description: &'static str,
+    apply: fn(&Connection) -> SqlResult<()>,
+}
+
+/// All schema changes, oldest first; the position of a step is its version
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { description: "create sessions, entries and bookmarks", apply: create_base_tables },
+    Migration { description: "add session source type and incomplete flag", apply: add_session_flags },
+    Migration { description: "create per-file import reports", apply: create_import_files },
+    Migration { description: "create session attributes", apply: create_session_attributes },
+    Migration { description: "add thread, module and source line", apply: add_hierarchy_columns },
+    Migration { description: "add parsed timestamps", apply: add_timestamp_ms },
+    Migration { description: "add failure flag", apply: add_failure_flag },
+    Migration { description: "normalize levels and add severity", apply: add_severity },
+    Migration { description: "add JSON payloads", apply: add_json_payload },
+    Migration { description: "create stack frames", apply: create_stack_frames },
+    Migration { description: "create full-text index", apply: create_fts_index },
+    Migration { description: "add entry positions", apply: add_entry_positions },
+    Migration { description: "drop file paths from the full-text index", apply: drop_fts_file_path },
+];
+
+/// Schema version written by this build
+pub fn schema_version() -> i64 {
+    MIGRATIONS.len() as i64
+}
+
+pub enum MigrationError {
+    /// The database was upgraded by a newer version of the app
+    NewerSchema { found: i64, supported: i64 },
+    BackupFailed { path: String, source: rusqlite::Error },
+    StepFailed { version: i64, description: &'static str, source: rusqlite::Error },
+    Sql(rusqlite::Error),
+}
+
+impl std::fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationError::NewerSchema { found, supported } => write!(
+                f,
+                "Database schema v{} was written by a newer version of logTerminator (this version supports up to v{}); please update the app",
+                found, supported
+            ),
+            MigrationError::BackupFailed { path, source } => {
+                write!(f, "Failed to back up database to {} before upgrading: {}", path, source)
+            }
+            MigrationError::StepFailed { version, description, source } => {
+                write!(f, "Failed to upgrade database to schema v{} ({}): {}", version, description, source)
+            }
+            MigrationError::Sql(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::fmt::Debug for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sql(e)
+    }
+}
+
+/// Bring the database at `db_path` up to [`schema_version`]
+pub fn migrate(conn: &mut Connection, db_path: &str) -> Result<(), MigrationError> {
+    let current: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    let supported = schema_version();
+    if current > supported {
+        return Err(MigrationError::NewerSchema { found: current, supported });
+    }
+    if current == supported {
+        return Ok(());
+    }
+
+    let is_empty: bool = conn.query_row("SELECT NOT EXISTS (SELECT 1 FROM sqlite_master)", [], |row| row.get(0))?;
+    if !is_empty {
+        backup(conn, db_path, current)?;
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let version = index as i64 + 1;
+        let step_failed = |source| MigrationError::StepFailed { version, description: migration.description, source };
+
+        let tx = conn.transaction().map_err(step_failed)?;
+        (migration.apply)(&tx).map_err(step_failed)?;
+        tx.pragma_update(None, "user_version", version).map_err(step_failed)?;
+        tx.commit().map_err(step_failed)?;
+        log::info!("[DB] Upgraded schema to v{}: {}", version, migration.description);
+    }
+
+    Ok(())
+}
+
+/// Copy the database to `<db_path>.v<version>.bak`, replacing an older copy
+fn backup(conn: &Connection, db_path: &str, version: i64) -> Result<(), MigrationError> {
+    let path = format!("{}.v{}.bak", db_path, version);
+    // VACUUM INTO refuses to overwrite an existing file
+    let _ = std::fs::remove_file(&path);
+    conn.execute("VACUUM INTO ?", [&path])
+        .map_err(|source| MigrationError::BackupFailed { path: path.clone(), source })?;
+    log::info!("[DB] Backed up schema v{} database to {}", version, path);
+    Ok(())
+}
+
+/// Add a column unless the table already has it; returns whether it was added
+fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> SqlResult<bool> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?) WHERE name = ?)",
+        [table, column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(!exists)
+}
+
+fn has_table(conn: &Connection, name: &str) -> SqlResult<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+        [name],
+        |row| row.get(0),
+    )
+}
+
+fn create_base_tables(conn: &Connection) -> SqlResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS test_sessions (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            directory_path TEXT NOT NULL,
+            file_count INTEGER DEFAULT 0,
+            total_entries INTEGER DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            last_parsed_at DATETIME
+        );
+        CREATE TABLE IF NOT EXISTS log_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            test_session_id TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            file_index INTEGER NOT NULL,
+            timestamp TEXT NOT NULL,
+            level TEXT NOT NULL,
+            stack TEXT NOT NULL,
+            message TEXT NOT NULL,
+            line_number INTEGER NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (test_session_id) REFERENCES test_sessions(id)
+        );
+        CREATE TABLE IF NOT EXISTS bookmarks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            log_entry_id INTEGER NOT NULL,
+            title TEXT,
+            notes TEXT,
+            color TEXT DEFAULT 'yellow',
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (log_entry_id) REFERENCES log_entries(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_entries_session ON log_entries(test_session_id);
+        CREATE INDEX IF NOT EXISTS idx_entries_session_file ON log_entries(test_session_id, file_path);
+        CREATE INDEX IF NOT EXISTS idx_entries_timestamp ON log_entries(timestamp);
+        CREATE INDEX IF NOT EXISTS idx_entries_level ON log_entries(level);
+        CREATE INDEX IF NOT EXISTS idx_bookmarks_entry ON bookmarks(log_entry_id);",
+    )
+}
+
+fn add_session_flags(conn: &Connection) -> SqlResult<()> {
+    add_column(conn, "test_sessions", "source_type", "TEXT DEFAULT 'local'")?;
+    add_column(conn, "test_sessions", "incomplete", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+fn create_import_files(conn: &Connection) -> SqlResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS import_files (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            test_session_id TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            sequence_index INTEGER,
+            status TEXT NOT NULL,
+            bytes INTEGER NOT NULL,
+            rows INTEGER NOT NULL,
+            skipped_rows INTEGER NOT NULL,
+            error TEXT,
+            FOREIGN KEY (test_session_id) REFERENCES test_sessions(id)
+        )",
+        [],
+    )?;
+    for (column, definition) in [("encoding", "TEXT"), ("file_size", "INTEGER"), ("mtime_ms", "INTEGER"), ("sha256", "TEXT")] {
+        add_column(conn, "import_files", column, definition)?;
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_import_files_session ON import_files(test_session_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn create_session_attributes(conn: &Connection) -> SqlResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS session_attributes (
+            test_session_id TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            PRIMARY KEY (test_session_id, key),
+            FOREIGN KEY (test_session_id) REFERENCES test_sessions(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_session_attributes_key ON session_attributes(key, value);",
+    )
+}
+
+fn add_hierarchy_columns(conn: &Connection) -> SqlResult<()> {
+    for (column, definition) in [("thread", "TEXT"), ("module", "TEXT"), ("source_line", "INTEGER")] {
+        add_column(conn, "log_entries", column, definition)?;
+    }
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_entries_thread ON log_entries(test_session_id, thread);
+        CREATE INDEX IF NOT EXISTS idx_entries_module ON log_entries(test_session_id, module);",
+    )
+}
+
+/// Add the parsed timestamp, parsing the raw timestamp text of existing rows
+/// with the default formats
+fn add_timestamp_ms(conn: &Connection) -> SqlResult<()> {
+    if add_column(conn, "log_entries", "timestamp_ms", "INTEGER")? {
+        let parser = TimestampParser::default();
+        let mut select = conn.prepare("SELECT id, timestamp FROM log_entries")?;
+        let rows: Vec<(i64, String)> = select
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut update = conn.prepare("UPDATE log_entries SET timestamp_ms = ? WHERE id = ?")?;
+        let mut parsed = 0;
+        for (id, timestamp) in &rows {
+            if let Some(ms) = parser.parse_millis(timestamp) {
+                update.execute(params![ms, id])?;
+                parsed += 1;
+            }
+        }
+        log::info!("[DB] Backfilled timestamp_ms for {}/{} existing entries", parsed, rows.len());
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_entries_session_time ON log_entries(test_session_id, timestamp_ms, id)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Add the failure flag. Older versions marked failure anchors by appending
+/// " [FAIL]" to the message; set the flag on those rows and strip the suffix
+fn add_failure_flag(conn: &Connection) -> SqlResult<()> {
+    if add_column(conn, "log_entries", "is_failure", "INTEGER NOT NULL DEFAULT 0")? {
+        let migrated = conn.execute(
+            "UPDATE log_entries
+             SET is_failure = 1, message = substr(message, 1, length(message) - length(' [FAIL]'))
+             WHERE message LIKE '% [FAIL]'",
+            [],
+        )?;
+        log::info!("[DB] Migrated {} entries from the [FAIL] message suffix", migrated);
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_entries_failure ON log_entries(test_session_id) WHERE is_failure = 1",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Add the severity, rewriting the levels of existing rows in canonical form
+/// (`[INFO]` becomes `INFO`)
+fn add_severity(conn: &Connection) -> SqlResult<()> {
+    if add_column(conn, "log_entries", "severity", "INTEGER")? {
+        let mut select = conn.prepare("SELECT DISTINCT level FROM log_entries")?;
+        let levels: Vec<String> = select
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut update = conn.prepare("UPDATE log_entries SET level = ?, severity = ? WHERE level = ?")?;
+        for raw in &levels {
+            let level = normalize_level(raw);
+            update.execute(params![&level, severity(&level), raw])?;
+        }
+        log::info!("[DB] Normalized {} distinct levels of existing entries", levels.len());
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_entries_session_severity ON log_entries(test_session_id, severity)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Add the JSON payload, extracting it from the messages of existing rows
+fn add_json_payload(conn: &Connection) -> SqlResult<()> {
+    if add_column(conn, "log_entries", "json_payload", "TEXT")? {
+        let mut select = conn.prepare(
+            "SELECT id, message FROM log_entries WHERE message LIKE '%{%' OR message LIKE '%[%'",
+        )?;
+        let rows: Vec<(i64, String)> = select
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut update = conn.prepare("UPDATE log_entries SET json_payload = ? WHERE id = ?")?;
+        let mut extracted = 0;
+        for (id, message) in &rows {
+            if let Some(payload) = extract_json_payload(message) {
+                update.execute(params![payload, id])?;
+                extracted += 1;
+            }
+        }
+        log::info!("[DB] Backfilled json_payload for {}/{} existing entries", extracted, rows.len());
+    }
+    Ok(())
+}
+
+/// Create the stack frames table, parsing tracebacks in the stack and
+/// message of existing rows
+fn create_stack_frames(conn: &Connection) -> SqlResult<()> {
+    let existed = has_table(conn, "stack_frames")?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS stack_frames (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            log_entry_id INTEGER NOT NULL,
+            traceback_index INTEGER NOT NULL,
+            frame_index INTEGER NOT NULL,
+            file TEXT NOT NULL,
+            line INTEGER NOT NULL,
+            function TEXT,
+            code TEXT,
+            exception_type TEXT,
+            exception_message TEXT,
+            FOREIGN KEY (log_entry_id) REFERENCES log_entries(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_stack_frames_entry ON stack_frames(log_entry_id);",
+    )?;
+    if existed {
+        return Ok(());
+    }
+
+    let mut select = conn.prepare(
+        "SELECT id, stack, message FROM log_entries
+         WHERE stack LIKE '%Traceback (most recent call last):%'
+            OR message LIKE '%Traceback (most recent call last):%'",
+    )?;
+    let rows: Vec<(i64, String, String)> = select
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut insert = conn.prepare(
+        "INSERT INTO stack_frames
+         (log_entry_id, traceback_index, frame_index, file, line, function, code, exception_type, exception_message)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )?;
+    for (id, stack, message) in &rows {
+        for frame in entry_frames(stack, message) {
+            insert.execute(params![
+                id,
+                frame.traceback_index,
+                frame.frame_index,
+                &frame.file,
+                frame.line,
+                &frame.function,
+                &frame.code,
+                &frame.exception_type,
+                &frame.exception_message
+            ])?;
+        }
+    }
+    log::info!("[DB] Backfilled stack frames for {} existing entries", rows.len());
+    Ok(())
+}
+
+/// Create the full-text index over the text fields of entries, kept in sync
+/// by triggers and built from existing rows
+fn create_fts_index(conn: &Connection) -> SqlResult<()> {
+    let existed = has_table(conn, "log_entries_fts")?;
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS log_entries_fts USING fts5(
+            message, stack, thread, module, file_path,
+            content = 'log_entries', content_rowid = 'id'
+        );
+        CREATE TRIGGER IF NOT EXISTS log_entries_fts_insert AFTER INSERT ON log_entries BEGIN
+            INSERT INTO log_entries_fts (rowid, message, stack, thread, module, file_path)
+            VALUES (new.id, new.message, new.stack, new.thread, new.module, new.file_path);
+        END;
+        CREATE TRIGGER IF NOT EXISTS log_entries_fts_delete AFTER DELETE ON log_entries BEGIN
+            INSERT INTO log_entries_fts (log_entries_fts, rowid, message, stack, thread, module, file_path)
+            VALUES ('delete', old.id, old.message, old.stack, old.thread, old.module, old.file_path);
+        END;
+        CREATE TRIGGER IF NOT EXISTS log_entries_fts_update AFTER UPDATE ON log_entries BEGIN
+            INSERT INTO log_entries_fts (log_entries_fts, rowid, message, stack, thread, module, file_path)
+            VALUES ('delete', old.id, old.message, old.stack, old.thread, old.module, old.file_path);
+            INSERT INTO log_entries_fts (rowid, message, stack, thread, module, file_path)
+            VALUES (new.id, new.message, new.stack, new.thread, new.module, new.file_path);
+        END;",
+    )?;
+    if !existed {
+        conn.execute("INSERT INTO log_entries_fts (log_entries_fts) VALUES ('rebuild')", [])?;
+        log::info!("[DB] Built full-text index for existing entries");
+    }
+    Ok(())
+}
+
+/// Number the entries of each session in display order, so pages can seek
+/// to a row position instead of counting the rows before it
+fn add_entry_positions(conn: &Connection) -> SqlResult<()> {
+    add_column(conn, "log_entries", "seq", "INTEGER")?;
+    add_column(conn, "test_sessions", "entries_version", "INTEGER NOT NULL DEFAULT 0")?;
+
+    // Renumbering touches many rows; only changes to indexed text need to reach the index
+    conn.execute_batch(
+        "DROP TRIGGER IF EXISTS log_entries_fts_update;
+        CREATE TRIGGER log_entries_fts_update
+        AFTER UPDATE OF message, stack, thread, module, file_path ON log_entries BEGIN
+            INSERT INTO log_entries_fts (log_entries_fts, rowid, message, stack, thread, module, file_path)
+            VALUES ('delete', old.id, old.message, old.stack, old.thread, old.module, old.file_path);
+            INSERT INTO log_entries_fts (rowid, message, stack, thread, module, file_path)
+            VALUES (new.id, new.message, new.stack, new.thread, new.module, new.file_path);
+        END;",
+    )?;
+
+    let numbered = conn.execute(
+        "UPDATE log_entries SET seq = numbered.seq
+         FROM (SELECT id, ROW_NUMBER() OVER (PARTITION BY test_session_id ORDER BY timestamp_ms, id) - 1 AS seq
+               FROM log_entries) AS numbered
+         WHERE log_entries.id = numbered.id",
+        [],
+    )?;
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_entries_session_seq ON log_entries(test_session_id, seq);
+        CREATE INDEX IF NOT EXISTS idx_entries_unnumbered ON log_entries(test_session_id, timestamp_ms, id)
+            WHERE seq IS NULL;",
+    )?;
+    log::info!("[DB] Numbered {} existing entries", numbered);
+    Ok(())
+}
+
+/// Rebuild the full-text index over the searched fields only: the words of
+/// a file path match every entry of that file, so searches never used them
+fn drop_fts_file_path(conn: &Connection) -> SqlResult<()> {
+    conn.execute_batch(
+        "DROP TRIGGER IF EXISTS log_entries_fts_insert;
+        DROP TRIGGER IF EXISTS log_entries_fts_delete;
+        DROP TRIGGER IF EXISTS log_entries_fts_update;
+        DROP TABLE IF EXISTS log_entries_fts;
+        CREATE VIRTUAL TABLE log_entries_fts USING fts5(
+            message, stack, thread, module,
+            content = 'log_entries', content_rowid = 'id'
+        );
+        CREATE TRIGGER log_entries_fts_insert AFTER INSERT ON log_entries BEGIN
+            INSERT INTO log_entries_fts (rowid, message, stack, thread, module)
+            VALUES (new.id, new.message, new.stack, new.thread, new.module);
+        END;
+        CREATE TRIGGER log_entries_fts_delete AFTER DELETE ON log_entries BEGIN
+            INSERT INTO log_entries_fts (log_entries_fts, rowid, message, stack, thread, module)
+            VALUES ('delete', old.id, old.message, old.stack, old.thread, old.module);
+        END;
+        CREATE TRIGGER log_entries_fts_update
+        AFTER UPDATE OF message, stack, thread, module ON log_entries BEGIN
+            INSERT INTO log_entries_fts (log_entries_fts, rowid, message, stack, thread, module)
+            VALUES ('delete', old.id, old.message, old.stack, old.thread, old.module);
+            INSERT INTO log_entries_fts (rowid, message, stack, thread, module)
+            VALUES (new.id, new.message, new.stack, new.thread, new.module);
+        END;
+        INSERT INTO log_entries_fts (log_entries_fts) VALUES ('rebuild');",
+    )?;
+    log::info!("[DB] Rebuilt full-text index without file paths");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_version(conn: &Connection) -> i64 {
+        conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_fresh_database_is_current() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fresh.db");
+        let path = path.to_str().unwrap();
+        let mut conn = Connection::open(path).unwrap();
+
+        migrate(&mut conn, path).unwrap();
+        assert_eq!(user_version(&conn), schema_version());
+        assert!(has_table(&conn, "log_entries_fts").unwrap());
+        // Nothing to back up in a new database
+        assert!(!std::path::Path::new(&format!("{}.v0.bak", path)).exists());
+
+        // Opening again is a no-op
+        migrate(&mut conn, path).unwrap();
+        assert_eq!(user_version(&conn), schema_version());
+    }
+
+    #[test]
+    fn test_legacy_database_is_backed_up_and_upgraded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("legacy.db");
+        let path = path.to_str().unwrap();
+        let mut conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE test_sessions (id TEXT PRIMARY KEY, name TEXT NOT NULL, directory_path TEXT NOT NULL,
+                file_count INTEGER DEFAULT 0, total_entries INTEGER DEFAULT 0,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP, last_parsed_at DATETIME);
+            INSERT INTO test_sessions (id, name, directory_path) VALUES ('s', 'run', '/logs');",
+        )
+        .unwrap();
+
+        migrate(&mut conn, path).unwrap();
+        assert_eq!(user_version(&conn), schema_version());
+        let source_type: String = conn
+            .query_row("SELECT source_type FROM test_sessions WHERE id = 's'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(source_type, "local");
+
+        let backup = Connection::open(format!("{}.v0.bak", path)).unwrap();
+        assert_eq!(user_version(&backup), 0);
+        let sessions: i64 = backup.query_row("SELECT COUNT(*) FROM test_sessions", [], |row| row.get(0)).unwrap();
+        assert_eq!(sessions, 1);
+    }
+
+    #[test]
+    fn test_existing_entries_are_backfilled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backfill.db");
+        let path = path.to_str().unwrap();
+        let mut conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE log_entries (id INTEGER PRIMARY KEY AUTOINCREMENT, test_session_id TEXT NOT NULL,
+                file_path TEXT NOT NULL, file_index INTEGER NOT NULL, timestamp TEXT NOT NULL, level TEXT NOT NULL,
+                stack TEXT NOT NULL, message TEXT NOT NULL, line_number INTEGER NOT NULL, created_at DATETIME);",
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO log_entries (test_session_id, file_path, file_index, timestamp, level, stack, message, line_number)
+             VALUES ('s', 'run.html', 0, '2026/01/14 07:17:37,370', '[warn]', ?, 'response: {\"status\": 500}', 1)",
+            ["Traceback (most recent call last):\n  File \"/srv/app.py\", line 7, in main\n    run()\nValueError: bad"],
+        )
+        .unwrap();
+
+        // What the backfills derive is pinned here; see the module docs
+        migrate(&mut conn, path).unwrap();
+        let (timestamp_ms, level, severity, payload): (i64, String, i64, String) = conn
+            .query_row("SELECT timestamp_ms, level, severity, json_payload FROM log_entries", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap();
+        assert_eq!(timestamp_ms, 1768375057370);
+        assert_eq!((level.as_str(), severity), ("WARNING", 30));
+        assert_eq!(payload, r#"{"status":500}"#);
+        let frame: (String, i64, String) = conn
+            .query_row("SELECT file, line, exception_type FROM stack_frames", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(frame, ("/srv/app.py".to_string(), 7, "ValueError".to_string()));
+    }
+
+    #[test]
+    fn test_entries_are_numbered_per_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("positions.db");
+        let path = path.to_str().unwrap();
+        let mut conn = Connection::open(path).unwrap();
+        migrate(&mut conn, path).unwrap();
+        conn.execute_batch(
+            "INSERT INTO test_sessions (id, name, directory_path) VALUES ('a', 'a', '/logs'), ('b', 'b', '/logs');
+             INSERT INTO log_entries (test_session_id, file_path, file_index, timestamp, level, stack, message, line_number, timestamp_ms)
+             VALUES ('a', 'f', 0, '', 'INFO', '', 'a2', 1, 20), ('b', 'f', 0, '', 'INFO', '', 'b0', 1, 5),
+                    ('a', 'f', 0, '', 'INFO', '', 'a1', 2, 10), ('a', 'f', 0, '', 'INFO', '', 'a0', 3, NULL);",
+        )
+        .unwrap();
+        // Back to just before entries were numbered
+        let numbering = MIGRATIONS.iter().position(|m| m.description == "add entry positions").unwrap();
+        conn.pragma_update(None, "user_version", numbering as i64).unwrap();
+
+        migrate(&mut conn, path).unwrap();
+        let mut stmt = conn.prepare("SELECT message, seq FROM log_entries ORDER BY message").unwrap();
+        let positions: Vec<(String, i64)> =
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap().collect::<Result<_, _>>().unwrap();
+        let expected = [("a0", 0), ("a1", 1), ("a2", 2), ("b0", 0)].map(|(message, seq)| (message.to_string(), seq));
+        assert_eq!(positions, expected);
+    }
+
+    #[test]
+    fn test_newer_database_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("newer.db");
+        let path = path.to_str().unwrap();
+        let mut conn = Connection::open(path).unwrap();
+        conn.pragma_update(None, "user_version", schema_version() + 1).unwrap();
+
+        let err = migrate(&mut conn, path).unwrap_err();
+        assert!(matches!(err, MigrationError::NewerSchema { found, supported }
+            if found == schema_version() + 1 && supported == schema_version()));
+        assert!(err.to_string().contains("newer version"));
+    }
+}
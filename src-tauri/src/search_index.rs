@@ -0,0 +1,128 @@
+//! Full-text queries against the `log_entries_fts` index
+//!
+//! The index tokenizes message, stack, thread and module into words, so a
+//! search term becomes an FTS5 `MATCH` expression instead of a
+//! `LIKE '%term%'` scan.
+//!
+//! A bare term is matched as a phrase of word prefixes (`conn fail` finds
+//! "connection failed"); a term in double quotes is matched as an exact
+//! phrase. Text inside a word (`nect`) is not a prefix and is not found;
+//! regex searches still scan for it.
+//!
+//! Terms the word index cannot answer, such as punctuation-only markers or
+//! CJK text whose words are not separated by spaces, yield `None` and the
+//! search falls back to `LIKE`.
+
+/// Words of `text` as the `unicode61` tokenizer splits ASCII text
+fn words(text: &str) -> Vec<&str> {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// FTS5 expression for a search term, or `None` if it needs the `LIKE` fallback
+pub fn fts_query(term: &str) -> Option<String> {
+    let term = term.trim();
+    if !term.is_ascii() {
+        return None;
+    }
+
+    if let Some(phrase) = term.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        let words = words(phrase);
+        return (!words.is_empty()).then(|| format!("\"{}\"", words.join(" ")));
+    }
+
+    // Consecutive prefix tokens joined with `+` still form one phrase
+    let words = words(term.trim_end_matches('*'));
+    (!words.is_empty()).then(|| {
+        words
+            .iter()
+            .map(|word| format!("\"{}\" *", word))
+            .collect::<Vec<_>>()
+            .join(" + ")
+    })
+}
+
+/// FTS5 expression combining the terms of an advanced search, each joined
+/// to the previous one by its operator (`AND` or `OR`). `None` if any term
+/// needs the `LIKE` fallback.
+pub fn fts_conditions<'a>(conditions: impl IntoIterator<Item = (&'a str, &'a str)>) -> Option<String> {
+    let mut expression = String::new();
+    for (i, (term, operator)) in conditions.into_iter().enumerate() {
+        let query = fts_query(term)?;
+        if i > 0 {
+            expression.push_str(&format!(" {} ", operator));
+        }
+        expression.push_str(&query);
+    }
+    (!expression.is_empty()).then_some(expression)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("timeout").as_deref(), Some("\"timeout\" *"));
+        assert_eq!(fts_query(" conn fail* ").as_deref(), Some("\"conn\" * + \"fail\" *"));
+        assert_eq!(
+            fts_query("atlas_framework 7.0").as_deref(),
+            Some("\"atlas\" * + \"framework\" * + \"7\" * + \"0\" *")
+        );
+        assert_eq!(fts_query("\"connection failed\"").as_deref(), Some("\"connection failed\""));
+        // Quotes inside a term cannot break out of the phrase
+        assert_eq!(fts_query("a\" OR b").as_deref(), Some("\"a\" * + \"OR\" * + \"b\" *"));
+
+        assert_eq!(fts_query("###"), None);
+        assert_eq!(fts_query("设备温度"), None);
+        assert_eq!(fts_query(""), None);
+    }
+
+    #[test]
+    fn test_fts_conditions() {
+        assert_eq!(
+            fts_conditions([("error", "AND"), ("disk", "AND"), ("\"timed out\"", "OR")]).as_deref(),
+            Some("\"error\" * AND \"disk\" * OR \"timed out\"")
+        );
+        assert_eq!(fts_conditions([("error", "AND"), ("###", "OR")]), None);
+    }
+
+    #[test]
+    fn test_fts_index_skips_file_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("search.db");
+        let path = path.to_str().unwrap();
+        let mut conn = rusqlite::Connection::open(path).unwrap();
+        crate::migrations::migrate(&mut conn, path).unwrap();
+        conn.execute_batch(
+            "INSERT INTO test_sessions (id, name, directory_path) VALUES ('s', 's', '/logs');
+             INSERT INTO log_entries (test_session_id, file_path, file_index, timestamp, level, stack, message, line_number)
+             VALUES ('s', '/logs/gateway/TestA---0.html', 0, '', 'INFO', '', 'link up', 1),
+                    ('s', '/logs/gateway/TestA---0.html', 0, '', 'ERROR', 'at gateway.connect', 'link down', 2);
+             UPDATE log_entries SET thread = 'MainThread', module = 'netwatch' WHERE line_number = 1;",
+        )
+        .unwrap();
+
+        let search = |term: &str| -> Vec<String> {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT message FROM log_entries
+                     WHERE id IN (SELECT rowid FROM log_entries_fts WHERE log_entries_fts MATCH ?) ORDER BY id",
+                )
+                .unwrap();
+            stmt.query_map([fts_query(term).unwrap()], |row| row.get(0))
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+        assert_eq!(search("link"), vec!["link up", "link down"]);
+        assert_eq!(search("li do"), vec!["link down"]);
+        assert!(search("do li").is_empty());
+        assert_eq!(search("gateway"), vec!["link down"]);
+        assert_eq!(search("netwatch"), vec!["link up"]);
+        assert_eq!(search("mainthread"), vec!["link up"]);
+        assert!(search("TestA").is_empty());
+        assert!(search("logs").is_empty());
+    }
+}
@@ -0,0 +1,253 @@
+//! Run metadata extracted from a session's logs
+//!
+//! Extractors pull key/value attributes such as package versions, testbed and
+//! hostname out of the first entries of a session, the `<title>` of its first
+//! HTML file, or its directory path. Attributes are stored with the session
+//! and sessions can be filtered by them.
+
+use crate::log_parser::LogEntry;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Number of leading entries of a session that message extractors look at
+pub const ATTRIBUTE_ENTRY_LIMIT: usize = 50;
+
+/// Key/value attributes of a session, ordered by key
+pub type SessionAttributes = BTreeMap<String, String>;
+
+/// Text an extractor is matched against
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttributeSource {
+    /// Messages of the first [`ATTRIBUTE_ENTRY_LIMIT`] entries
+    Message,
+    /// `<title>` of the session's first HTML file
+    Title,
+    /// Directory path (or URL) the session was loaded from
+    Path,
+}
+
+/// An attribute extractor as stored in the settings
+///
+/// Every match of `pattern` yields attributes: a `key` and `value` capture
+/// pair gives one attribute named by the text of `key`, and any other named
+/// capture gives an attribute named after the capture.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttributeExtractor {
+    pub source: AttributeSource,
+    pub pattern: String,
+    /// Only texts matching this regex are searched, e.g. `Package version info`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub only_if: Option<String>,
+}
+
+impl AttributeExtractor {
+    /// Extractors for package versions, testbed and hostname
+    pub fn builtin() -> Vec<Self> {
+        vec![
+            Self {
+                source: AttributeSource::Message,
+                pattern: r"(?P<key>[A-Za-z][\w.-]*):\s*(?P<value>\d+(?:\.\d+)+\S*)".to_string(),
+                only_if: Some(r"(?i)version info".to_string()),
+            },
+            Self {
+                source: AttributeSource::Message,
+                pattern: r"(?i)\btestbed(?:\s+name)?\s*[:=]\s*(?P<testbed>[\w.-]+)".to_string(),
+                only_if: None,
+            },
+            Self {
+                source: AttributeSource::Message,
+                pattern: r"(?i)\bhost(?:name)?\s*[:=]\s*(?P<hostname>[\w.-]+)".to_string(),
+                only_if: None,
+            },
+        ]
+    }
+
+    /// Compile the extractor, checking it captures something to store
+    pub fn compile(&self) -> Result<CompiledExtractor, String> {
+        let pattern = Regex::new(&self.pattern)
+            .map_err(|e| format!("Invalid attribute pattern '{}': {}", self.pattern, e))?;
+        let only_if = self
+            .only_if
+            .as_deref()
+            .map(|p| Regex::new(p).map_err(|e| format!("Invalid attribute condition '{}': {}", p, e)))
+            .transpose()?;
+
+        let names: Vec<&str> = pattern.capture_names().flatten().collect();
+        if names.contains(&"key") != names.contains(&"value") {
+            return Err(format!("Attribute pattern '{}' must capture both 'key' and 'value'", self.pattern));
+        }
+        if names.is_empty() {
+            return Err(format!("Attribute pattern '{}' has no named capture", self.pattern));
+        }
+
+        Ok(CompiledExtractor {
+            source: self.source,
+            pattern,
+            only_if,
+        })
+    }
+}
+
+pub struct CompiledExtractor {
+    source: AttributeSource,
+    pattern: Regex,
+    only_if: Option<Regex>,
+}
+
+impl CompiledExtractor {
+    /// Add the attributes found in `text`; keys already present are kept
+    fn extract_into(&self, text: &str, attributes: &mut SessionAttributes) {
+        if self.only_if.as_ref().is_some_and(|cond| !cond.is_match(text)) {
+            return;
+        }
+        for caps in self.pattern.captures_iter(text) {
+            if let (Some(key), Some(value)) = (caps.name("key"), caps.name("value")) {
+                insert_attribute(attributes, key.as_str(), value.as_str());
+            }
+            for name in self.pattern.capture_names().flatten() {
+                if name == "key" || name == "value" {
+                    continue;
+                }
+                if let Some(value) = caps.name(name) {
+                    insert_attribute(attributes, name, value.as_str());
+                }
+            }
+        }
+    }
+}
+
+fn insert_attribute(attributes: &mut SessionAttributes, key: &str, value: &str) {
+    let (key, value) = (key.trim(), value.trim());
+    if !key.is_empty() && !value.is_empty() {
+        attributes.entry(key.to_string()).or_insert_with(|| value.to_string());
+    }
+}
+
+/// Compiled attribute extractors
+#[derive(Default)]
+pub struct AttributeExtractors {
+    extractors: Vec<CompiledExtractor>,
+}
+
+impl AttributeExtractors {
+    /// Compile `extractors`, skipping invalid ones with a warning; settings
+    /// are validated on save, so this only happens for hand-edited files
+    pub fn new(extractors: &[AttributeExtractor]) -> Self {
+        let extractors = extractors
+            .iter()
+            .filter_map(|extractor| match extractor.compile() {
+                Ok(compiled) => Some(compiled),
+                Err(e) => {
+                    log::warn!("Skipping attribute extractor: {}", e);
+                    None
+                }
+            })
+            .collect();
+        Self { extractors }
+    }
+
+    /// Attributes of a session loaded from `path`, whose first file has
+    /// `title` and whose entries start with `entries`.
+    ///
+    /// When several texts yield the same key, the first one wins, in the
+    /// order of the extractors and then of the entries.
+    pub fn extract(&self, path: &str, title: Option<&str>, entries: &[LogEntry]) -> SessionAttributes {
+        let mut attributes = SessionAttributes::new();
+        for extractor in &self.extractors {
+            match extractor.source {
+                AttributeSource::Message => {
+                    for entry in entries.iter().take(ATTRIBUTE_ENTRY_LIMIT) {
+                        extractor.extract_into(&entry.message, &mut attributes);
+                    }
+                }
+                AttributeSource::Title => {
+                    if let Some(title) = title {
+                        extractor.extract_into(title, &mut attributes);
+                    }
+                }
+                AttributeSource::Path => {
+                    extractor.extract_into(&path.replace('\\', "/"), &mut attributes);
+                }
+            }
+        }
+        attributes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message: &str) -> LogEntry {
+        LogEntry {
+            id: None,
+            test_session_id: "s1".to_string(),
+            file_path: "T_ID_1---0.html".to_string(),
+            file_index: 0,
+            timestamp: String::new(),
+            timestamp_ms: None,
+            level: "INFO".to_string(),
+            stack: String::new(),
+            message: message.to_string(),
+            line_number: 1,
+            thread: None,
+            module: None,
+            source_line: None,
+            is_failure: false,
+            json_payload: None,
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn test_builtin_extractors() {
+        let extractors = AttributeExtractors::new(&AttributeExtractor::builtin());
+        let entries = [
+            entry("Package version info: atlas-framework: 7.0.378 atlas-unity: 1.0.52"),
+            entry("Testbed: tb-lab-07, hostname=ci-runner-3"),
+            entry("Retry timeout: 30.5"),
+        ];
+
+        let attributes = extractors.extract("/logs/run", None, &entries);
+        assert_eq!(attributes.get("atlas-framework").map(String::as_str), Some("7.0.378"));
+        assert_eq!(attributes.get("atlas-unity").map(String::as_str), Some("1.0.52"));
+        assert_eq!(attributes.get("testbed").map(String::as_str), Some("tb-lab-07"));
+        assert_eq!(attributes.get("hostname").map(String::as_str), Some("ci-runner-3"));
+        // Numbers outside the version line are not taken for versions
+        assert!(!attributes.contains_key("timeout"));
+    }
+
+    #[test]
+    fn test_title_and_path_sources() {
+        let extractors = AttributeExtractors::new(&[
+            AttributeExtractor {
+                source: AttributeSource::Title,
+                pattern: r"on (?P<testbed>\S+)$".to_string(),
+                only_if: None,
+            },
+            AttributeExtractor {
+                source: AttributeSource::Path,
+                pattern: r"/builds/(?P<build>\d+)/".to_string(),
+                only_if: None,
+            },
+        ]);
+
+        let attributes = extractors.extract(r"D:\builds\1234\smoke", Some("Smoke run on tb-9"), &[]);
+        assert_eq!(attributes.get("testbed").map(String::as_str), Some("tb-9"));
+        assert_eq!(attributes.get("build").map(String::as_str), Some("1234"));
+    }
+
+    #[test]
+    fn test_compile_rejects_patterns_without_captures() {
+        let extractor = |pattern: &str| AttributeExtractor {
+            source: AttributeSource::Message,
+            pattern: pattern.to_string(),
+            only_if: None,
+        };
+        assert!(extractor(r"version \d+").compile().is_err());
+        assert!(extractor(r"(?P<key>\w+)=\w+").compile().is_err());
+        assert!(extractor(r"(?P<key>\w+)=(?P<value>\w+)").compile().is_ok());
+    }
+}
@@ -0,0 +1,156 @@
+use crate::grouping::GroupingRule;
+use crate::html_profile::HtmlTableProfile;
+use crate::log_formats::{FormatRegistry, LogGrammar};
+use crate::session_attributes::{AttributeExtractor, AttributeExtractors};
+use crate::storage::RetentionPolicy;
+use crate::timestamp::{TimestampParser, DEFAULT_TIMESTAMP_FORMATS};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// User-configurable application settings, persisted as JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    /// chrono format strings tried in order when parsing entry timestamps
+    pub timestamp_formats: Vec<String>,
+    /// Named regex grammars for line-oriented text logs
+    pub log_grammars: Vec<LogGrammar>,
+    /// HTML table profiles in addition to the built-in pytest and log4j ones
+    pub html_profiles: Vec<HtmlTableProfile>,
+    /// Rules grouping files into sessions, tried before the `_ID_<n>---<k>` scheme
+    pub grouping_rules: Vec<GroupingRule>,
+    /// Extractors filling session attributes from early entries, titles and paths
+    pub attribute_extractors: Vec<AttributeExtractor>,
+    /// Directory holding the workspace databases; the per-user app data
+    /// directory if unset
+    pub workspace_directory: Option<String>,
+    /// Rules for deleting old sessions, applied on request
+    pub retention: RetentionPolicy,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            timestamp_formats: DEFAULT_TIMESTAMP_FORMATS.iter().map(|f| f.to_string()).collect(),
+            log_grammars: vec![LogGrammar::builtin()],
+            html_profiles: Vec::new(),
+            grouping_rules: Vec::new(),
+            attribute_extractors: AttributeExtractor::builtin(),
+            workspace_directory: None,
+            retention: RetentionPolicy::default(),
+        }
+    }
+}
+
+impl AppSettings {
+    /// Reject settings that would break parsing before they are saved
+    pub fn validate(&self) -> Result<(), String> {
+        for format in &self.timestamp_formats {
+            TimestampParser::validate_format(format)?;
+        }
+        for (i, grammar) in self.log_grammars.iter().enumerate() {
+            grammar.compile()?;
+            if self.log_grammars[..i].iter().any(|g| g.name == grammar.name) {
+                return Err(format!("Duplicate grammar name: '{}'", grammar.name));
+            }
+        }
+        for profile in &self.html_profiles {
+            profile.validate()?;
+        }
+        for rule in &self.grouping_rules {
+            rule.compile()?;
+        }
+        for extractor in &self.attribute_extractors {
+            extractor.compile()?;
+        }
+        if let Some(dir) = &self.workspace_directory {
+            let path = Path::new(dir.trim());
+            if !dir.trim().is_empty() && (path.is_relative() || path.is_file()) {
+                return Err(format!("Workspace directory must be an absolute directory path: '{}'", dir));
+            }
+        }
+        self.retention.validate()
+    }
+
+    /// Timestamp parser built from the configured formats
+    pub fn timestamp_parser(&self) -> TimestampParser {
+        TimestampParser::new(self.timestamp_formats.clone())
+    }
+
+    /// Compiled session attribute extractors
+    pub fn attribute_extractors(&self) -> AttributeExtractors {
+        AttributeExtractors::new(&self.attribute_extractors)
+    }
+
+    /// Format registry with every saved grammar available for detection
+    pub fn format_registry(&self) -> FormatRegistry {
+        FormatRegistry::configured(&self.html_profiles, &self.log_grammars, &self.grouping_rules)
+    }
+
+    /// Format registry restricted to one saved grammar, or all of them if `None`
+    pub fn format_registry_for(&self, grammar: Option<&str>) -> Result<FormatRegistry, String> {
+        match grammar {
+            None => Ok(self.format_registry()),
+            Some(name) => {
+                let grammar = self
+                    .log_grammars
+                    .iter()
+                    .find(|g| g.name == name)
+                    .ok_or_else(|| format!("Unknown log grammar: '{}'", name))?;
+                Ok(FormatRegistry::configured(
+                    &self.html_profiles,
+                    std::slice::from_ref(grammar),
+                    &self.grouping_rules,
+                ))
+            }
+        }
+    }
+}
+
+/// Get the settings file path
+fn get_settings_file() -> Result<PathBuf, String> {
+    // Get the directory containing the executable (same as storage pattern in lib.rs)
+    let exe_path = env::current_exe()
+        .map_err(|e| format!("Failed to get exe path: {}", e))?;
+    let exe_dir = exe_path
+        .parent()
+        .ok_or_else(|| "Cannot determine executable directory".to_string())?;
+    Ok(exe_dir.join("settings.json"))
+}
+
+/// Load settings from file, falling back to defaults if missing or unreadable
+pub fn load_settings() -> AppSettings {
+    let settings_file = match get_settings_file() {
+        Ok(path) => path,
+        Err(_) => return AppSettings::default(),
+    };
+
+    if !settings_file.exists() {
+        return AppSettings::default();
+    }
+
+    fs::read_to_string(&settings_file)
+        .ok()
+        .and_then(|content| match serde_json::from_str(&content) {
+            Ok(settings) => Some(settings),
+            Err(e) => {
+                log::warn!("Ignoring invalid settings file {:?}: {}", settings_file, e);
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+/// Validate and save settings
+pub fn save_settings(settings: &AppSettings) -> Result<(), String> {
+    settings.validate()?;
+
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let settings_file = get_settings_file()?;
+
+    fs::write(&settings_file, content)
+        .map_err(|e| format!("Failed to write settings: {}", e))
+}
@@ -0,0 +1,200 @@
+//! Storage management: disk usage per session, retention rules and database maintenance
+//!
+//! Retention rules are kept in the settings and applied on request. They
+//! select sessions to delete by name and age. Sessions the user bookmarked can
+//! be kept regardless; the automatic bookmarks on markers, steps and failures
+//! don't count.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Rules selecting sessions to delete, see [`expired_sessions`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Keep only this many of the most recently imported sessions of each name
+    pub keep_last_per_name: Option<usize>,
+    /// Delete sessions last imported more than this many days ago
+    pub max_age_days: Option<u32>,
+    /// Keep sessions the user bookmarked whatever the rules above say
+    pub keep_bookmarked: bool,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            keep_last_per_name: None,
+            max_age_days: None,
+            keep_bookmarked: true,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.keep_last_per_name == Some(0) {
+            return Err("Sessions kept per name must be at least 1".to_string());
+        }
+        if self.max_age_days == Some(0) {
+            return Err("Maximum session age must be at least 1 day".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// What the retention rules look at for one session
+#[derive(Debug, Clone)]
+pub struct RetentionCandidate {
+    pub id: String,
+    pub name: String,
+    /// Days since the session was last imported; `None` if unknown
+    pub age_days: Option<f64>,
+    /// Has a bookmark other than the automatic ones
+    pub bookmarked: bool,
+}
+
+/// Ids of the sessions `policy` deletes, oldest first within each name
+pub fn expired_sessions(policy: &RetentionPolicy, candidates: &[RetentionCandidate]) -> Vec<String> {
+    let mut by_name: HashMap<&str, Vec<&RetentionCandidate>> = HashMap::new();
+    for candidate in candidates {
+        by_name.entry(candidate.name.as_str()).or_default().push(candidate);
+    }
+
+    let mut names: Vec<&str> = by_name.keys().copied().collect();
+    names.sort_unstable();
+
+    let mut expired = Vec::new();
+    for name in names {
+        let sessions = by_name.get_mut(name).expect("name taken from the map");
+        // Newest first; sessions of unknown age count as the oldest
+        sessions.sort_by(|a, b| a.age_days.unwrap_or(f64::MAX).total_cmp(&b.age_days.unwrap_or(f64::MAX)));
+
+        for (rank, session) in sessions.iter().enumerate().rev() {
+            let beyond_count = policy.keep_last_per_name.is_some_and(|keep| rank >= keep);
+            let too_old = policy
+                .max_age_days
+                .is_some_and(|days| session.age_days.is_some_and(|age| age > days as f64));
+            if (beyond_count || too_old) && !(policy.keep_bookmarked && session.bookmarked) {
+                expired.push(session.id.clone());
+            }
+        }
+    }
+    expired
+}
+
+/// Whether a session recorded with `directory_path` came from `source`: the
+/// same directory or URL, or one below it. Archive contents such as
+/// `logs.zip!/run` count as below the archive.
+pub fn is_from_source(directory_path: &str, source: &str) -> bool {
+    let source = source.trim().trim_end_matches(['/', '\\']);
+    if source.is_empty() {
+        return false;
+    }
+    match directory_path.strip_prefix(source) {
+        Some(rest) => rest.is_empty() || rest.starts_with(['/', '\\', '!']),
+        None => false,
+    }
+}
+
+/// Space taken by one session
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionUsage {
+    pub session_id: String,
+    pub name: String,
+    pub directory_path: String,
+    pub entries: usize,
+    /// Estimated share of the database file: its used pages split in
+    /// proportion to the size of each session's entry data
+    pub bytes: u64,
+}
+
+/// Space taken by the database of the open workspace
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageUsage {
+    /// Size of the database file
+    pub file_bytes: u64,
+    /// Free pages in the file, given back to the disk by compacting
+    pub free_bytes: u64,
+    /// Size of the write-ahead log next to the file
+    pub wal_bytes: u64,
+    /// Sessions, largest first
+    pub sessions: Vec<SessionUsage>,
+}
+
+/// Progress of [`DatabaseManager::run_maintenance`](crate::database::DatabaseManager::run_maintenance),
+/// sent before each step starts
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceProgress {
+    /// What is being done, e.g. `analyze log_entries` or `vacuum`
+    pub step: String,
+    pub completed_steps: usize,
+    pub total_steps: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceReport {
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    pub duration_ms: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: &str, name: &str, age_days: Option<f64>, bookmarked: bool) -> RetentionCandidate {
+        RetentionCandidate {
+            id: id.to_string(),
+            name: name.to_string(),
+            age_days,
+            bookmarked,
+        }
+    }
+
+    #[test]
+    fn test_keep_last_per_name() {
+        let candidates = [
+            candidate("a1", "TestA", Some(3.0), false),
+            candidate("a2", "TestA", Some(1.0), false),
+            candidate("a3", "TestA", Some(2.0), true),
+            candidate("a0", "TestA", None, false),
+            candidate("b1", "TestB", Some(9.0), false),
+        ];
+        let policy = RetentionPolicy { keep_last_per_name: Some(1), ..Default::default() };
+        assert_eq!(expired_sessions(&policy, &candidates), vec!["a0", "a1"]);
+
+        let policy = RetentionPolicy { keep_bookmarked: false, ..policy };
+        assert_eq!(expired_sessions(&policy, &candidates), vec!["a0", "a1", "a3"]);
+    }
+
+    #[test]
+    fn test_max_age() {
+        let candidates = [
+            candidate("old", "TestA", Some(40.5), false),
+            candidate("kept", "TestA", Some(40.5), true),
+            candidate("new", "TestA", Some(2.0), false),
+            candidate("unknown", "TestB", None, false),
+        ];
+        let policy = RetentionPolicy { max_age_days: Some(30), ..Default::default() };
+        assert_eq!(expired_sessions(&policy, &candidates), vec!["old"]);
+        assert!(expired_sessions(&RetentionPolicy::default(), &candidates).is_empty());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(RetentionPolicy::default().validate().is_ok());
+        assert!(RetentionPolicy { keep_last_per_name: Some(0), ..Default::default() }.validate().is_err());
+        assert!(RetentionPolicy { max_age_days: Some(0), ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_is_from_source() {
+        assert!(is_from_source("/logs/run1", "/logs/run1"));
+        assert!(is_from_source("/logs/run1/TestA", "/logs/run1/"));
+        assert!(is_from_source("C:\\logs\\run1\\TestA", "C:\\logs\\run1"));
+        assert!(is_from_source("/logs/run1.zip!/TestA", "/logs/run1.zip"));
+        assert!(is_from_source("http://ci/logs/42/", "http://ci/logs/42"));
+        assert!(!is_from_source("/logs/run10", "/logs/run1"));
+        assert!(!is_from_source("/logs/run1", ""));
+    }
+}
@@ -0,0 +1,155 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, NaiveDateTime};
+
+/// Formats tried when no user configuration is present.
+///
+/// Zone-less formats are interpreted as UTC. Formats containing `%z` are
+/// parsed with their explicit offset.
+///
+/// A migration parsed the timestamps of older rows with these, so formats
+/// may be added after the existing ones but not changed or reordered.
+pub const DEFAULT_TIMESTAMP_FORMATS: &[&str] = &[
+    "%Y/%m/%d %H:%M:%S,%3f",
+    "%Y-%m-%d %H:%M:%S,%3f",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f %z",
+    "%Y/%m/%d %H:%M:%S,%3f %z",
+    "%Y-%m-%d %I:%M:%S%.f %p",
+    "%m/%d/%Y %I:%M:%S %p",
+    "%d/%m/%Y %H:%M:%S",
+];
+
+/// Zone suffixes that chrono cannot parse but that are unambiguously UTC
+const UTC_SUFFIXES: &[&str] = &[" UTC", " GMT", "Z"];
+
+/// Parses log timestamps into epoch milliseconds using an ordered list of formats
+#[derive(Debug, Clone)]
+pub struct TimestampParser {
+    formats: Vec<String>,
+}
+
+impl Default for TimestampParser {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIMESTAMP_FORMATS.iter().map(|f| f.to_string()).collect())
+    }
+}
+
+impl TimestampParser {
+    /// Create a parser from chrono format strings; an empty list falls back to the defaults
+    pub fn new(formats: Vec<String>) -> Self {
+        if formats.is_empty() {
+            return Self::default();
+        }
+        Self { formats }
+    }
+
+    pub fn formats(&self) -> &[String] {
+        &self.formats
+    }
+
+    /// Check that a format string only contains specifiers chrono understands
+    pub fn validate_format(format: &str) -> Result<(), String> {
+        if format.trim().is_empty() {
+            return Err("Timestamp format cannot be empty".to_string());
+        }
+        if StrftimeItems::new(format).any(|item| item == Item::Error) {
+            return Err(format!("Invalid timestamp format: '{}'", format));
+        }
+        Ok(())
+    }
+
+    /// Parse a timestamp into milliseconds since the Unix epoch.
+    ///
+    /// RFC 3339 is always accepted; otherwise the configured formats are tried
+    /// in order. Returns `None` when nothing matches.
+    ///
+    /// # Examples
+    /// ```
+    /// use logterminator_lib::timestamp::TimestampParser;
+    /// let parser = TimestampParser::default();
+    /// assert_eq!(parser.parse_millis("2026/01/14 07:17:37,370 UTC"), Some(1768375057370));
+    /// assert_eq!(parser.parse_millis("not a time"), None);
+    /// ```
+    pub fn parse_millis(&self, text: &str) -> Option<i64> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+
+        if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+            return Some(dt.timestamp_millis());
+        }
+
+        let body = UTC_SUFFIXES
+            .iter()
+            .find_map(|suffix| text.strip_suffix(suffix))
+            .map(str::trim_end)
+            .unwrap_or(text);
+
+        for format in &self.formats {
+            if format.contains("%z") || format.contains("%:z") || format.contains("%#z") {
+                if let Ok(dt) = DateTime::parse_from_str(text, format) {
+                    return Some(dt.timestamp_millis());
+                }
+            } else if let Ok(dt) = NaiveDateTime::parse_from_str(body, format) {
+                return Some(dt.and_utc().timestamp_millis());
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_default_formats() {
+        let parser = TimestampParser::default();
+
+        let base = parser.parse_millis("2026/01/14 07:17:37,370 UTC").unwrap();
+        assert_eq!(parser.parse_millis("2026/01/14 07:17:37,370"), Some(base));
+        assert_eq!(parser.parse_millis("2026-01-14 07:17:37,370"), Some(base));
+        assert_eq!(parser.parse_millis("2026-01-14T07:17:37.370Z"), Some(base));
+        assert_eq!(parser.parse_millis("2026-01-14 07:17:37.370"), Some(base));
+        assert_eq!(parser.parse_millis("2026-01-14 07:17:37"), Some(base - 370));
+    }
+
+    #[test]
+    fn test_parse_offsets_and_12h_clock() {
+        let parser = TimestampParser::default();
+
+        let utc = parser.parse_millis("2026-01-14 07:17:37 UTC").unwrap();
+        assert_eq!(parser.parse_millis("2026-01-14 08:17:37 +0100"), Some(utc));
+        assert_eq!(parser.parse_millis("2026-01-14T09:17:37+02:00"), Some(utc));
+        assert_eq!(parser.parse_millis("2026-01-14 07:17:37 AM"), Some(utc));
+        assert_eq!(parser.parse_millis("01/14/2026 07:17:37 PM"), Some(utc + 12 * 3600 * 1000));
+    }
+
+    #[test]
+    fn test_parse_unparseable() {
+        let parser = TimestampParser::default();
+
+        assert_eq!(parser.parse_millis(""), None);
+        assert_eq!(parser.parse_millis("Timestamp"), None);
+        assert_eq!(parser.parse_millis("2026-01-14 07:17:37 PST"), None);
+    }
+
+    #[test]
+    fn test_custom_formats() {
+        let parser = TimestampParser::new(vec!["%d.%m.%Y %H:%M:%S".to_string()]);
+
+        assert!(parser.parse_millis("14.01.2026 07:17:37").is_some());
+        assert_eq!(parser.parse_millis("2026/01/14 07:17:37,370"), None);
+    }
+
+    #[test]
+    fn test_validate_format() {
+        assert!(TimestampParser::validate_format("%Y-%m-%d %H:%M:%S").is_ok());
+        assert!(TimestampParser::validate_format("%Y-%m-%d %Q").is_err());
+        assert!(TimestampParser::validate_format("  ").is_err());
+    }
+}
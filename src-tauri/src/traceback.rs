@@ -0,0 +1,186 @@
+//! Python tracebacks found in log messages and stack cells
+//!
+//! Each `Traceback (most recent call last):` block is split into its frames,
+//! and every frame carries the exception the block ended with. Chained
+//! exceptions ("During handling of the above exception...") produce one
+//! block each, in the order they appear.
+
+use regex::Regex;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+const TRACEBACK_HEADER: &str = "Traceback (most recent call last):";
+
+/// One `File "...", line N, in func` frame of a traceback
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StackFrame {
+    /// Position of the traceback within the entry's text
+    pub traceback_index: usize,
+    /// Position of the frame within its traceback, outermost call first
+    pub frame_index: usize,
+    pub file: String,
+    pub line: usize,
+    pub function: Option<String>,
+    /// Source line shown under the frame
+    pub code: Option<String>,
+    pub exception_type: Option<String>,
+    pub exception_message: Option<String>,
+}
+
+fn frame_regex() -> &'static Regex {
+    static FRAME: OnceLock<Regex> = OnceLock::new();
+    FRAME.get_or_init(|| Regex::new(r#"^File "([^"]+)", line (\d+)(?:, in (.+))?$"#).unwrap())
+}
+
+fn exception_regex() -> &'static Regex {
+    static EXCEPTION: OnceLock<Regex> = OnceLock::new();
+    EXCEPTION.get_or_init(|| Regex::new(r"^([A-Za-z_][\w.]*)(?::\s*(.*))?$").unwrap())
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Frames of every traceback in `text`, or none if it holds no traceback
+pub fn parse_tracebacks(text: &str) -> Vec<StackFrame> {
+    if !text.contains(TRACEBACK_HEADER) {
+        return Vec::new();
+    }
+
+    let mut frames = Vec::new();
+    let mut lines = text.lines().peekable();
+    let mut traceback_index = 0;
+
+    while let Some(line) = lines.next() {
+        let Some(pos) = line.find(TRACEBACK_HEADER) else {
+            continue;
+        };
+        // Text in front of the header (a log prefix) does not count as indentation
+        let header_indent = if line[..pos].trim().is_empty() { pos } else { 0 };
+
+        let first = frames.len();
+        let mut frame_indent = None;
+        let mut exception = None;
+
+        while let Some(&next) = lines.peek() {
+            let trimmed = next.trim();
+            if trimmed.is_empty() || trimmed.starts_with("[Previous line repeated") {
+                lines.next();
+                continue;
+            }
+
+            if let Some(caps) = frame_regex().captures(trimmed) {
+                frame_indent = Some(indent(next));
+                frames.push(StackFrame {
+                    traceback_index,
+                    frame_index: frames.len() - first,
+                    file: caps[1].to_string(),
+                    line: caps[2].parse().unwrap_or_default(),
+                    function: caps.get(3).map(|m| m.as_str().trim().to_string()),
+                    code: None,
+                    exception_type: None,
+                    exception_message: None,
+                });
+            } else if frame_indent.is_some_and(|frame| indent(next) > frame) && indent(next) > header_indent {
+                // Source line under a frame, or the `^^^^` markers of Python 3.11+
+                let is_marker = trimmed.chars().all(|c| matches!(c, '^' | '~' | ' '));
+                if let Some(frame) = frames[first..].last_mut().filter(|f| f.code.is_none() && !is_marker) {
+                    frame.code = Some(trimmed.to_string());
+                }
+            } else {
+                if let Some(caps) = exception_regex().captures(trimmed) {
+                    exception = Some((
+                        caps[1].to_string(),
+                        caps.get(2).map(|m| m.as_str().to_string()).filter(|m| !m.is_empty()),
+                    ));
+                    lines.next();
+                }
+                break;
+            }
+            lines.next();
+        }
+
+        if frames.len() > first {
+            if let Some((exception_type, exception_message)) = exception {
+                for frame in &mut frames[first..] {
+                    frame.exception_type = Some(exception_type.clone());
+                    frame.exception_message = exception_message.clone();
+                }
+            }
+            traceback_index += 1;
+        }
+    }
+
+    frames
+}
+
+/// Frames for a log entry: from its stack if that has a traceback, else its message.
+///
+/// Also run over existing rows by the migration that created the stack frames
+/// table, so the frames of a traceback it already parses are frozen; see
+/// the `migrations` module.
+pub fn entry_frames(stack: &str, message: &str) -> Vec<StackFrame> {
+    let frames = parse_tracebacks(stack);
+    if frames.is_empty() {
+        parse_tracebacks(message)
+    } else {
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAINED: &str = r#"Traceback (most recent call last):
+  File "/srv/app/client.py", line 42, in fetch
+    return session.get(url)
+           ^^^^^^^^^^^^^^^^
+KeyError: 'token'
+
+During handling of the above exception, another exception occurred:
+
+Traceback (most recent call last):
+  File "/srv/tests/test_api.py", line 12, in test_fetch
+    client.fetch("/status")
+  File "/srv/app/client.py", line 44, in fetch
+    raise ApiError("no token")
+app.errors.ApiError: no token"#;
+
+    #[test]
+    fn test_chained_tracebacks() {
+        let frames = parse_tracebacks(CHAINED);
+        assert_eq!(frames.len(), 3);
+
+        assert_eq!(frames[0].traceback_index, 0);
+        assert_eq!(frames[0].file, "/srv/app/client.py");
+        assert_eq!(frames[0].line, 42);
+        assert_eq!(frames[0].function.as_deref(), Some("fetch"));
+        assert_eq!(frames[0].code.as_deref(), Some("return session.get(url)"));
+        assert_eq!(frames[0].exception_type.as_deref(), Some("KeyError"));
+        assert_eq!(frames[0].exception_message.as_deref(), Some("'token'"));
+
+        assert_eq!(frames[2].traceback_index, 1);
+        assert_eq!(frames[2].frame_index, 1);
+        assert_eq!(frames[2].code.as_deref(), Some(r#"raise ApiError("no token")"#));
+        assert_eq!(frames[2].exception_type.as_deref(), Some("app.errors.ApiError"));
+        assert_eq!(frames[2].exception_message.as_deref(), Some("no token"));
+    }
+
+    #[test]
+    fn test_prefixed_header_and_bare_exception() {
+        let text = "ERROR worker crashed: Traceback (most recent call last):\n  File \"w.py\", line 3, in <module>\n    main()\nKeyboardInterrupt";
+        let frames = parse_tracebacks(text);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].function.as_deref(), Some("<module>"));
+        assert_eq!(frames[0].exception_type.as_deref(), Some("KeyboardInterrupt"));
+        assert_eq!(frames[0].exception_message, None);
+    }
+
+    #[test]
+    fn test_entry_frames_prefers_stack() {
+        assert!(entry_frames("", "plain message").is_empty());
+        assert_eq!(entry_frames("", CHAINED).len(), 3);
+        assert_eq!(entry_frames(CHAINED, "Traceback (most recent call last):\n  File \"x.py\", line 1\nE").len(), 3);
+    }
+}
@@ -0,0 +1,292 @@
+//! Bounded worker pool for parsing log files on all cores
+//!
+//! Files are parsed concurrently, but their results are read back on the
+//! calling thread one group at a time, in group order and file order, so
+//! everything that touches the database stays on a single thread and runs in
+//! a fixed order. Results stream through small per-file queues, and only a
+//! few files are started ahead of the one being read, so memory stays
+//! bounded however large the files are.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Condvar, Mutex};
+use std::thread;
+
+/// Upper bound on parser threads, regardless of core count
+pub const MAX_WORKERS: usize = 16;
+
+/// Results of one file buffered before its parser waits for them to be read
+const FILE_QUEUE_LEN: usize = 2;
+
+/// One parser thread per available core, capped at [`MAX_WORKERS`]
+pub fn default_worker_count() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_WORKERS)
+}
+
+/// Run `parse(group, file_index, path, send)` for every file of every group
+/// on up to `workers` threads, each handing its results to `send`.
+///
+/// Files are handed out in group order, at most `workers * 2` ahead of the
+/// file being read. `on_group(group, files)` runs on the calling thread
+/// strictly in group order and reads the group's files through
+/// [`GroupFiles::next_file`]; files it leaves unread are skipped. `send`
+/// blocks while the file's queue is full and returns false once its results
+/// are no longer read, after which `parse` should stop. If `on_group` fails,
+/// no further files are started and its error is returned.
+pub fn for_each_group_parallel<T, P, G>(
+    groups: &[Vec<String>],
+    workers: usize,
+    parse: P,
+    mut on_group: G,
+) -> Result<(), String>
+where
+    T: Send,
+    P: Fn(usize, usize, &str, &mut dyn FnMut(T) -> bool) + Sync,
+    G: FnMut(usize, &mut GroupFiles<T>) -> Result<(), String>,
+{
+    let jobs: Vec<(usize, usize)> = groups
+        .iter()
+        .enumerate()
+        .flat_map(|(group, files)| (0..files.len()).map(move |file| (group, file)))
+        .collect();
+    let workers = workers.clamp(1, jobs.len().max(1));
+    let lookahead = workers * 2;
+    let next_job = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    // Job of the file being read; workers wait before starting one too far past it
+    let reading = (Mutex::new(0), Condvar::new());
+
+    thread::scope(|scope| {
+        let (started_tx, started_rx) = mpsc::channel::<(usize, mpsc::Receiver<T>)>();
+
+        for _ in 0..workers {
+            let started_tx = started_tx.clone();
+            let (jobs, next_job, stop, reading, parse) = (&jobs, &next_job, &stop, &reading, &parse);
+            scope.spawn(move || loop {
+                let job = next_job.fetch_add(1, Ordering::Relaxed);
+                let Some(&(group, file)) = jobs.get(job) else {
+                    break;
+                };
+                let (lock, ready) = reading;
+                let mut read = lock.lock().unwrap_or_else(|e| e.into_inner());
+                while !stop.load(Ordering::Relaxed) && job >= *read + lookahead {
+                    read = ready.wait(read).unwrap_or_else(|e| e.into_inner());
+                }
+                drop(read);
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let (tx, rx) = mpsc::sync_channel(FILE_QUEUE_LEN);
+                if started_tx.send((job, rx)).is_err() {
+                    break;
+                }
+                parse(group, file, &groups[group][file], &mut |result| {
+                    !stop.load(Ordering::Relaxed) && tx.send(result).is_ok()
+                });
+            });
+        }
+        drop(started_tx);
+
+        let mut files = GroupFiles {
+            started: started_rx,
+            pending: HashMap::new(),
+            reading: &reading,
+            next_job: 0,
+            remaining: 0,
+        };
+        let mut outcome = Ok(());
+        for (group, group_files) in groups.iter().enumerate() {
+            files.remaining = group_files.len();
+            if let Err(e) = on_group(group, &mut files) {
+                outcome = Err(e);
+                break;
+            }
+            while let Some(unread) = files.next_file() {
+                unread.for_each(drop);
+            }
+        }
+
+        // Release workers waiting to start or send before the scope joins them.
+        // Set under the lock, so a worker between checking `stop` and waiting
+        // cannot miss the wakeup.
+        {
+            let _read = reading.0.lock().unwrap_or_else(|e| e.into_inner());
+            stop.store(true, Ordering::Relaxed);
+            reading.1.notify_all();
+        }
+        drop(files);
+        outcome
+    })
+}
+
+/// Files of the group being handed to `on_group` by [`for_each_group_parallel`]
+pub struct GroupFiles<'a, T> {
+    /// Queues of started files by job, sent by workers as they start them
+    started: mpsc::Receiver<(usize, mpsc::Receiver<T>)>,
+    pending: HashMap<usize, mpsc::Receiver<T>>,
+    reading: &'a (Mutex<usize>, Condvar),
+    next_job: usize,
+    remaining: usize,
+}
+
+impl<T> GroupFiles<'_, T> {
+    /// Results of the group's next file in file order, as they are parsed;
+    /// `None` after the last file
+    pub fn next_file(&mut self) -> Option<mpsc::IntoIter<T>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let job = self.next_job;
+        self.next_job += 1;
+        self.remaining -= 1;
+
+        let (lock, ready) = self.reading;
+        *lock.lock().unwrap_or_else(|e| e.into_inner()) = job;
+        ready.notify_all();
+
+        loop {
+            if let Some(results) = self.pending.remove(&job) {
+                return Some(results.into_iter());
+            }
+            // Workers start files in job order, so this one is on its way
+            let (started, results) = self.started.recv().ok()?;
+            self.pending.insert(started, results);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups() -> Vec<Vec<String>> {
+        vec![
+            vec!["a0".into(), "a1".into(), "a2".into()],
+            vec![],
+            vec!["c0".into()],
+            vec!["d0".into(), "d1".into()],
+        ]
+    }
+
+    #[test]
+    fn test_groups_delivered_in_order() {
+        let mut seen = Vec::new();
+        for_each_group_parallel(
+            &groups(),
+            4,
+            |group, file, path, send| {
+                // Make earlier files slower so they finish out of order
+                std::thread::sleep(std::time::Duration::from_millis((3 - file as u64) * 5));
+                for part in 0..3 {
+                    if !send(format!("{}:{}:{}:{}", group, file, path, part)) {
+                        return;
+                    }
+                }
+            },
+            |group, files| {
+                // The second file of the last group is left unread
+                let read = if group == 3 { 1 } else { usize::MAX };
+                let mut results = Vec::new();
+                for _ in 0..read {
+                    let Some(file) = files.next_file() else { break };
+                    results.push(file.collect::<Vec<_>>().join(","));
+                }
+                seen.push((group, results));
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            seen,
+            vec![
+                (
+                    0,
+                    vec![
+                        "0:0:a0:0,0:0:a0:1,0:0:a0:2".to_string(),
+                        "0:1:a1:0,0:1:a1:1,0:1:a1:2".to_string(),
+                        "0:2:a2:0,0:2:a2:1,0:2:a2:2".to_string(),
+                    ]
+                ),
+                (1, vec![]),
+                (2, vec!["2:0:c0:0,2:0:c0:1,2:0:c0:2".to_string()]),
+                (3, vec!["3:0:d0:0,3:0:d0:1,3:0:d0:2".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_files_started_ahead_are_bounded() {
+        let groups = vec![(0..20).map(|i| format!("f{}", i)).collect::<Vec<_>>()];
+        let started = AtomicUsize::new(0);
+        for_each_group_parallel(
+            &groups,
+            2,
+            |_, _, _, send| {
+                started.fetch_add(1, Ordering::SeqCst);
+                for part in 0..10 {
+                    if !send(part) {
+                        return;
+                    }
+                }
+            },
+            |_, files| {
+                let first = files.next_file().unwrap();
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                // Two workers with a lookahead of four files past the one being read
+                assert!(started.load(Ordering::SeqCst) <= 4);
+                assert_eq!(first.count(), 10);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(started.load(Ordering::SeqCst), 20);
+    }
+
+    #[test]
+    fn test_group_error_stops_processing() {
+        let mut calls = 0;
+        let result = for_each_group_parallel(
+            &groups(),
+            1,
+            |_, _, path, send| {
+                send(path.to_string());
+            },
+            |group, _| {
+                calls += 1;
+                if group == 0 {
+                    Err("disk full".to_string())
+                } else {
+                    Ok(())
+                }
+            },
+        );
+
+        assert_eq!(result, Err("disk full".to_string()));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_group_error_releases_waiting_workers() {
+        // More files than the lookahead, so workers are left waiting to start one
+        let groups = vec![(0..20).map(|i| format!("f{}", i)).collect::<Vec<_>>(), vec!["g0".into()]];
+        for _ in 0..50 {
+            let started = AtomicUsize::new(0);
+            let result = for_each_group_parallel(
+                &groups,
+                2,
+                |_, _, path, send| {
+                    started.fetch_add(1, Ordering::SeqCst);
+                    send(path.to_string());
+                },
+                |_, _: &mut GroupFiles<String>| Err("disk full".to_string()),
+            );
+            assert_eq!(result, Err("disk full".to_string()));
+            assert!(started.load(Ordering::SeqCst) <= 4);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Named workspaces, each with its own log database
+//!
+//! A workspace is a `<name>.db` file in the workspace directory. That is
+//! `workspaces/` in the per-user app data directory, unless the settings name
+//! another one. One workspace is open at a time. Its name is kept in
+//! `active_workspace.txt` in the same directory, so the next start reopens it.
+
+use crate::db_pool::{DbPool, DEFAULT_READERS};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Workspace opened when none was chosen yet
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+/// File in the workspace directory naming the active workspace
+const ACTIVE_FILE: &str = "active_workspace.txt";
+
+const MAX_NAME_LEN: usize = 64;
+
+/// An open workspace
+pub struct Workspace {
+    pub name: String,
+    pub directory: WorkspaceDirectory,
+    pub db: Arc<DbPool>,
+}
+
+/// Workspaces available in the current directory, as shown in the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceList {
+    pub active: String,
+    pub directory: String,
+    pub workspaces: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspaceDirectory {
+    root: PathBuf,
+}
+
+/// Check that a workspace name is usable as a file name
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Workspace name cannot be empty".to_string());
+    }
+    if name.trim() != name || name.starts_with('.') || name.chars().count() > MAX_NAME_LEN {
+        return Err(format!("Invalid workspace name: '{}'", name));
+    }
+    if let Some(c) = name.chars().find(|c| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ' '))) {
+        return Err(format!("Invalid character '{}' in workspace name '{}'", c, name));
+    }
+    Ok(())
+}
+
+impl WorkspaceDirectory {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The directory from the settings if set, otherwise `workspaces/` in the app data directory
+    pub fn configured(setting: Option<&str>, app_data_dir: &Path) -> Self {
+        match setting.map(str::trim).filter(|dir| !dir.is_empty()) {
+            Some(dir) => Self::new(dir),
+            None => Self::new(app_data_dir.join("workspaces")),
+        }
+    }
+
+    pub fn database_path(&self, name: &str) -> Result<PathBuf, String> {
+        validate_name(name)?;
+        Ok(self.root.join(format!("{}.db", name)))
+    }
+
+    /// Names of the workspace databases in the directory, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(&self.root)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "db"))
+            .filter_map(|path| path.file_stem()?.to_str().map(str::to_string))
+            .filter(|name| validate_name(name).is_ok())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Name of the workspace that was active last; the default if none was
+    /// recorded or its database is gone
+    pub fn active_name(&self) -> String {
+        fs::read_to_string(self.root.join(ACTIVE_FILE))
+            .ok()
+            .map(|content| content.trim().to_string())
+            .filter(|name| self.database_path(name).is_ok_and(|path| path.exists()))
+            .unwrap_or_else(|| DEFAULT_WORKSPACE.to_string())
+    }
+
+    /// Open an existing workspace, or the default one, which is created when missing
+    pub fn open(&self, name: &str) -> Result<Workspace, String> {
+        let path = self.database_path(name)?;
+        if name != DEFAULT_WORKSPACE && !path.exists() {
+            return Err(format!("Workspace not found: '{}'", name));
+        }
+        self.open_path(name, &path)
+    }
+
+    /// Create a new workspace and open it
+    pub fn create(&self, name: &str) -> Result<Workspace, String> {
+        let path = self.database_path(name)?;
+        if path.exists() {
+            return Err(format!("Workspace already exists: '{}'", name));
+        }
+        self.open_path(name, &path)
+    }
+
+    /// Open the workspace that was active last
+    pub fn open_active(&self) -> Result<Workspace, String> {
+        self.open(&self.active_name())
+    }
+
+    pub fn list(&self, active: &str) -> WorkspaceList {
+        let mut workspaces = self.names();
+        if !workspaces.iter().any(|name| name == active) {
+            workspaces.push(active.to_string());
+            workspaces.sort();
+        }
+        WorkspaceList {
+            active: active.to_string(),
+            directory: self.root.display().to_string(),
+            workspaces,
+        }
+    }
+
+    /// Copy a database from before workspaces existed into the default
+    /// workspace, unless that already exists. Returns whether it was copied.
+    pub fn adopt_legacy_database(&self, legacy: &Path) -> Result<bool, String> {
+        let target = self.database_path(DEFAULT_WORKSPACE)?;
+        if target.exists() || !legacy.is_file() {
+            return Ok(false);
+        }
+        fs::create_dir_all(&self.root)
+            .map_err(|e| format!("Failed to create workspace directory {}: {}", self.root.display(), e))?;
+
+        // VACUUM INTO copies a consistent snapshot, including pages still in the WAL
+        let conn = rusqlite::Connection::open(legacy)
+            .map_err(|e| format!("Failed to open {}: {}", legacy.display(), e))?;
+        conn.execute("VACUUM INTO ?", [target.to_string_lossy()])
+            .map_err(|e| format!("Failed to copy {} to {}: {}", legacy.display(), target.display(), e))?;
+        log::info!("[Workspace] Copied {} into the default workspace at {}", legacy.display(), target.display());
+        Ok(true)
+    }
+
+    fn open_path(&self, name: &str, path: &Path) -> Result<Workspace, String> {
+        fs::create_dir_all(&self.root)
+            .map_err(|e| format!("Failed to create workspace directory {}: {}", self.root.display(), e))?;
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| format!("Workspace path is not valid UTF-8: {}", path.display()))?;
+        let db = DbPool::open(path_str, DEFAULT_READERS)
+            .map_err(|e| format!("Failed to open workspace '{}': {}", name, e))?;
+
+        if let Err(e) = fs::write(self.root.join(ACTIVE_FILE), name) {
+            log::warn!("[Workspace] Failed to remember active workspace '{}': {}", name, e);
+        }
+        log::info!("[Workspace] Opened '{}' at {}", name, path.display());
+        Ok(Workspace {
+            name: name.to_string(),
+            directory: self.clone(),
+            db: Arc::new(db),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("default").is_ok());
+        assert!(validate_name("release 7.0.378").is_ok());
+        assert!(validate_name("回归测试").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name(" padded").is_err());
+        assert!(validate_name(".hidden").is_err());
+        assert!(validate_name("../escape").is_err());
+        assert!(validate_name("a/b").is_err());
+        assert!(validate_name("a\\b").is_err());
+    }
+
+    #[test]
+    fn test_create_switch_and_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspaces = WorkspaceDirectory::new(dir.path().join("workspaces"));
+
+        // The default workspace is created on first start
+        let default = workspaces.open_active().unwrap();
+        assert_eq!(default.name, DEFAULT_WORKSPACE);
+
+        let release = workspaces.create("release-7.0").unwrap();
+        assert_eq!(release.name, "release-7.0");
+        assert!(workspaces.create("release-7.0").is_err());
+        assert!(workspaces.open("missing").is_err());
+        assert_eq!(workspaces.names(), vec!["default", "release-7.0"]);
+
+        // The last opened workspace is reopened on the next start
+        assert_eq!(workspaces.open_active().unwrap().name, "release-7.0");
+        workspaces.open(DEFAULT_WORKSPACE).unwrap();
+        assert_eq!(workspaces.active_name(), DEFAULT_WORKSPACE);
+    }
+
+    #[test]
+    fn test_configured_directory() {
+        let data = Path::new("/data/logTerminator");
+        let default = WorkspaceDirectory::new(data.join("workspaces"));
+        assert_eq!(WorkspaceDirectory::configured(None, data), default);
+        assert_eq!(WorkspaceDirectory::configured(Some("  "), data), default);
+        assert_eq!(WorkspaceDirectory::configured(Some("/srv/logs"), data), WorkspaceDirectory::new("/srv/logs"));
+    }
+
+    #[test]
+    fn test_adopt_legacy_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join("logterminator.db");
+        rusqlite::Connection::open(&legacy)
+            .unwrap()
+            .execute_batch("CREATE TABLE marker (id INTEGER); INSERT INTO marker VALUES (1);")
+            .unwrap();
+        let workspaces = WorkspaceDirectory::new(dir.path().join("workspaces"));
+
+        assert!(workspaces.adopt_legacy_database(&legacy).unwrap());
+        let copied = rusqlite::Connection::open(workspaces.database_path(DEFAULT_WORKSPACE).unwrap()).unwrap();
+        let n: i64 = copied.query_row("SELECT COUNT(*) FROM marker", [], |row| row.get(0)).unwrap();
+        assert_eq!(n, 1);
+
+        // An existing default workspace is never overwritten
+        assert!(!workspaces.adopt_legacy_database(&legacy).unwrap());
+    }
+}
@@ -1,4 +1,6 @@
-use logterminator_lib::log_parser::{HtmlLogParser, LogEntry};
+use logterminator_lib::log_formats::FormatRegistry;
+use logterminator_lib::log_parser::HtmlLogParser;
+use logterminator_lib::timestamp::TimestampParser;
 
 #[cfg(test)]
 mod tests {
@@ -72,7 +74,6 @@ mod tests {
     #[test]
     fn test_scan_and_group_files() {
         use std::fs;
-        use std::path::PathBuf;
 
         let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
         let dir_path = temp_dir.path();
@@ -92,10 +93,11 @@ mod tests {
             fs::write(&file_path, html_content).expect("Failed to write test file");
         }
 
-        let result = HtmlLogParser::scan_html_files(dir_path.to_str().unwrap());
+        let result = FormatRegistry::default().scan_directory(dir_path.to_str().unwrap());
         assert!(result.is_ok());
 
-        let groups = result.unwrap();
+        let scanned = result.unwrap();
+        let groups = &scanned.groups;
 
         assert_eq!(groups.len(), 2);
 
@@ -117,6 +119,8 @@ mod tests {
                 assert!(!file.contains("MainRollup"));
             }
         }
+        assert_eq!(scanned.ignored.len(), 1);
+        assert!(scanned.ignored[0].path.ends_with("MainRollup.html"));
     }
 
     #[test]
@@ -146,7 +150,7 @@ mod tests {
         // Write to temp file
         std::fs::write("test_temp.html", html).expect("Failed to write test file");
 
-        let result = HtmlLogParser::parse_file("test_temp.html", "test_session", 0);
+        let result = HtmlLogParser::parse_file("test_temp.html", "test_session", 0, &TimestampParser::default());
 
         // Clean up
         let _ = std::fs::remove_file("test_temp.html");
@@ -199,7 +203,7 @@ test_module.test_function -- 123</td><td class="message"><pre class="ERROR">Conn
         let test_file = "real_test_log.html";
         std::fs::write(test_file, html_content).expect("Failed to write test file");
 
-        let result = HtmlLogParser::parse_file(test_file, "test_session", 0);
+        let result = HtmlLogParser::parse_file(test_file, "test_session", 0, &TimestampParser::default());
 
         // Clean up
         let _ = std::fs::remove_file(test_file);